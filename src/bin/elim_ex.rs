@@ -59,7 +59,15 @@ struct Edit {
 impl Board {
     pub fn from_line(line: &str) -> Board {
         let mut board = Board::default();
-        let mut line = line.chars();
+        let givens = line.split_whitespace().next().unwrap_or_default();
+        let len = givens.chars().count();
+        assert!(len == 9 * 9, "{len} fields instead of 81");
+        // region maps and the other constraints are only solved by iterative_generic
+        if let Some(part) = line.split_whitespace().nth(1) {
+            let key = part.split_once('=').map_or("regions", |(key, _)| key);
+            panic!("unsupported constraint: {key}");
+        }
+        let mut line = givens.chars();
         for i in 0..9 {
             for j in 0..9 {
                 let c = line.next().expect("line ended early");
@@ -297,4 +305,14 @@ mod test {
         // assert!(board.open.contains(&Addr::new(3, 7)));
         // assert_eq!(board.open.len(), 9 * 9);
     }
+    #[test]
+    #[should_panic(expected = "36 fields instead of 81")]
+    fn test_other_sizes() {
+        Board::from_line("51.........216......4..5.....16..25.");
+    }
+    #[test]
+    #[should_panic(expected = "unsupported constraint: regions")]
+    fn test_constraints() {
+        Board::from_line("9......8....17...9..............54.1......79....4...2......2...6..93.....8......7 111333333111222223144552263147522663147555866447558869477858669447888869777999999");
+    }
 }
@@ -0,0 +1,305 @@
+//! Branching heuristics for `solve`: which open field to branch on next and in which order its
+//! candidates are tried.
+
+use super::{Addr, Board, Field, PEERS};
+use bitset_core::BitSet;
+use std::env::args;
+
+/// How `solve` finds the open fields with the fewest candidates. `Counts` and `Bucket` keep their
+/// state up to date in every `manipulate` / `rollback`, which costs more than the scan saves on
+/// 9x9 as long as the scan stops early at a field with a single candidate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// scan the open fields, recomputing `candidates_for` for each (the original behaviour)
+    #[default]
+    Rescan,
+    /// scan the open fields using the incrementally maintained counts. Picks exactly the same
+    /// fields as `Rescan`.
+    Counts,
+    /// pick from the bucket queue without scanning. Ties are broken differently, so step counts
+    /// differ from the scanning variants and `CellPolicy` is ignored.
+    Bucket,
+}
+
+/// Which of the open fields with the fewest candidates is branched on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CellPolicy {
+    /// first one in `open` order (the original behaviour)
+    #[default]
+    FirstMin,
+    /// last one in `open` order
+    LastMin,
+    /// the one with the most open peers, first one on ties
+    Degree,
+    /// the one whose row, column or box has the fewest open fields, first one on ties
+    House,
+    /// uniformly random, drawn from the seeded rng
+    Random,
+    /// the one with the lowest field index. Unlike the others this does not depend on the order
+    /// of `open`, so the search is a function of the board's content alone. `early_exit` is
+    /// ignored.
+    LowestIndex,
+}
+
+/// In which order the candidates of the selected field are tried.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValuePolicy {
+    /// lowest number first (the original behaviour)
+    #[default]
+    Lowest,
+    /// the number that removes the fewest candidates from open peers first
+    LeastConstraining,
+    /// uniformly random, drawn from the seeded rng
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Heuristics {
+    pub selection: Selection,
+    pub cell: CellPolicy,
+    pub value: ValuePolicy,
+    /// stop scanning as soon as a field with a single candidate is found. Only the first of
+    /// several such fields can be selected then.
+    pub early_exit: bool,
+    pub seed: u64,
+}
+impl Default for Heuristics {
+    fn default() -> Self {
+        Self {
+            selection: Selection::default(),
+            cell: CellPolicy::default(),
+            value: ValuePolicy::default(),
+            early_exit: true,
+            seed: 1,
+        }
+    }
+}
+impl Heuristics {
+    /// parses `--counts`, `--bucket`, `--cell=<policy>`, `--value=<policy>`, `--no-early-exit` and
+    /// `--seed=<n>` from the command line.
+    pub fn from_args() -> Heuristics {
+        let mut heuristics = Heuristics::default();
+        for arg in args().skip(1) {
+            if arg == "--counts" {
+                heuristics.selection = Selection::Counts;
+            } else if arg == "--bucket" {
+                heuristics.selection = Selection::Bucket;
+            } else if arg == "--no-early-exit" {
+                heuristics.early_exit = false;
+            } else if let Some(cell) = arg.strip_prefix("--cell=") {
+                heuristics.cell = match cell {
+                    "first" => CellPolicy::FirstMin,
+                    "last" => CellPolicy::LastMin,
+                    "degree" => CellPolicy::Degree,
+                    "house" => CellPolicy::House,
+                    "random" => CellPolicy::Random,
+                    "index" => CellPolicy::LowestIndex,
+                    _ => panic!("unknown cell policy: {cell}"),
+                };
+            } else if let Some(value) = arg.strip_prefix("--value=") {
+                heuristics.value = match value {
+                    "lowest" => ValuePolicy::Lowest,
+                    "lcv" => ValuePolicy::LeastConstraining,
+                    "random" => ValuePolicy::Random,
+                    _ => panic!("unknown value policy: {value}"),
+                };
+            } else if let Some(seed) = arg.strip_prefix("--seed=") {
+                heuristics.seed = seed.parse().expect("bad seed");
+            }
+        }
+        heuristics
+    }
+    pub fn name(&self) -> String {
+        let mut name = match self.selection {
+            Selection::Bucket => "bucket".to_string(),
+            _ => format!("{:?}", self.cell),
+        };
+        if self.value != ValuePolicy::Lowest {
+            name += &format!("/{:?}", self.value);
+        }
+        if !self.early_exit && self.selection != Selection::Bucket {
+            name += " (no exit)";
+        }
+        if self.cell == CellPolicy::Random || self.value == ValuePolicy::Random {
+            name += &format!(" seed {}", self.seed);
+        }
+        name
+    }
+}
+
+/// xorshift64*. Plenty for tie-breaking and reproducible from the seed.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift must not start at zero
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    /// uniform in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+fn num_candidates(board: &Board, addr: &Addr) -> u32 {
+    match board.heuristics.selection {
+        Selection::Rescan => board.candidates_for(addr).count_ones(),
+        _ => board.counts[addr.index()] as u32,
+    }
+}
+
+fn degree(board: &Board, addr: &Addr) -> usize {
+    PEERS[addr.index()]
+        .iter()
+        .filter(|p| {
+            let p = Addr::from_index(**p as usize);
+            board.fields[p.y][p.x] == Field::Empty
+        })
+        .count()
+}
+
+// number of open fields in every row (0-8), column (9-17) and box (18-26)
+fn open_per_house(board: &Board) -> [u8; 27] {
+    let mut open = [0u8; 27];
+    for addr in &board.open {
+        open[addr.y] += 1;
+        open[9 + addr.x] += 1;
+        open[18 + addr.b] += 1;
+    }
+    open
+}
+
+/// index into `board.open` of the field to branch on next.
+pub fn select_open(board: &mut Board) -> usize {
+    let heuristics = board.heuristics;
+    if heuristics.selection == Selection::Bucket {
+        let field = board
+            .queue
+            .first_min()
+            .expect("no minimal candidate found. should be impossible.");
+        return board.open_pos[field];
+    }
+    if heuristics.cell == CellPolicy::FirstMin {
+        return first_min(board, heuristics.early_exit);
+    }
+    let open_per_house = match heuristics.cell {
+        CellPolicy::House => open_per_house(board),
+        _ => [0; 27],
+    };
+
+    let mut min_i = usize::MAX;
+    let mut min = u32::MAX;
+    let mut min_degree = 0;
+    let mut min_house = u8::MAX;
+    let mut ties = 0;
+    for i in 0..board.open.len() {
+        let addr = board.open[i];
+        let num = num_candidates(board, &addr);
+        let better = match heuristics.cell {
+            CellPolicy::FirstMin => num < min,
+            CellPolicy::LastMin => num <= min,
+            CellPolicy::Degree => {
+                let degree = degree(board, &addr);
+                let better = num < min || (num == min && degree > min_degree);
+                if better {
+                    min_degree = degree;
+                }
+                better
+            }
+            CellPolicy::House => {
+                let house = open_per_house[addr.y]
+                    .min(open_per_house[9 + addr.x])
+                    .min(open_per_house[18 + addr.b]);
+                let better = num < min || (num == min && house < min_house);
+                if better {
+                    min_house = house;
+                }
+                better
+            }
+            CellPolicy::LowestIndex => {
+                num < min || (num == min && addr.index() < board.open[min_i].index())
+            }
+            CellPolicy::Random => {
+                // reservoir sampling over the fields with the fewest candidates
+                if num < min {
+                    ties = 1;
+                    true
+                } else if num == min {
+                    ties += 1;
+                    board.rng.below(ties) == 0
+                } else {
+                    false
+                }
+            }
+        };
+        if better {
+            min_i = i;
+            min = num;
+        }
+        if heuristics.early_exit && heuristics.cell != CellPolicy::LowestIndex && min == 1 {
+            break;
+        }
+    }
+    if min_i == usize::MAX {
+        panic!("no minimal candidate found. should be impossible.")
+    }
+    min_i
+}
+
+// CellPolicy::FirstMin, the default, without the bookkeeping of the other policies
+fn first_min(board: &Board, early_exit: bool) -> usize {
+    match board.heuristics.selection {
+        Selection::Rescan => first_min_by(board, early_exit, |addr| {
+            board.candidates_for(addr).count_ones()
+        }),
+        _ => first_min_by(board, early_exit, |addr| board.counts[addr.index()] as u32),
+    }
+}
+fn first_min_by(board: &Board, early_exit: bool, num_candidates: impl Fn(&Addr) -> u32) -> usize {
+    let mut min_i = usize::MAX;
+    let mut min = u32::MAX;
+    for (i, addr) in board.open.iter().enumerate() {
+        let num = num_candidates(addr);
+        if num < min {
+            min_i = i;
+            min = num;
+            if early_exit && min == 1 {
+                break;
+            }
+        }
+    }
+    if min_i == usize::MAX {
+        panic!("no minimal candidate found. should be impossible.")
+    }
+    min_i
+}
+
+/// the next number to try from `candidates` of the field at `addr`, None if none is left.
+pub fn select_value(board: &mut Board, addr: &Addr, candidates: u16) -> Option<usize> {
+    if candidates == 0 {
+        return None;
+    }
+    let mut nums = (0..9).filter(|num| candidates.bit_test(*num));
+    match board.heuristics.value {
+        ValuePolicy::Lowest => Some(candidates.trailing_zeros() as usize),
+        ValuePolicy::LeastConstraining => nums.min_by_key(|num| {
+            PEERS[addr.index()]
+                .iter()
+                .map(|p| Addr::from_index(*p as usize))
+                .filter(|p| {
+                    board.fields[p.y][p.x] == Field::Empty && board.candidates_for(p).bit_test(*num)
+                })
+                .count()
+        }),
+        ValuePolicy::Random => {
+            let n = board.rng.below(candidates.count_ones() as usize);
+            nums.nth(n)
+        }
+    }
+}
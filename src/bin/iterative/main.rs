@@ -0,0 +1,1195 @@
+use bitset_core::BitSet;
+use heuristics::{select_open, select_value, CellPolicy, Heuristics, Rng, Selection, ValuePolicy};
+use parallel::{solve_parallel, Goal};
+use report::{Format, Record, Status};
+use restart::{solve_with, Strategy};
+use std::env::args;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod heuristics;
+mod parallel;
+mod report;
+mod restart;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Addr {
+    x: usize,
+    y: usize,
+    b: usize,
+}
+impl Addr {
+    pub fn new(x: usize, y: usize) -> Addr {
+        Addr {
+            x,
+            y,
+            b: (y / 3) * 3 + (x / 3),
+        }
+    }
+    pub fn from_index(i: usize) -> Addr {
+        Addr::new(i % 9, i / 9)
+    }
+    pub fn index(&self) -> usize {
+        self.y * 9 + self.x
+    }
+}
+
+const FIELD_NONE: u8 = u8::MAX;
+
+// for each field the 20 other fields sharing a row, column or box with it
+const PEERS: [[u8; 20]; 9 * 9] = gen_peers();
+
+// for each field its row, column and box, so updating peers needs no divisions
+const HOUSES: [[u8; 3]; 9 * 9] = gen_houses();
+
+const fn gen_houses() -> [[u8; 3]; 9 * 9] {
+    let mut houses = [[0u8; 3]; 9 * 9];
+    let mut f = 0;
+    while f < 9 * 9 {
+        let (x, y) = (f % 9, f / 9);
+        houses[f] = [y as u8, x as u8, ((y / 3) * 3 + x / 3) as u8];
+        f += 1;
+    }
+    houses
+}
+
+const fn gen_peers() -> [[u8; 20]; 9 * 9] {
+    let mut peers = [[0u8; 20]; 9 * 9];
+    let mut f = 0;
+    while f < 9 * 9 {
+        let (x, y) = (f % 9, f / 9);
+        let mut n = 0;
+        let mut p = 0;
+        while p < 9 * 9 {
+            let (px, py) = (p % 9, p / 9);
+            let same_box = px / 3 == x / 3 && py / 3 == y / 3;
+            if p != f && (px == x || py == y || same_box) {
+                peers[f][n] = p as u8;
+                n += 1;
+            }
+            p += 1;
+        }
+        f += 1;
+    }
+    peers
+}
+
+/// Open fields bucketed by their current number of candidates. Buckets are intrusive doubly
+/// linked lists over the field indices, so moving a field between buckets and picking a field
+/// from the lowest non-empty bucket are both O(1). Within a bucket the most recently linked field
+/// comes first.
+#[derive(Clone)]
+struct BucketQueue {
+    head: [u8; 10],
+    next: [u8; 9 * 9],
+    prev: [u8; 9 * 9],
+    bucket: [u8; 9 * 9],
+    non_empty: u16,
+    len: usize,
+}
+impl Default for BucketQueue {
+    fn default() -> Self {
+        Self {
+            head: [FIELD_NONE; 10],
+            next: [FIELD_NONE; 9 * 9],
+            prev: [FIELD_NONE; 9 * 9],
+            bucket: [FIELD_NONE; 9 * 9],
+            non_empty: 0,
+            len: 0,
+        }
+    }
+}
+impl BucketQueue {
+    fn contains(&self, field: usize) -> bool {
+        self.bucket[field] != FIELD_NONE
+    }
+    fn link(&mut self, field: usize, count: u8) {
+        assert!(!self.contains(field));
+        let head = self.head[count as usize];
+        self.next[field] = head;
+        self.prev[field] = FIELD_NONE;
+        if head != FIELD_NONE {
+            self.prev[head as usize] = field as u8;
+        }
+        self.head[count as usize] = field as u8;
+        self.bucket[field] = count;
+        self.non_empty.bit_set(count as usize);
+        self.len += 1;
+    }
+    fn unlink(&mut self, field: usize) {
+        assert!(self.contains(field));
+        let count = self.bucket[field] as usize;
+        let (prev, next) = (self.prev[field], self.next[field]);
+        if prev != FIELD_NONE {
+            self.next[prev as usize] = next;
+        } else {
+            self.head[count] = next;
+            if next == FIELD_NONE {
+                self.non_empty.bit_reset(count);
+            }
+        }
+        if next != FIELD_NONE {
+            self.prev[next as usize] = prev;
+        }
+        self.bucket[field] = FIELD_NONE;
+        self.len -= 1;
+    }
+    fn first_min(&self) -> Option<usize> {
+        if self.non_empty == 0 {
+            return None;
+        }
+        Some(self.head[self.non_empty.trailing_zeros() as usize] as usize)
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Debug, Clone)]
+enum Field {
+    #[default]
+    Empty,
+    Set(u8),
+}
+impl Field {}
+
+#[derive(Clone)]
+struct Board {
+    // open: BTreeSet<Addr>,
+    open: Vec<Addr>,
+    fields: [[Field; 9]; 9],
+    h_free: [u16; 9],
+    v_free: [u16; 9],
+    b_free: [u16; 9],
+    // popcount of `candidates_for` for every field (open or not), updated by manipulate/rollback.
+    // Only with Selection::Counts and Selection::Bucket.
+    counts: [u8; 9 * 9],
+    // position of each open field in `open`. Only with Selection::Bucket.
+    open_pos: [usize; 9 * 9],
+    // only with Selection::Bucket
+    queue: BucketQueue,
+    heuristics: Heuristics,
+    rng: Rng,
+}
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            open: (0..9)
+                .flat_map(|y| (0..9).map(move |x| Addr::new(x, y)))
+                .collect(),
+            fields: Default::default(),
+            h_free: [0b111111111; 9],
+            v_free: [0b111111111; 9],
+            b_free: [0b111111111; 9],
+            counts: [9; 9 * 9],
+            open_pos: std::array::from_fn(|i| i),
+            queue: BucketQueue::default(),
+            heuristics: Heuristics::default(),
+            rng: Rng::new(Heuristics::default().seed),
+        }
+    }
+}
+#[derive(Clone, Debug)]
+struct Edit {
+    addr: Addr,
+    num: u8,
+}
+impl Board {
+    pub fn from_line(line: &str, heuristics: Heuristics) -> Board {
+        Board::try_from_line(line, heuristics).unwrap_or_else(|err| panic!("{err}"))
+    }
+    pub fn try_from_line(line: &str, heuristics: Heuristics) -> Result<Board, String> {
+        let mut board = Board {
+            heuristics,
+            rng: Rng::new(heuristics.seed),
+            ..Default::default()
+        };
+        board.rebuild_selection();
+        let givens = line.split_whitespace().next().unwrap_or_default();
+        let len = givens.chars().count();
+        if len != 9 * 9 {
+            return Err(format!("{len} fields instead of 81"));
+        }
+        // region maps and the other constraints are only solved by iterative_generic
+        if let Some(part) = line.split_whitespace().nth(1) {
+            let key = part.split_once('=').map_or("regions", |(key, _)| key);
+            return Err(format!("unsupported constraint: {key}"));
+        }
+        let mut line = givens.chars();
+        for i in 0..9 {
+            for j in 0..9 {
+                let c = line.next().ok_or("line ended early")?;
+
+                if c.is_numeric() {
+                    let num = c.to_digit(10).ok_or(format!("bad num in input: {c}"))? as usize;
+                    if num < 1 {
+                        return Err(format!("bad num in input: {num}"));
+                    }
+                    let num = num - 1;
+
+                    let addr = Addr::new(j, i);
+                    board.remove_open_ordered(&addr);
+                    board.manipulate(&addr, num);
+                }
+            }
+        }
+        Ok(board)
+    }
+
+    /// switches to other heuristics. A different selection gets its counts and queue rebuilt
+    /// from the current board.
+    pub fn set_heuristics(&mut self, heuristics: Heuristics) {
+        let rebuild = heuristics.selection != self.heuristics.selection;
+        self.heuristics = heuristics;
+        if rebuild {
+            self.rebuild_selection();
+        }
+    }
+    // recomputes what the selection keeps up to date: the counts, and the positions and the queue
+    // of the open fields
+    fn rebuild_selection(&mut self) {
+        let selection = self.heuristics.selection;
+        if selection == Selection::Rescan {
+            return;
+        }
+        for i in 0..9 * 9 {
+            self.counts[i] = self.candidates_for(&Addr::from_index(i)).count_ones() as u8;
+        }
+        if selection == Selection::Bucket {
+            self.queue = BucketQueue::default();
+            for (i, addr) in self.open.iter().enumerate().rev() {
+                self.open_pos[addr.index()] = i;
+                self.queue.link(addr.index(), self.counts[addr.index()]);
+            }
+        }
+    }
+    fn remove_open_ordered(&mut self, addr: &Addr) {
+        // keeps the order of the remaining open fields, so scanning tie-breaks stay as they were
+        let i = self.open.iter().position(|open| open == addr).unwrap();
+        self.open.remove(i);
+        if self.heuristics.selection == Selection::Bucket {
+            for (j, open) in self.open.iter().enumerate().skip(i) {
+                self.open_pos[open.index()] = j;
+            }
+            self.queue.unlink(addr.index());
+        }
+    }
+    fn remove_open(&mut self, i: usize) -> Addr {
+        let addr = self.open.swap_remove(i);
+        if self.heuristics.selection == Selection::Bucket {
+            if let Some(moved) = self.open.get(i) {
+                self.open_pos[moved.index()] = i;
+            }
+            self.queue.unlink(addr.index());
+        }
+        addr
+    }
+    pub fn push_open(&mut self, addr: Addr) {
+        if self.heuristics.selection == Selection::Bucket {
+            self.open_pos[addr.index()] = self.open.len();
+            self.queue.link(addr.index(), self.counts[addr.index()]);
+        }
+        self.open.push(addr);
+    }
+    pub fn num_open(&self) -> usize {
+        self.open.len()
+    }
+    /// removes the open field chosen by the heuristics and returns it together with its
+    /// candidates.
+    pub fn take_best(&mut self) -> (u16, Addr) {
+        let i = select_open(self);
+        let addr = self.remove_open(i);
+        (self.candidates_for(&addr), addr)
+    }
+    fn add_count_if(&mut self, field: usize, mask: u16, delta: i8) {
+        let has = (self.candidates_for_index(field) & mask != 0) as u8;
+        self.counts[field] = if delta < 0 {
+            self.counts[field] - has
+        } else {
+            self.counts[field] + has
+        };
+    }
+    fn add_count(&mut self, field: usize, delta: i8) {
+        let count = self.counts[field].wrapping_add_signed(delta);
+        self.counts[field] = count;
+        if self.queue.contains(field) {
+            self.queue.unlink(field);
+            self.queue.link(field, count);
+        }
+    }
+    // adjust the counts of the field and its peers that have (had) `num` as candidate
+    fn update_counts(&mut self, addr: &Addr, num: usize, delta: i8) {
+        let field = addr.index();
+        match self.heuristics.selection {
+            Selection::Rescan => (),
+            Selection::Counts => {
+                // whether a peer has num is hard to predict, so without a queue to update adding
+                // 0 is cheaper than a branch
+                let mask = 1 << num;
+                for peer in PEERS[field] {
+                    self.add_count_if(peer as usize, mask, delta);
+                }
+                self.add_count_if(field, mask, delta);
+            }
+            Selection::Bucket => {
+                for peer in PEERS[field].iter().map(|p| *p as usize).chain([field]) {
+                    if self.candidates_for_index(peer).bit_test(num) {
+                        self.add_count(peer, delta);
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_h_mut(&mut self, addr: &Addr) -> &mut u16 {
+        &mut self.h_free[addr.y]
+    }
+    fn get_v_mut(&mut self, addr: &Addr) -> &mut u16 {
+        &mut self.v_free[addr.x]
+    }
+    fn get_b_mut(&mut self, addr: &Addr) -> &mut u16 {
+        &mut self.b_free[addr.b]
+    }
+    fn get_h(&self, addr: &Addr) -> u16 {
+        self.h_free[addr.y]
+    }
+    fn get_v(&self, addr: &Addr) -> u16 {
+        self.v_free[addr.x]
+    }
+    fn get_b(&self, addr: &Addr) -> u16 {
+        self.b_free[addr.b]
+    }
+    pub fn manipulate(&mut self, addr: &Addr, num: usize) -> Edit {
+        assert!(num < 9);
+
+        self.update_counts(addr, num, -1);
+        self.get_h_mut(addr).bit_reset(num);
+        self.get_v_mut(addr).bit_reset(num);
+        self.get_b_mut(addr).bit_reset(num);
+
+        let f = &mut self.fields[addr.y][addr.x];
+        assert_eq!(*f, Field::Empty);
+        *f = Field::Set(num as u8);
+        Edit {
+            addr: *addr,
+            num: num as u8,
+        }
+    }
+    pub fn rollback(&mut self, edit: Edit) {
+        self.get_h_mut(&edit.addr).bit_set(edit.num as usize);
+        self.get_v_mut(&edit.addr).bit_set(edit.num as usize);
+        self.get_b_mut(&edit.addr).bit_set(edit.num as usize);
+        self.update_counts(&edit.addr, edit.num as usize, 1);
+        let f = &mut self.fields[edit.addr.y][edit.addr.x];
+        assert_eq!(*f, Field::Set(edit.num));
+        *f = Field::Empty;
+    }
+    pub fn candidates_for(&self, addr: &Addr) -> u16 {
+        self.get_h(addr) & self.get_v(addr) & self.get_b(addr)
+    }
+    fn candidates_for_index(&self, field: usize) -> u16 {
+        let [y, x, b] = HOUSES[field];
+        self.h_free[y as usize] & self.v_free[x as usize] & self.b_free[b as usize]
+    }
+    /// the 81 fields row by row, '.' where open
+    pub fn to_line(&self) -> String {
+        self.fields
+            .iter()
+            .flatten()
+            .map(|field| match field {
+                Field::Empty => '.',
+                Field::Set(num) => (b'1' + num) as char,
+            })
+            .collect()
+    }
+    pub fn print(&self) {
+        for y in 0..9 {
+            for x in 0..9 {
+                match self.fields[y][x] {
+                    Field::Empty => print!(". "),
+                    Field::Set(num) => print!("{} ", num + 1),
+                }
+            }
+            println!();
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum IterState {
+    Initial,
+    Applied {
+        candidates: u16,
+        edit: Edit,
+        addr: Addr,
+    },
+    /// like `Applied` but without an edit to roll back first: the bottom of a search split off
+    /// another one.
+    Pending {
+        candidates: u16,
+        addr: Addr,
+    },
+}
+#[derive(Clone, Copy, Debug, Default)]
+struct Stats {
+    max_depth: usize,
+    num_steps: usize,
+    restarts: usize,
+    // index of the heuristics that solved the puzzle in portfolio mode
+    winner: Option<usize>,
+    solutions: usize,
+}
+
+/// Bounds on a search. The default is unbounded.
+#[derive(Clone, Debug, Default)]
+struct Limits {
+    max_steps: Option<usize>,
+    deadline: Option<Instant>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+impl Limits {
+    // the deadline and the cancellation token are only checked every this many steps
+    const CHECK_INTERVAL: usize = 1024;
+
+    /// parses `--max-steps=<n>` and `--timeout=<ms>` from the command line. The deadline is
+    /// relative to the time of the call.
+    fn from_args() -> Limits {
+        let arg = |prefix: &str| {
+            args().find_map(|arg| {
+                arg.strip_prefix(prefix)
+                    .map(|v| v.parse::<u64>().expect("bad limit"))
+            })
+        };
+        Limits {
+            max_steps: arg("--max-steps=").map(|steps| steps as usize),
+            deadline: arg("--timeout=").map(|ms| Instant::now() + Duration::from_millis(ms)),
+            cancel: None,
+        }
+    }
+    fn exceeded(&self, stats: &Stats) -> bool {
+        self.max_steps.is_some_and(|max| stats.num_steps >= max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+    // how many steps may run before the limits have to be checked again
+    fn steps_until_check(&self, stats: &Stats) -> usize {
+        let to_max = self
+            .max_steps
+            .map_or(usize::MAX, |max| max.saturating_sub(stats.num_steps));
+        to_max.min(Self::CHECK_INTERVAL)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SolveOutcome {
+    Solved,
+    Unsolvable,
+    /// a limit was hit. The board is back in the state it was in before the search.
+    Aborted {
+        steps: usize,
+        depth: usize,
+    },
+}
+
+/// The explicit search stack of `solve`. Keeping it outside of `run` allows pausing a search after
+/// a number of steps and continuing it later.
+#[derive(Debug)]
+struct Search {
+    stack: Vec<IterState>,
+}
+impl Default for Search {
+    fn default() -> Self {
+        Self {
+            stack: vec![IterState::Initial],
+        }
+    }
+}
+impl Search {
+    /// runs the search for at most `max_steps` steps. None if it was paused before it found a
+    /// solution.
+    fn run(&mut self, board: &mut Board, stats: &mut Stats, max_steps: usize) -> Option<bool> {
+        let stack = &mut self.stack;
+        for _ in 0..max_steps {
+            stats.max_depth = stats.max_depth.max(stack.len());
+            stats.num_steps += 1;
+            match stack.pop() {
+                Some(IterState::Initial) => {
+                    if board.num_open() == 0 {
+                        return Some(true);
+                    }
+                    let (mut min_candidates, addr) = board.take_best();
+
+                    // println!("best candidate: {:?} {}", addr, min_candidates);
+                    if let Some(test) = select_value(board, &addr, min_candidates) {
+                        // test candidate field:
+                        // 1. knock out selected bit
+                        // 2. 'recursion'
+                        min_candidates.bit_reset(test);
+                        let edit = board.manipulate(&addr, test);
+                        stack.push(IterState::Applied {
+                            candidates: min_candidates,
+                            edit,
+                            addr,
+                        });
+                        stack.push(IterState::Initial)
+                    } else {
+                        // unsolvable -> return / backtrack
+                        board.push_open(addr);
+                    }
+                }
+
+                Some(state @ (IterState::Applied { .. } | IterState::Pending { .. })) => {
+                    let (mut candidates, addr) = match state {
+                        IterState::Applied {
+                            candidates,
+                            edit,
+                            addr,
+                        } => {
+                            board.rollback(edit);
+                            (candidates, addr)
+                        }
+                        IterState::Pending { candidates, addr } => (candidates, addr),
+                        IterState::Initial => unreachable!(),
+                    };
+                    // println!("test: {} {}", test, candidates);
+                    if let Some(test) = select_value(board, &addr, candidates) {
+                        // test candidate field:
+                        // 1. knock out selected bit
+                        // 2. 'recursion'
+                        candidates.bit_reset(test);
+                        let edit = board.manipulate(&addr, test);
+                        stack.push(IterState::Applied {
+                            candidates,
+                            edit,
+                            addr,
+                        });
+                        stack.push(IterState::Initial);
+                    } else {
+                        // all candidate numbers knocked out but not solved -> return / backtrack
+                        board.push_open(addr);
+                    }
+                }
+                None => {
+                    // backtracked out of the first field: nothing left to try
+                    stack.push(IterState::Initial);
+                    return Some(false);
+                }
+            }
+        }
+        None
+    }
+    /// like `run`, but also stops when `limits` are exceeded. None if the search was paused
+    /// after `max_steps`.
+    fn run_limited(
+        &mut self,
+        board: &mut Board,
+        stats: &mut Stats,
+        max_steps: usize,
+        limits: &Limits,
+    ) -> Option<SolveOutcome> {
+        let end = stats.num_steps.saturating_add(max_steps);
+        loop {
+            if limits.exceeded(stats) {
+                return Some(SolveOutcome::Aborted {
+                    steps: stats.num_steps,
+                    depth: self.stack.len(),
+                });
+            }
+            if stats.num_steps >= end {
+                return None;
+            }
+            let steps = limits.steps_until_check(stats).min(end - stats.num_steps);
+            match self.run(board, stats, steps) {
+                Some(true) => return Some(SolveOutcome::Solved),
+                Some(false) => return Some(SolveOutcome::Unsolvable),
+                None => (),
+            }
+        }
+    }
+    /// undoes every edit of the search, leaving the board as it was before the search started.
+    fn unwind(&mut self, board: &mut Board) {
+        while let Some(state) = self.stack.pop() {
+            match state {
+                IterState::Applied { edit, addr, .. } => {
+                    board.rollback(edit);
+                    board.push_open(addr);
+                }
+                IterState::Pending { addr, .. } => board.push_open(addr),
+                IterState::Initial => (),
+            }
+        }
+        self.stack.push(IterState::Initial);
+    }
+    /// Hands the untried candidates of the bottom-most frame that has any over to a new search on
+    /// a copy of the board, which covers exactly the part of the search space this search gives
+    /// up. Also returns the depth of that frame.
+    fn split(&mut self, board: &Board) -> Option<(Board, Search, usize)> {
+        let k = self.stack.iter().position(
+            |state| matches!(state, IterState::Applied { candidates, .. } if *candidates != 0),
+        )?;
+        // take the copy back to the state it had when frame k was entered
+        let mut board = board.clone();
+        for state in self.stack[k + 1..].iter().rev() {
+            match state {
+                IterState::Applied { edit, addr, .. } => {
+                    board.rollback(edit.clone());
+                    board.push_open(*addr);
+                }
+                IterState::Pending { addr, .. } => board.push_open(*addr),
+                IterState::Initial => (),
+            }
+        }
+        let IterState::Applied {
+            candidates,
+            edit,
+            addr,
+        } = &mut self.stack[k]
+        else {
+            unreachable!()
+        };
+        board.rollback(edit.clone());
+        let search = Search {
+            stack: vec![IterState::Pending {
+                candidates: *candidates,
+                addr: *addr,
+            }],
+        };
+        *candidates = 0;
+        Some((board, search, k))
+    }
+}
+
+fn solve(board: &mut Board, stats: &mut Stats, limits: &Limits) -> SolveOutcome {
+    let mut search = Search::default();
+    let outcome = search
+        .run_limited(board, stats, usize::MAX, limits)
+        .expect("unlimited search paused");
+    if let SolveOutcome::Aborted { .. } = outcome {
+        search.unwind(board);
+    }
+    outcome
+}
+
+/// counts all solutions. The board is left as it was.
+fn count_solutions(board: &mut Board, stats: &mut Stats, limits: &Limits) -> SolveOutcome {
+    let mut search = Search::default();
+    loop {
+        match search
+            .run_limited(board, stats, usize::MAX, limits)
+            .expect("unlimited search paused")
+        {
+            // the solution's Initial state was popped, so running on backtracks out of it
+            SolveOutcome::Solved => stats.solutions += 1,
+            SolveOutcome::Unsolvable => return SolveOutcome::Unsolvable,
+            aborted @ SolveOutcome::Aborted { .. } => {
+                search.unwind(board);
+                return aborted;
+            }
+        }
+    }
+}
+
+// the configurations compared by `--compare`
+fn compare_heuristics() -> Vec<(Heuristics, Strategy)> {
+    let first = Heuristics::default();
+    let mut configs = vec![
+        first,
+        Heuristics {
+            early_exit: false,
+            ..first
+        },
+        Heuristics {
+            cell: CellPolicy::LastMin,
+            early_exit: false,
+            ..first
+        },
+        Heuristics {
+            cell: CellPolicy::Degree,
+            early_exit: false,
+            ..first
+        },
+        Heuristics {
+            cell: CellPolicy::House,
+            early_exit: false,
+            ..first
+        },
+        Heuristics {
+            value: ValuePolicy::LeastConstraining,
+            ..first
+        },
+        Heuristics {
+            selection: Selection::Bucket,
+            ..first
+        },
+    ];
+    for seed in 1..=3 {
+        configs.push(Heuristics {
+            cell: CellPolicy::Random,
+            early_exit: false,
+            seed,
+            ..first
+        });
+        configs.push(Heuristics {
+            value: ValuePolicy::Random,
+            seed,
+            ..first
+        });
+    }
+    let mut configs = configs
+        .into_iter()
+        .map(|heuristics| (heuristics, Strategy::Single))
+        .collect::<Vec<_>>();
+    for unit in [50, 500] {
+        configs.push((
+            Heuristics {
+                cell: CellPolicy::Random,
+                early_exit: false,
+                ..first
+            },
+            Strategy::Restarts { unit },
+        ));
+    }
+    configs.push((
+        first,
+        Strategy::Portfolio {
+            heuristics: restart::default_portfolio(),
+            slice: 1000,
+        },
+    ));
+    configs
+}
+
+/// solves every puzzle in `lines` with each configuration and prints one summary row per
+/// configuration.
+fn compare(lines: &[String]) {
+    println!(
+        "{:<40} {:>6} {:>12} {:>10} {:>10} {:>9} {:>8} {:>10}",
+        "heuristics", "solved", "total steps", "mean", "max", "max depth", "restarts", "time [ms]"
+    );
+    for (heuristics, strategy) in compare_heuristics() {
+        let start = Instant::now();
+        let mut solved = 0;
+        let mut total = Stats::default();
+        let mut max_steps = 0;
+        for line in lines {
+            let mut board = Board::from_line(line, heuristics);
+            let mut stats = Stats::default();
+            if solve_with(&mut board, &strategy, &Limits::default(), &mut stats)
+                == SolveOutcome::Solved
+            {
+                solved += 1;
+            }
+            total.num_steps += stats.num_steps;
+            total.restarts += stats.restarts;
+            total.max_depth = total.max_depth.max(stats.max_depth);
+            max_steps = max_steps.max(stats.num_steps);
+        }
+        println!(
+            "{:<40} {:>6} {:>12} {:>10} {:>10} {:>9} {:>8} {:>10}",
+            strategy.name(&heuristics),
+            solved,
+            total.num_steps,
+            total.num_steps / lines.len().max(1),
+            max_steps,
+            total.max_depth,
+            total.restarts,
+            start.elapsed().as_millis()
+        );
+    }
+}
+
+fn main() {
+    // other sizes, box shapes and extra houses are only solved by iterative_generic
+    let generic_only = ["--size=", "--box=", "--variant="];
+    if let Some(arg) = args().find(|arg| generic_only.iter().any(|o| arg.starts_with(o))) {
+        println!("{arg} needs iterative_generic");
+        return;
+    }
+    let mut heuristics = Heuristics::from_args();
+    let strategy = Strategy::from_args();
+    if matches!(strategy, Strategy::Restarts { .. })
+        && !args().any(|arg| arg.starts_with("--cell=") || arg.starts_with("--value="))
+    {
+        // restarting with deterministic tie-breaking would just repeat the same search
+        heuristics.cell = CellPolicy::Random;
+        heuristics.early_exit = false;
+    }
+    let count = args().any(|arg| arg == "--count");
+    let deterministic = args().any(|arg| arg == "--deterministic");
+    // `--threads=0` uses one thread per core. `--deterministic` alone implies it.
+    let mut threads = args().find_map(|arg| {
+        arg.strip_prefix("--threads=")
+            .map(|n| n.parse::<usize>().expect("bad thread count"))
+    });
+    if deterministic && threads.is_none() {
+        threads = Some(0);
+    }
+    let threads = threads.map(|n| match n {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    });
+    // the parallel search always runs the board's heuristics directly
+    let strategy = match threads {
+        Some(_) => Strategy::Single,
+        None => strategy,
+    };
+    let mut args = args();
+    if args.len() < 2 {
+        println!("missing filename");
+        return;
+    }
+
+    let filename = args.next_back().unwrap();
+
+    let file = File::open(&filename).unwrap();
+    let lines = io::BufReader::new(file).lines().map(|line| line.unwrap());
+    if std::env::args().any(|arg| arg == "--compare") {
+        compare(&lines.collect::<Vec<_>>());
+        return;
+    }
+
+    let format = Format::from_args();
+    let text = format == Format::Text;
+    if text {
+        println!("{filename}");
+    }
+    format.print_header();
+    for line in lines {
+        let start = Instant::now();
+        let mut board = match Board::try_from_line(&line[..], heuristics) {
+            Ok(board) => board,
+            Err(err) if !text => {
+                eprintln!("{line}: {err}");
+                Record {
+                    puzzle: &line,
+                    solution: None,
+                    status: Status::ParseError,
+                    max_depth: 0,
+                    num_steps: 0,
+                    time: start.elapsed(),
+                }
+                .print(format);
+                continue;
+            }
+            Err(err) => panic!("{err}"),
+        };
+        if text {
+            println!("=========================\nsolving:\n");
+            board.print();
+        }
+        let mut stats = Stats::default();
+        // each puzzle gets the full timeout
+        let limits = Limits::from_args();
+        let initial = (count && !text).then(|| board.clone());
+        let outcome = match (threads, count) {
+            (Some(threads), _) => {
+                let goal = if count { Goal::Count } else { Goal::First };
+                solve_parallel(
+                    &mut board,
+                    threads,
+                    goal,
+                    deterministic,
+                    &limits,
+                    &mut stats,
+                )
+            }
+            (None, true) => count_solutions(&mut board, &mut stats, &limits),
+            (None, false) => solve_with(&mut board, &strategy, &limits, &mut stats),
+        };
+        if !text {
+            let status = match (outcome, count, stats.solutions) {
+                (SolveOutcome::Aborted { .. }, _, _) => Status::Aborted,
+                (_, true, 0) | (SolveOutcome::Unsolvable, false, _) => Status::Unsolvable,
+                (_, true, 1) | (SolveOutcome::Solved, false, _) => Status::Solved,
+                (_, true, _) => Status::Multiple,
+            };
+            let solution = match (status, initial) {
+                // counting leaves the board as it was, so the single solution is searched again
+                (Status::Solved, Some(mut initial)) => {
+                    solve(&mut initial, &mut Stats::default(), &Limits::default());
+                    Some(initial.to_line())
+                }
+                (Status::Solved, None) => Some(board.to_line()),
+                _ => None,
+            };
+            Record {
+                puzzle: &line,
+                solution,
+                status,
+                max_depth: stats.max_depth,
+                num_steps: stats.num_steps,
+                time: start.elapsed(),
+            }
+            .print(format);
+            continue;
+        }
+        if count {
+            println!("max depth: {}, steps: {}", stats.max_depth, stats.num_steps);
+            println!("solutions: {}", stats.solutions);
+        }
+        match outcome {
+            SolveOutcome::Solved => {
+                board.print();
+                println!("max depth: {}, steps: {}", stats.max_depth, stats.num_steps);
+                match &strategy {
+                    Strategy::Single => (),
+                    Strategy::Restarts { .. } => println!("restarts: {}", stats.restarts),
+                    Strategy::Portfolio { heuristics, .. } => {
+                        let winner = &heuristics[stats.winner.unwrap()];
+                        println!("portfolio winner: {}", winner.name())
+                    }
+                }
+                println!("solved.");
+            }
+            // an exhausted count ends like an unsolvable puzzle
+            SolveOutcome::Unsolvable if count => (),
+            SolveOutcome::Unsolvable => println!("unsolvable"),
+            SolveOutcome::Aborted { steps, depth } => {
+                println!("aborted after {steps} steps at depth {depth}")
+            }
+        }
+    }
+    if text {
+        println!("end");
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_manipulate() {
+        let mut board = Board::default();
+        let a = &Addr::new(0, 0);
+        assert!(board.get_h(a).count_ones() == 9);
+        let edit = board.manipulate(a, 0);
+        assert!(board.get_h(a).count_ones() == 8);
+        assert!(board.get_h(a).bit_test(0) == false);
+        board.rollback(edit);
+        assert!(board.get_h(a).count_ones() == 9);
+
+        let a = &Addr::new(8, 8);
+        let edit = board.manipulate(a, 6);
+        assert!(board.get_h(a).count_ones() == 8);
+        assert!(board.get_h(a).bit_test(6) == false);
+        assert!(board.get_v(a).count_ones() == 8);
+        assert!(board.get_v(a).bit_test(6) == false);
+        assert!(board.get_b(a).count_ones() == 8);
+        assert!(board.get_b(a).bit_test(6) == false);
+        board.rollback(edit);
+        assert!(board.get_h(a).count_ones() == 9);
+    }
+    #[test]
+    pub fn test_board_init() {
+        let mut board = Board::default();
+        assert_eq!(board.open.len(), 9 * 9);
+        assert!(board.open.contains(&Addr::new(0, 0)));
+        assert!(board.open.contains(&Addr::new(8, 8)));
+        assert!(board.open.contains(&Addr::new(0, 8)));
+        assert!(board.open.contains(&Addr::new(8, 0)));
+        assert!(board.open.contains(&Addr::new(3, 7)));
+        // let edit = board.manipulate(Addr::new(3, 7), 7);
+        // assert!(!board.open.contains(&Addr::new(3, 7)));
+
+        // assert_eq!(board.open.len(), 9 * 9 - 1);
+        // board.rollback(edit);
+        // assert!(board.open.contains(&Addr::new(3, 7)));
+        // assert_eq!(board.open.len(), 9 * 9);
+    }
+    #[test]
+    fn test_counts() {
+        let mut board = Board::from_line(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+            Heuristics {
+                selection: Selection::Bucket,
+                ..Default::default()
+            },
+        );
+        let check = |board: &Board| {
+            for i in 0..9 * 9 {
+                let addr = Addr::from_index(i);
+                assert_eq!(
+                    board.counts[i] as u32,
+                    board.candidates_for(&addr).count_ones()
+                );
+                if board.open.contains(&addr) {
+                    assert_eq!(board.queue.bucket[i], board.counts[i]);
+                    assert_eq!(board.open[board.open_pos[i]], addr);
+                } else {
+                    assert!(!board.queue.contains(i));
+                }
+            }
+            assert_eq!(board.queue.len, board.open.len());
+        };
+        check(&board);
+        let (candidates, addr) = board.take_best();
+        let min = board.open.iter().map(|a| board.counts[a.index()]).min();
+        assert!(candidates.count_ones() <= min.unwrap() as u32);
+        let edit = board.manipulate(&addr, candidates.trailing_zeros() as usize);
+        check(&board);
+        board.rollback(edit);
+        board.push_open(addr);
+        check(&board);
+    }
+    #[test]
+    fn test_counts_steps() {
+        // Counts has to select exactly the fields Rescan selects
+        for filename in ["top95.txt", "hardest.txt"] {
+            let file = File::open(filename).unwrap();
+            for line in io::BufReader::new(file).lines() {
+                let line = line.unwrap();
+                let mut steps = Vec::new();
+                for selection in [Selection::Rescan, Selection::Counts] {
+                    let heuristics = Heuristics {
+                        selection,
+                        ..Default::default()
+                    };
+                    let mut board = Board::from_line(&line, heuristics);
+                    let mut stats = Stats::default();
+                    let outcome = solve(&mut board, &mut stats, &Limits::default());
+                    steps.push((outcome, stats.num_steps, board.to_line()));
+                }
+                assert_eq!(steps[0], steps[1], "{line}");
+            }
+        }
+    }
+    #[test]
+    fn test_heuristics_solve() {
+        let line =
+            "52...6.........7.13...........4..8..6......5...........418.........3..2...87.....";
+        for (heuristics, strategy) in compare_heuristics() {
+            let mut board = Board::from_line(line, heuristics);
+            let mut stats = Stats::default();
+            let name = strategy.name(&heuristics);
+            let outcome = solve_with(&mut board, &strategy, &Limits::default(), &mut stats);
+            assert_eq!(outcome, SolveOutcome::Solved, "{name}");
+            assert_eq!(board.num_open(), 0);
+            assert!(board.h_free.iter().chain(&board.v_free).all(|f| *f == 0));
+            assert!(board.b_free.iter().all(|f| *f == 0));
+        }
+    }
+    #[test]
+    fn test_other_sizes() {
+        for line in ["51.........216......4..5.....16..25.", &"1".repeat(144)] {
+            let err = Board::try_from_line(line, Heuristics::default()).err();
+            assert_eq!(err, Some(format!("{} fields instead of 81", line.len())));
+        }
+    }
+    #[test]
+    fn test_constraints() {
+        let jigsaw = "9......8....17...9..............54.1......79....4...2......2...6..93.....8......7 111333333111222223144552263147522663147555866447558869477858669447888869777999999";
+        let err = Board::try_from_line(jigsaw, Heuristics::default()).err();
+        assert_eq!(err.as_deref(), Some("unsupported constraint: regions"));
+        let killer = format!("{} cages=a/3", ".".repeat(81));
+        let err = Board::try_from_line(&killer, Heuristics::default()).err();
+        assert_eq!(err.as_deref(), Some("unsupported constraint: cages"));
+    }
+    #[test]
+    fn test_unwind() {
+        let line =
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+        let mut board = Board::from_line(line, Heuristics::default());
+        let initial = board.clone();
+        let mut search = Search::default();
+        let mut stats = Stats::default();
+        assert_eq!(search.run(&mut board, &mut stats, 1000), None);
+        search.unwind(&mut board);
+        assert_eq!(board.fields, initial.fields);
+        assert_eq!(board.counts, initial.counts);
+        assert_eq!(board.num_open(), initial.num_open());
+        assert!(board.h_free == initial.h_free && board.b_free == initial.b_free);
+    }
+    #[test]
+    fn test_limits() {
+        let line =
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+        let initial = Board::from_line(line, Heuristics::default());
+
+        let mut board = initial.clone();
+        let mut stats = Stats::default();
+        let limits = Limits {
+            max_steps: Some(5000),
+            ..Default::default()
+        };
+        let outcome = solve(&mut board, &mut stats, &limits);
+        assert!(matches!(outcome, SolveOutcome::Aborted { steps: 5000, .. }));
+        assert_eq!(board.fields, initial.fields);
+        assert_eq!(board.counts, initial.counts);
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let limits = Limits {
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let mut stats = Stats::default();
+        let outcome = solve(&mut board, &mut stats, &limits);
+        assert_eq!(outcome, SolveOutcome::Aborted { steps: 0, depth: 1 });
+        cancel.store(false, Ordering::Relaxed);
+        assert_eq!(solve(&mut board, &mut stats, &limits), SolveOutcome::Solved);
+
+        let line =
+            "12345678.........9...............................................................";
+        let mut board = Board::from_line(line, Heuristics::default());
+        let outcome = solve(&mut board, &mut stats, &Limits::default());
+        assert_eq!(outcome, SolveOutcome::Unsolvable);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let line =
+            "......8.3.4.7.................5.4.7.3..2.....1.6.......2.....5.....8.6......1....";
+        let initial = Board::from_line(line, Heuristics::default());
+        let mut stats = Stats::default();
+        let outcome = count_solutions(&mut initial.clone(), &mut stats, &Limits::default());
+        assert_eq!(outcome, SolveOutcome::Unsolvable);
+        assert_eq!(stats.solutions, 15919);
+        for threads in [1, 3] {
+            let mut stats = Stats::default();
+            let limits = Limits::default();
+            let mut board = initial.clone();
+            let outcome =
+                solve_parallel(&mut board, threads, Goal::Count, false, &limits, &mut stats);
+            assert_eq!(outcome, SolveOutcome::Unsolvable);
+            assert_eq!(stats.solutions, 15919);
+        }
+
+        // the first solution in sequential order, however the work is split
+        let heuristics = Heuristics {
+            cell: CellPolicy::LowestIndex,
+            ..Default::default()
+        };
+        let mut expected = Board::from_line(line, heuristics);
+        solve(&mut expected, &mut Stats::default(), &Limits::default());
+        for threads in [1, 4] {
+            let mut board = initial.clone();
+            let limits = Limits::default();
+            let mut stats = Stats::default();
+            let outcome =
+                solve_parallel(&mut board, threads, Goal::First, true, &limits, &mut stats);
+            assert_eq!(outcome, SolveOutcome::Solved);
+            assert_eq!(board.fields, expected.fields);
+        }
+
+        // one step budget for all threads
+        for threads in [1, 3] {
+            let mut board = initial.clone();
+            let limits = Limits {
+                max_steps: Some(5000),
+                ..Default::default()
+            };
+            let mut stats = Stats::default();
+            let outcome =
+                solve_parallel(&mut board, threads, Goal::Count, false, &limits, &mut stats);
+            assert!(matches!(outcome, SolveOutcome::Aborted { .. }));
+            assert!(stats.num_steps <= 5000, "{}", stats.num_steps);
+            if threads == 1 {
+                assert_eq!(stats.num_steps, 5000);
+            }
+        }
+    }
+}
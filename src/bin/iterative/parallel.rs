@@ -0,0 +1,274 @@
+//! Parallel search within a single puzzle. Every worker runs a `Search` on its own copy of the
+//! board. While some worker is idle, the busy ones split the untried candidates off the bottom of
+//! their stack (the biggest unexplored subtrees) and queue them as new tasks.
+//!
+//! Solution counts never depend on the scheduling. The first solution only does in deterministic
+//! mode, where each task carries a key ordering it like the sequential search would, the search
+//! only depends on the board content (`CellPolicy::LowestIndex`) and the solution of the
+//! smallest key wins.
+
+use super::{
+    Board, CellPolicy, Heuristics, Limits, Search, Selection, SolveOutcome, Stats, ValuePolicy,
+};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+// steps between two looks at the shared state
+const CHUNK: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// stop at the first solution
+    First,
+    /// count all solutions
+    Count,
+}
+
+struct Task {
+    // A task's own solutions come before those of tasks split off it, and the later a task was
+    // split off, the earlier its solutions, so comparing keys lexicographically orders solutions
+    // like the sequential search would.
+    key: Vec<u32>,
+    board: Board,
+    search: Search,
+    splits: u32,
+    // depth of the frame the task was split off at, counted from the root
+    depth: usize,
+}
+
+#[derive(Default)]
+struct Queue {
+    tasks: Vec<Task>,
+    idle: usize,
+    finished: bool,
+}
+
+struct Pool {
+    workers: usize,
+    goal: Goal,
+    deterministic: bool,
+    queue: Mutex<Queue>,
+    wakeup: Condvar,
+    // number of idle workers, read by busy workers without taking the lock
+    hungry: AtomicUsize,
+    // set once the search is over, checked between chunks
+    done: AtomicBool,
+    aborted: AtomicBool,
+    // the step budget of all workers together, see `reserve`
+    max_steps: Option<usize>,
+    steps: AtomicUsize,
+    solutions: AtomicUsize,
+    best: Mutex<Option<(Vec<u32>, Board)>>,
+    stats: Mutex<Stats>,
+}
+
+impl Pool {
+    fn next_task(&self) -> Option<Task> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if queue.finished {
+                return None;
+            }
+            // smallest key first, so work that may end the search early is done first
+            if let Some(i) = (0..queue.tasks.len()).min_by(|a, b| {
+                let tasks = &queue.tasks;
+                tasks[*a].key.cmp(&tasks[*b].key)
+            }) {
+                return Some(queue.tasks.swap_remove(i));
+            }
+            queue.idle += 1;
+            if queue.idle == self.workers {
+                // nobody left who could split off more work
+                queue.finished = true;
+                self.wakeup.notify_all();
+                return None;
+            }
+            self.hungry.fetch_add(1, Ordering::Relaxed);
+            queue = self.wakeup.wait(queue).unwrap();
+            self.hungry.fetch_sub(1, Ordering::Relaxed);
+            queue.idle -= 1;
+        }
+    }
+    fn give(&self, task: Task) {
+        self.queue.lock().unwrap().tasks.push(task);
+        self.wakeup.notify_one();
+    }
+    fn abort(&self) {
+        self.aborted.store(true, Ordering::Relaxed);
+        self.stop();
+    }
+    fn stop(&self) {
+        self.done.store(true, Ordering::Relaxed);
+        self.queue.lock().unwrap().finished = true;
+        self.wakeup.notify_all();
+    }
+    // takes up to CHUNK steps of the shared budget for the next run, 0 once it is used up. The
+    // steps a run doesn't take go back with `refund`.
+    fn reserve(&self) -> usize {
+        let Some(max) = self.max_steps else {
+            return CHUNK;
+        };
+        match self
+            .steps
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |taken| {
+                (taken < max).then(|| taken + CHUNK.min(max - taken))
+            }) {
+            Ok(taken) => CHUNK.min(max - taken),
+            Err(_) => 0,
+        }
+    }
+    fn refund(&self, steps: usize) {
+        if self.max_steps.is_some() {
+            self.steps.fetch_sub(steps, Ordering::Relaxed);
+        }
+    }
+    // in deterministic mode a task can be dropped once a solution with a smaller key is known
+    fn superseded(&self, key: &[u32]) -> bool {
+        self.deterministic
+            && self
+                .best
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|(best, _)| best.as_slice() < key)
+    }
+    // `limits` without max_steps, the pool keeps track of those
+    fn run(&self, mut task: Task, limits: &Limits) {
+        let mut stats = Stats::default();
+        loop {
+            if self.done.load(Ordering::Relaxed) || self.superseded(&task.key) {
+                break;
+            }
+            if self.hungry.load(Ordering::Relaxed) > 0 {
+                if let Some((board, search, k)) = task.search.split(&task.board) {
+                    task.splits += 1;
+                    let mut key = task.key.clone();
+                    key.push(u32::MAX - task.splits);
+                    self.give(Task {
+                        key,
+                        board,
+                        search,
+                        splits: 0,
+                        depth: task.depth + k,
+                    });
+                }
+            }
+            let steps = self.reserve();
+            if steps == 0 {
+                self.abort();
+                break;
+            }
+            let before = stats.num_steps;
+            let outcome = task
+                .search
+                .run_limited(&mut task.board, &mut stats, steps, limits);
+            self.refund(steps - (stats.num_steps - before));
+            match outcome {
+                Some(SolveOutcome::Solved) => {
+                    self.solutions.fetch_add(1, Ordering::Relaxed);
+                    if self.goal == Goal::Count {
+                        // the Initial state of the solution is popped, so the next run
+                        // backtracks out of it
+                        continue;
+                    }
+                    let mut best = self.best.lock().unwrap();
+                    if best.as_ref().is_none_or(|(key, _)| task.key < *key) {
+                        *best = Some((task.key.clone(), task.board.clone()));
+                    }
+                    if !self.deterministic {
+                        drop(best);
+                        self.stop();
+                    }
+                    break;
+                }
+                Some(SolveOutcome::Unsolvable) => break,
+                Some(SolveOutcome::Aborted { .. }) => {
+                    self.abort();
+                    break;
+                }
+                None => (),
+            }
+        }
+        let mut total = self.stats.lock().unwrap();
+        total.num_steps += stats.num_steps;
+        total.max_depth = total.max_depth.max(task.depth + stats.max_depth);
+    }
+}
+
+/// solves on `threads` threads. With `Goal::First` the board is the solution afterwards, the
+/// number of solutions found is in `stats.solutions` in both cases. `limits.max_steps` bounds the
+/// steps of all threads together.
+pub fn solve_parallel(
+    board: &mut Board,
+    threads: usize,
+    goal: Goal,
+    deterministic: bool,
+    limits: &Limits,
+    stats: &mut Stats,
+) -> SolveOutcome {
+    if deterministic {
+        assert!(
+            board.heuristics.value != ValuePolicy::Random,
+            "a random value order can't be deterministic"
+        );
+        board.set_heuristics(Heuristics {
+            cell: CellPolicy::LowestIndex,
+            selection: Selection::Counts,
+            ..board.heuristics
+        });
+    }
+    let pool = Pool {
+        workers: threads.max(1),
+        goal,
+        deterministic,
+        queue: Mutex::new(Queue::default()),
+        wakeup: Condvar::new(),
+        hungry: AtomicUsize::new(0),
+        done: AtomicBool::new(false),
+        aborted: AtomicBool::new(false),
+        max_steps: limits.max_steps,
+        steps: AtomicUsize::new(0),
+        solutions: AtomicUsize::new(0),
+        best: Mutex::new(None),
+        stats: Mutex::new(Stats::default()),
+    };
+    pool.give(Task {
+        key: Vec::new(),
+        board: board.clone(),
+        search: Search::default(),
+        splits: 0,
+        depth: 0,
+    });
+    let limits = Limits {
+        max_steps: None,
+        ..limits.clone()
+    };
+    thread::scope(|scope| {
+        for _ in 0..pool.workers {
+            scope.spawn(|| {
+                while let Some(task) = pool.next_task() {
+                    pool.run(task, &limits);
+                }
+            });
+        }
+    });
+
+    let total = *pool.stats.lock().unwrap();
+    stats.num_steps += total.num_steps;
+    stats.max_depth = stats.max_depth.max(total.max_depth);
+    stats.solutions += pool.solutions.load(Ordering::Relaxed);
+    if pool.aborted.load(Ordering::Relaxed) {
+        return SolveOutcome::Aborted {
+            steps: stats.num_steps,
+            depth: stats.max_depth,
+        };
+    }
+    if goal == Goal::First {
+        if let Some((_, solution)) = pool.best.into_inner().unwrap() {
+            *board = solution;
+            return SolveOutcome::Solved;
+        }
+    }
+    SolveOutcome::Unsolvable
+}
@@ -0,0 +1,171 @@
+//! Search strategies against the heavy tail of backtracking: a few puzzles take orders of
+//! magnitude more steps than the average with a given branching order, but far fewer with a
+//! slightly different one.
+
+use super::{Board, CellPolicy, Heuristics, Limits, Rng, Search, SolveOutcome, Stats, ValuePolicy};
+use std::env::args;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// a single search with the board's heuristics
+    #[default]
+    Single,
+    /// abandon the search after `unit * luby(i)` steps and start over with the rng reseeded.
+    /// Only useful with a random cell or value policy.
+    Restarts { unit: usize },
+    /// interleave one search per heuristics, `slice` steps at a time. The first search to finish
+    /// wins.
+    Portfolio {
+        heuristics: Vec<Heuristics>,
+        slice: usize,
+    },
+}
+impl Strategy {
+    /// parses `--restarts[=<unit>]`, `--portfolio` and `--slice=<steps>` from the command line.
+    pub fn from_args() -> Strategy {
+        let slice = args()
+            .find_map(|arg| {
+                arg.strip_prefix("--slice=")
+                    .map(|s| s.parse().expect("bad slice"))
+            })
+            .unwrap_or(1000);
+        for arg in args().skip(1) {
+            if arg == "--restarts" {
+                return Strategy::Restarts { unit: 100 };
+            } else if let Some(unit) = arg.strip_prefix("--restarts=") {
+                return Strategy::Restarts {
+                    unit: unit.parse().expect("bad restart unit"),
+                };
+            } else if arg == "--portfolio" {
+                return Strategy::Portfolio {
+                    heuristics: default_portfolio(),
+                    slice,
+                };
+            }
+        }
+        Strategy::Single
+    }
+    pub fn name(&self, heuristics: &Heuristics) -> String {
+        match self {
+            Strategy::Single => heuristics.name(),
+            Strategy::Restarts { unit } => format!("{} restarts {unit}", heuristics.name()),
+            Strategy::Portfolio { heuristics, slice } => {
+                format!("portfolio of {} / {slice}", heuristics.len())
+            }
+        }
+    }
+}
+
+/// heuristics that did well on top95.txt, with their worst cases on different puzzles
+pub fn default_portfolio() -> Vec<Heuristics> {
+    let base = Heuristics {
+        early_exit: false,
+        ..Default::default()
+    };
+    vec![
+        Heuristics {
+            cell: CellPolicy::Degree,
+            ..base
+        },
+        Heuristics {
+            cell: CellPolicy::LastMin,
+            ..base
+        },
+        Heuristics {
+            cell: CellPolicy::Random,
+            seed: 3,
+            ..base
+        },
+        Heuristics {
+            cell: CellPolicy::Random,
+            value: ValuePolicy::Random,
+            seed: 7,
+            ..base
+        },
+    ]
+}
+
+/// i-th element (starting at 1) of the Luby sequence 1 1 2 1 1 2 4 1 1 2 1 1 2 4 8 ...
+pub fn luby(mut i: u64) -> u64 {
+    assert!(i > 0);
+    loop {
+        // 2^(k-1) <= i < 2^k
+        let k = u64::BITS - i.leading_zeros();
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+pub fn solve_with(
+    board: &mut Board,
+    strategy: &Strategy,
+    limits: &Limits,
+    stats: &mut Stats,
+) -> SolveOutcome {
+    match strategy {
+        Strategy::Single => super::solve(board, stats, limits),
+        Strategy::Restarts { unit } => {
+            let seed = board.heuristics.seed;
+            let mut search = Search::default();
+            for i in 1.. {
+                // the first run uses the configured seed, so it matches Strategy::Single
+                board.rng = Rng::new(seed.wrapping_add(i - 1));
+                let max_steps = (*unit as u64).saturating_mul(luby(i));
+                match search.run_limited(board, stats, max_steps as usize, limits) {
+                    Some(SolveOutcome::Aborted { steps, depth }) => {
+                        search.unwind(board);
+                        return SolveOutcome::Aborted { steps, depth };
+                    }
+                    Some(outcome) => return outcome,
+                    None => (),
+                }
+                search.unwind(board);
+                stats.restarts += 1;
+            }
+            unreachable!()
+        }
+        Strategy::Portfolio { heuristics, slice } => {
+            let mut runs = heuristics
+                .iter()
+                .map(|heuristics| {
+                    let mut board = board.clone();
+                    board.set_heuristics(*heuristics);
+                    board.rng = Rng::new(heuristics.seed);
+                    (board, Search::default())
+                })
+                .collect::<Vec<_>>();
+            loop {
+                for (i, (run_board, search)) in runs.iter_mut().enumerate() {
+                    match search.run_limited(run_board, stats, *slice, limits) {
+                        // the runs work on copies, `board` is still untouched
+                        Some(SolveOutcome::Aborted { steps, .. }) => {
+                            let depth = runs.iter().map(|(_, s)| s.stack.len()).max();
+                            return SolveOutcome::Aborted {
+                                steps,
+                                depth: depth.unwrap_or(0),
+                            };
+                        }
+                        Some(outcome) => {
+                            stats.winner = Some(i);
+                            *board = run_board.clone();
+                            return outcome;
+                        }
+                        None => (),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_luby() {
+        let seq = (1..=15).map(luby).collect::<Vec<_>>();
+        assert_eq!(seq, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+}
@@ -0,0 +1,907 @@
+//! The solver as ca65 assembly for the 6502. `Board` was laid out for exactly this port: all state
+//! is flat `u8` arrays and the bit twiddling goes through lookup tables, so every method of the
+//! search loop becomes a short routine over the same arrays and tables. The program is built as
+//! a list of instructions from the Rust constants, so the port cannot drift from the reference.
+//!
+//! The port selects fields like the default `Selection::Rescan`, which needs neither the counts
+//! nor the buckets. It uses the swap/remove `remove_open` in
+//! the search and `remove_open_ordered` for the givens, like the Rust version, so both take the
+//! same steps.
+//!
+//! `--asm[=<file>]` writes the program instead of solving, see `Layout` for where it lives.
+
+use super::cpu::{self, Mode};
+use super::{
+    CANDIDATES_H_UNDEFINED, CANDIDATES_L_UNDEFINED, COUNT_ONES, F2B, F2H, F2V, FIELD_UNDEFINED,
+    RESET_MASK, SET_MASK, STACK_SIZE, TRAILING_ZEROS,
+};
+use std::collections::HashMap;
+use std::env::args;
+use std::fmt::Write;
+
+/// the scalar state, in zero page
+pub const ZERO_PAGE: [(&str, u16); 11] = [
+    ("stack_ptr", 1),
+    ("num_open", 1),
+    ("selected_open_field", 1),
+    ("tmp_open_field", 1),
+    ("min", 1),
+    ("candidates_l", 1),
+    ("candidates_h", 1),
+    ("max_depth", 1),
+    // 0 when solved, 1 when unsolvable
+    ("result", 1),
+    // the bit mask set_field / clear_field apply to the houses
+    ("mask", 1),
+    ("num_steps", 4),
+];
+
+/// the arrays of `Board`, and the puzzle `rs_init` reads the givens from: 0 to 8 for a given
+/// field, `FIELD_UNDEFINED` for an empty one
+pub const RAM: [(&str, u16); 13] = [
+    ("puzzle", 9 * 9),
+    ("open", 9 * 9),
+    ("fields", 9 * 9),
+    ("h_free_l", 9),
+    ("v_free_l", 9),
+    ("b_free_l", 9),
+    ("h_free_h", 9),
+    ("v_free_h", 9),
+    ("b_free_h", 9),
+    ("candidates_l_stack", STACK_SIZE as u16),
+    ("candidates_h_stack", STACK_SIZE as u16),
+    ("num_stack", STACK_SIZE as u16),
+    ("field_stack", STACK_SIZE as u16),
+];
+
+/// Where the program keeps its state: the variables of `ZERO_PAGE` from `--zp=<address>` ($80 by
+/// default), the arrays of `RAM` from `--ram=<address>` ($0200) and the code and the tables from
+/// `--org=<address>` ($8000). Addresses are hex with `$` or `0x`, or decimal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub zero_page: u16,
+    pub ram: u16,
+    pub code: u16,
+}
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            zero_page: 0x80,
+            ram: 0x0200,
+            code: 0x8000,
+        }
+    }
+}
+impl Layout {
+    pub fn from_args() -> Result<Layout, String> {
+        let arg = |prefix: &str| args().find_map(|arg| arg.strip_prefix(prefix).map(parse_address));
+        let default = Layout::default();
+        let layout = Layout {
+            zero_page: arg("--zp=").unwrap_or(Ok(default.zero_page))?,
+            ram: arg("--ram=").unwrap_or(Ok(default.ram))?,
+            code: arg("--org=").unwrap_or(Ok(default.code))?,
+        };
+        layout.check()?;
+        Ok(layout)
+    }
+    /// the zero page variables must fit into zero page, and the arrays must neither overlap zero
+    /// page, the hardware stack at $0100, nor the start of the code
+    pub fn check(&self) -> Result<(), String> {
+        let zero_page_end =
+            self.zero_page as u32 + ZERO_PAGE.iter().map(|v| v.1 as u32).sum::<u32>();
+        if zero_page_end > 0x100 {
+            return Err(format!(
+                "the zero page variables at ${:02x} need {} bytes",
+                self.zero_page,
+                zero_page_end - self.zero_page as u32
+            ));
+        }
+        let ram_end = self.ram as u32 + RAM.iter().map(|v| v.1 as u32).sum::<u32>();
+        if self.ram < 0x200 || ram_end > 0x10000 {
+            return Err(format!("the arrays do not fit at ${:04x}", self.ram));
+        }
+        if (self.ram as u32..ram_end).contains(&(self.code as u32)) {
+            return Err(format!(
+                "the code at ${:04x} overlaps the arrays at ${:04x}-${:04x}",
+                self.code,
+                self.ram,
+                ram_end - 1
+            ));
+        }
+        Ok(())
+    }
+    /// the addresses of the variables and the arrays
+    pub fn symbols(&self) -> Vec<(&'static str, u16)> {
+        let mut symbols = Vec::new();
+        for (base, variables) in [(self.zero_page, &ZERO_PAGE[..]), (self.ram, &RAM)] {
+            let mut address = base;
+            for &(name, size) in variables.iter() {
+                symbols.push((name, address));
+                address += size;
+            }
+        }
+        symbols
+    }
+}
+
+fn parse_address(s: &str) -> Result<u16, String> {
+    let parsed = if let Some(hex) = s.strip_prefix('$').or_else(|| s.strip_prefix("0x")) {
+        u16::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|_| format!("bad address: {s}"))
+}
+
+/// a symbol plus a constant offset, like `num_steps+1`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Expr {
+    pub symbol: &'static str,
+    pub offset: i16,
+}
+
+/// the addressing modes the program uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Implied,
+    Immediate(u8),
+    ZeroPage(Expr),
+    Absolute(Expr),
+    AbsoluteX(Expr),
+    AbsoluteY(Expr),
+    /// a branch to a label
+    Relative(&'static str),
+}
+impl Operand {
+    fn offset(self, offset: i16) -> Operand {
+        let add = |expr: Expr| Expr {
+            offset: expr.offset + offset,
+            ..expr
+        };
+        match self {
+            Operand::ZeroPage(expr) => Operand::ZeroPage(add(expr)),
+            Operand::Absolute(expr) => Operand::Absolute(add(expr)),
+            Operand::AbsoluteX(expr) => Operand::AbsoluteX(add(expr)),
+            Operand::AbsoluteY(expr) => Operand::AbsoluteY(add(expr)),
+            operand => operand,
+        }
+    }
+    fn mode(self) -> Mode {
+        match self {
+            Operand::Implied => Mode::Implied,
+            Operand::Immediate(_) => Mode::Immediate,
+            Operand::ZeroPage(_) => Mode::ZeroPage,
+            Operand::Absolute(_) => Mode::Absolute,
+            Operand::AbsoluteX(_) => Mode::AbsoluteX,
+            Operand::AbsoluteY(_) => Mode::AbsoluteY,
+            Operand::Relative(_) => Mode::Relative,
+        }
+    }
+}
+
+const IMP: Operand = Operand::Implied;
+fn imm(value: u8) -> Operand {
+    Operand::Immediate(value)
+}
+fn expr(symbol: &'static str) -> Expr {
+    Expr { symbol, offset: 0 }
+}
+fn zp(symbol: &'static str) -> Operand {
+    Operand::ZeroPage(expr(symbol))
+}
+fn abs(symbol: &'static str) -> Operand {
+    Operand::Absolute(expr(symbol))
+}
+fn abs_x(symbol: &'static str) -> Operand {
+    Operand::AbsoluteX(expr(symbol))
+}
+fn abs_y(symbol: &'static str) -> Operand {
+    Operand::AbsoluteY(expr(symbol))
+}
+fn rel(label: &'static str) -> Operand {
+    Operand::Relative(label)
+}
+
+/// Labels starting with `@` are local to the previous global label, like in ca65.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Comment(&'static str),
+    Label(&'static str),
+    Instruction(&'static str, Operand),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    pub layout: Layout,
+    pub items: Vec<Item>,
+}
+impl Program {
+    fn comment(&mut self, text: &'static str) {
+        self.items.push(Item::Comment(text));
+    }
+    fn label(&mut self, name: &'static str) {
+        self.items.push(Item::Label(name));
+    }
+    fn op(&mut self, mnemonic: &'static str, operand: Operand) {
+        self.items.push(Item::Instruction(mnemonic, operand));
+    }
+    fn table(&mut self, name: &'static str, bytes: impl IntoIterator<Item = u8>) {
+        self.label(name);
+        self.items.push(Item::Bytes(bytes.into_iter().collect()));
+    }
+
+    /// the ca65 source of the program
+    pub fn source(&self) -> String {
+        let mut out = String::new();
+        for line in HEADER {
+            writeln!(out, ";{}{line}", if line.is_empty() { "" } else { " " }).unwrap();
+        }
+        writeln!(out, "\n.setcpu \"6502\"\n").unwrap();
+        for (name, address) in self.layout.symbols() {
+            if address < 0x100 {
+                writeln!(out, "{name} = ${address:02x}").unwrap();
+            } else {
+                writeln!(out, "{name} = ${address:04x}").unwrap();
+            }
+        }
+        writeln!(out, "\n.segment \"CODE\"\n.org ${:04x}", self.layout.code).unwrap();
+        for item in &self.items {
+            match item {
+                Item::Comment(text) => writeln!(out, "\n; {text}").unwrap(),
+                Item::Label(name) => writeln!(out, "{name}:").unwrap(),
+                Item::Instruction(mnemonic, Operand::Implied) => {
+                    writeln!(out, "    {mnemonic}").unwrap()
+                }
+                Item::Instruction(mnemonic, operand) => {
+                    let operand = match operand {
+                        Operand::Implied => unreachable!(),
+                        Operand::Immediate(value) => format!("#${value:02x}"),
+                        Operand::ZeroPage(expr) | Operand::Absolute(expr) => format_expr(expr),
+                        Operand::AbsoluteX(expr) => format!("{},x", format_expr(expr)),
+                        Operand::AbsoluteY(expr) => format!("{},y", format_expr(expr)),
+                        Operand::Relative(label) => label.to_string(),
+                    };
+                    writeln!(out, "    {mnemonic} {operand}").unwrap();
+                }
+                Item::Bytes(bytes) => {
+                    for chunk in bytes.chunks(16) {
+                        let chunk = chunk.iter().map(|b| format!("${b:02x}"));
+                        writeln!(out, "    .byte {}", chunk.collect::<Vec<_>>().join(",")).unwrap();
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// The machine code of a program for the emulator of `cpu`, to be loaded at `origin`.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+    // the variables, the arrays and the labels. Local labels are qualified with their scope,
+    // like `rs_solve@step`.
+    symbols: HashMap<String, u16>,
+}
+impl Image {
+    /// the address of a variable, an array or a label. Panics for unknown names.
+    pub fn address(&self, name: &str) -> u16 {
+        match self.symbols.get(name) {
+            Some(address) => *address,
+            None => panic!("no symbol {name}"),
+        }
+    }
+}
+
+impl Program {
+    /// assembles the program in two passes, the first one finds the address of every label
+    pub fn assemble(&self) -> Result<Image, String> {
+        let qualified = |scope: &str, name: &str| match name.starts_with('@') {
+            true => format!("{scope}{name}"),
+            false => name.to_string(),
+        };
+        let mut symbols: HashMap<String, u16> = (self.layout.symbols().into_iter())
+            .map(|(name, address)| (name.to_string(), address))
+            .collect();
+        let mut scope = "";
+        let mut address = self.layout.code as u32;
+        for item in &self.items {
+            match item {
+                Item::Label(name) => {
+                    if !name.starts_with('@') {
+                        scope = name;
+                    }
+                    symbols.insert(qualified(scope, name), address as u16);
+                }
+                Item::Instruction(_, operand) => {
+                    address += 1 + operand.mode().operand_size() as u32
+                }
+                Item::Bytes(bytes) => address += bytes.len() as u32,
+                Item::Comment(_) => (),
+            }
+        }
+        let ram_end = self.layout.ram as u32 + RAM.iter().map(|v| v.1 as u32).sum::<u32>();
+        let code = self.layout.code as u32..address;
+        if address > 0x10000
+            || code.start < 0x200
+            || code.start < ram_end && (self.layout.ram as u32) < code.end
+        {
+            return Err(format!(
+                "the code at ${:04x}-${:04x} does not fit next to the arrays",
+                self.layout.code,
+                address - 1
+            ));
+        }
+
+        let mut bytes = Vec::new();
+        scope = "";
+        for item in &self.items {
+            let (mnemonic, operand) = match item {
+                Item::Label(name) if !name.starts_with('@') => {
+                    scope = name;
+                    continue;
+                }
+                Item::Bytes(data) => {
+                    bytes.extend(data);
+                    continue;
+                }
+                Item::Instruction(mnemonic, operand) => (mnemonic, operand),
+                _ => continue,
+            };
+            let lookup = |name: &str| {
+                let name = qualified(scope, name);
+                symbols
+                    .get(&name)
+                    .copied()
+                    .ok_or(format!("undefined symbol {name}"))
+            };
+            let resolve = |expr: &Expr| {
+                Ok::<_, String>(lookup(expr.symbol)?.wrapping_add(expr.offset as u16))
+            };
+            let mode = operand.mode();
+            let opcode = cpu::encode(mnemonic, mode)
+                .or_else(|| {
+                    (mode == Mode::Implied)
+                        .then(|| cpu::encode(mnemonic, Mode::Accumulator))
+                        .flatten()
+                })
+                .ok_or(format!("no {mnemonic} with {mode:?} in {scope}"))?;
+            bytes.push(opcode);
+            match operand {
+                Operand::Implied => (),
+                Operand::Immediate(value) => bytes.push(*value),
+                Operand::ZeroPage(expr) => match resolve(expr)? {
+                    address @ 0..=0xff => bytes.push(address as u8),
+                    _ => return Err(format!("{} is not in zero page", format_expr(expr))),
+                },
+                Operand::Absolute(expr) | Operand::AbsoluteX(expr) | Operand::AbsoluteY(expr) => {
+                    bytes.extend(resolve(expr)?.to_le_bytes())
+                }
+                Operand::Relative(label) => {
+                    // relative to the address after the branch
+                    let next = self.layout.code as i32 + bytes.len() as i32 + 1;
+                    let offset = lookup(label)? as i32 - next;
+                    if !(-128..=127).contains(&offset) {
+                        return Err(format!("the branch to {label} in {scope} is too far"));
+                    }
+                    bytes.push(offset as i8 as u8);
+                }
+            }
+        }
+        Ok(Image {
+            origin: self.layout.code,
+            bytes,
+            symbols,
+        })
+    }
+}
+
+fn format_expr(expr: &Expr) -> String {
+    match expr.offset {
+        0 => expr.symbol.to_string(),
+        offset if offset < 0 => format!("{}{offset}", expr.symbol),
+        offset => format!("{}+{offset}", expr.symbol),
+    }
+}
+
+const HEADER: [&str; 10] = [
+    "The 8-bit solver of rusdoku for the 6502, generated by `iterative_8bit --asm`. Do not edit.",
+    "",
+    "rs_main   calls rs_init and rs_solve and then loops at `halt`, for machines without an",
+    "          operating system",
+    "rs_init   sets up the board with the givens of `puzzle`",
+    "rs_solve  searches until the board is solved (`result` = 0, `fields` holds the",
+    "          solution) or nothing is left to try (`result` = 1)",
+    "",
+    "Both are called with jsr and keep all state in the variables below, so the board",
+    "can be inspected afterwards.",
+];
+
+/// the solver for `layout`
+pub fn program(layout: Layout) -> Program {
+    let mut p = Program {
+        layout,
+        items: Vec::new(),
+    };
+    p.comment("rs_init and rs_solve, then stop");
+    p.label("rs_main");
+    p.op("jsr", abs("rs_init"));
+    p.op("jsr", abs("rs_solve"));
+    p.label("halt");
+    p.op("jmp", abs("halt"));
+    init(&mut p);
+    solve(&mut p);
+    set_field(&mut p);
+    clear_field(&mut p);
+    candidates_for_tmp_field(&mut p);
+    select_open_field(&mut p);
+    remove_open(&mut p);
+    remove_open_ordered(&mut p);
+    push_open(&mut p);
+    select_candidate(&mut p);
+    apply_candidate(&mut p);
+    reset_frame(&mut p);
+
+    p.comment("tables");
+    p.table("F2H", F2H.map(|h| h as u8));
+    p.table("F2V", F2V.map(|v| v as u8));
+    p.table("F2B", F2B.map(|b| b as u8));
+    p.table("COUNT_ONES", COUNT_ONES);
+    p.table("TRAILING_ZEROS", TRAILING_ZEROS);
+    p.table("SET_MASK", SET_MASK);
+    p.table("RESET_MASK", RESET_MASK);
+    p
+}
+
+// Board::default and Board::from_line
+fn init(p: &mut Program) {
+    p.comment("Board::default, then Board::from_line with the givens of puzzle");
+    p.label("rs_init");
+    p.op("ldx", imm(0));
+    p.label("@open");
+    p.op("txa", IMP);
+    p.op("sta", abs_x("open"));
+    p.op("lda", imm(FIELD_UNDEFINED));
+    p.op("sta", abs_x("fields"));
+    p.op("inx", IMP);
+    p.op("cpx", imm(9 * 9));
+    p.op("bne", rel("@open"));
+    p.op("stx", zp("num_open"));
+    p.op("ldx", imm(0));
+    p.label("@houses");
+    p.op("lda", imm(0b11111111));
+    for free in ["h_free_l", "v_free_l", "b_free_l"] {
+        p.op("sta", abs_x(free));
+    }
+    p.op("lda", imm(0b00000001));
+    for free in ["h_free_h", "v_free_h", "b_free_h"] {
+        p.op("sta", abs_x(free));
+    }
+    p.op("inx", IMP);
+    p.op("cpx", imm(9));
+    p.op("bne", rel("@houses"));
+    p.op("ldx", imm(0));
+    p.label("@frames");
+    p.op("lda", imm(CANDIDATES_L_UNDEFINED));
+    p.op("sta", abs_x("candidates_l_stack"));
+    p.op("lda", imm(CANDIDATES_H_UNDEFINED));
+    p.op("sta", abs_x("candidates_h_stack"));
+    p.op("lda", imm(0));
+    p.op("sta", abs_x("num_stack"));
+    p.op("lda", imm(FIELD_UNDEFINED));
+    p.op("sta", abs_x("field_stack"));
+    p.op("inx", IMP);
+    p.op("cpx", imm(STACK_SIZE as u8));
+    p.op("bne", rel("@frames"));
+    p.op("lda", imm(0));
+    for variable in ["stack_ptr", "max_depth", "result"] {
+        p.op("sta", zp(variable));
+    }
+    for i in 0..4 {
+        p.op("sta", zp("num_steps").offset(i));
+    }
+    // like from_line, the first frame walks over the fields
+    p.op("sta", abs("field_stack"));
+    p.label("@given");
+    p.op("ldx", abs("field_stack"));
+    p.op("lda", abs_x("puzzle"));
+    p.op("cmp", imm(FIELD_UNDEFINED));
+    p.op("beq", rel("@next"));
+    p.op("sta", abs("num_stack"));
+    p.op("ldy", imm(0));
+    p.label("@find");
+    p.op("lda", abs_y("open"));
+    p.op("cmp", abs("field_stack"));
+    p.op("beq", rel("@found"));
+    p.op("iny", IMP);
+    p.op("cpy", zp("num_open"));
+    p.op("bne", rel("@find"));
+    p.label("@found");
+    p.op("sty", zp("selected_open_field"));
+    p.op("jsr", abs("remove_open_ordered"));
+    p.op("jsr", abs("set_field"));
+    p.label("@next");
+    p.op("inc", abs("field_stack"));
+    p.op("lda", abs("field_stack"));
+    p.op("cmp", imm(9 * 9));
+    p.op("bne", rel("@given"));
+    p.op("rts", IMP);
+}
+
+// Board::step until it returns an outcome
+fn solve(p: &mut Program) {
+    p.comment("Board::step until the board is solved or unsolvable");
+    p.label("rs_solve");
+    p.label("@step");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("inx", IMP);
+    p.op("cpx", zp("max_depth"));
+    p.op("bcc", rel("@count"));
+    p.op("stx", zp("max_depth"));
+    p.label("@count");
+    p.op("inc", zp("num_steps"));
+    for i in 1..4 {
+        p.op("bne", rel("@frame"));
+        p.op("inc", zp("num_steps").offset(i));
+    }
+    p.label("@frame");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("lda", abs_x("candidates_h_stack"));
+    p.op("cmp", imm(CANDIDATES_H_UNDEFINED));
+    p.op("bne", rel("@retry"));
+    p.op("lda", zp("num_open"));
+    p.op("beq", rel("@solved"));
+    p.op("jsr", abs("select_open_field"));
+    p.op("jsr", abs("remove_open"));
+    p.op("jmp", abs("@candidate"));
+    p.label("@retry");
+    p.op("jsr", abs("clear_field"));
+    p.label("@candidate");
+    p.op("jsr", abs("select_candidate"));
+    p.op("ldx", zp("stack_ptr"));
+    p.op("sta", abs_x("num_stack"));
+    // where Board::step_traced records the step, see `trace`
+    p.label("@tried");
+    p.op("cmp", imm(9));
+    p.op("bcs", rel("@backtrack"));
+    p.op("jsr", abs("apply_candidate"));
+    p.op("jsr", abs("set_field"));
+    p.op("inc", zp("stack_ptr"));
+    p.op("jsr", abs("reset_frame"));
+    p.op("jmp", abs("@step"));
+    p.label("@backtrack");
+    p.op("jsr", abs("push_open"));
+    p.op("lda", zp("stack_ptr"));
+    p.op("beq", rel("@unsolvable"));
+    p.op("dec", zp("stack_ptr"));
+    p.op("jmp", abs("@step"));
+    p.label("@unsolvable");
+    p.op("jsr", abs("reset_frame"));
+    p.op("lda", imm(1));
+    p.op("sta", zp("result"));
+    p.op("rts", IMP);
+    p.label("@solved");
+    p.op("lda", imm(0));
+    p.op("sta", zp("result"));
+    p.op("rts", IMP);
+}
+
+// `op` (and / ora) of mask into the free bits of the houses of the field in Y
+fn update_houses(p: &mut Program, op: &'static str, free: [&'static str; 3]) {
+    for (f2house, free) in ["F2H", "F2V", "F2B"].into_iter().zip(free) {
+        p.op("ldx", abs_y(f2house));
+        p.op("lda", abs_x(free));
+        p.op(op, zp("mask"));
+        p.op("sta", abs_x(free));
+    }
+}
+
+// set_field and clear_field: the number in A < 8 goes to the low houses, otherwise to the high
+// ones. Y is the field.
+fn low_or_high(p: &mut Program, mask: &'static str, op: &'static str) {
+    p.op("cmp", imm(8));
+    p.op("bcs", rel("@high"));
+    p.op("tax", IMP);
+    p.op("lda", abs_x(mask));
+    p.op("sta", zp("mask"));
+    update_houses(p, op, ["h_free_l", "v_free_l", "b_free_l"]);
+    p.op("jmp", abs("@done"));
+    p.label("@high");
+    // the carry is set
+    p.op("sbc", imm(8));
+    p.op("tax", IMP);
+    p.op("lda", abs_x(mask));
+    p.op("sta", zp("mask"));
+    update_houses(p, op, ["h_free_h", "v_free_h", "b_free_h"]);
+    p.label("@done");
+}
+
+fn set_field(p: &mut Program) {
+    p.comment("Board::set_field");
+    p.label("set_field");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("ldy", abs_x("field_stack"));
+    p.op("lda", abs_x("num_stack"));
+    p.op("sta", abs_y("fields"));
+    low_or_high(p, "RESET_MASK", "and");
+    p.op("rts", IMP);
+}
+
+fn clear_field(p: &mut Program) {
+    p.comment("Board::clear_field");
+    p.label("clear_field");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("ldy", abs_x("field_stack"));
+    p.op("lda", abs_y("fields"));
+    low_or_high(p, "SET_MASK", "ora");
+    p.op("lda", imm(FIELD_UNDEFINED));
+    p.op("sta", abs_y("fields"));
+    p.op("rts", IMP);
+}
+
+fn candidates_for_tmp_field(p: &mut Program) {
+    p.comment("Board::candidates_for_tmp_field");
+    p.label("candidates_for_tmp_field");
+    p.op("ldx", zp("tmp_open_field"));
+    p.op("ldy", abs_x("open"));
+    for (free, candidates) in [
+        (["h_free_l", "v_free_l", "b_free_l"], "candidates_l"),
+        (["h_free_h", "v_free_h", "b_free_h"], "candidates_h"),
+    ] {
+        p.op("ldx", abs_y("F2H"));
+        p.op("lda", abs_x(free[0]));
+        p.op("ldx", abs_y("F2V"));
+        p.op("and", abs_x(free[1]));
+        p.op("ldx", abs_y("F2B"));
+        p.op("and", abs_x(free[2]));
+        p.op("sta", zp(candidates));
+    }
+    p.op("rts", IMP);
+}
+
+fn select_open_field(p: &mut Program) {
+    p.comment("Board::select_open_field_rescan");
+    p.label("select_open_field");
+    p.op("lda", imm(u8::MAX));
+    p.op("sta", zp("selected_open_field"));
+    p.op("sta", zp("min"));
+    p.op("lda", imm(0));
+    p.op("sta", zp("tmp_open_field"));
+    p.label("@scan");
+    p.op("lda", zp("tmp_open_field"));
+    p.op("cmp", zp("num_open"));
+    p.op("bcs", rel("@done"));
+    p.op("jsr", abs("candidates_for_tmp_field"));
+    p.op("ldx", zp("candidates_l"));
+    p.op("lda", abs_x("COUNT_ONES"));
+    p.op("ldx", zp("candidates_h"));
+    p.op("clc", IMP);
+    p.op("adc", abs_x("COUNT_ONES"));
+    p.op("cmp", zp("min"));
+    p.op("bcs", rel("@not_fewer"));
+    p.op("sta", zp("min"));
+    p.op("lda", zp("tmp_open_field"));
+    p.op("sta", zp("selected_open_field"));
+    p.op("ldx", zp("stack_ptr"));
+    p.op("lda", zp("candidates_l"));
+    p.op("sta", abs_x("candidates_l_stack"));
+    p.op("lda", zp("candidates_h"));
+    p.op("sta", abs_x("candidates_h_stack"));
+    p.label("@not_fewer");
+    p.op("lda", zp("min"));
+    p.op("cmp", imm(1));
+    p.op("beq", rel("@done"));
+    p.op("inc", zp("tmp_open_field"));
+    p.op("jmp", abs("@scan"));
+    p.label("@done");
+    p.op("rts", IMP);
+}
+
+fn remove_open(p: &mut Program) {
+    p.comment("Board::remove_open");
+    p.label("remove_open");
+    p.op("ldy", zp("selected_open_field"));
+    p.op("lda", abs_y("open"));
+    p.op("ldx", zp("stack_ptr"));
+    p.op("sta", abs_x("field_stack"));
+    p.op("dec", zp("num_open"));
+    p.op("ldx", zp("num_open"));
+    p.op("lda", abs_x("open"));
+    p.op("sta", abs_y("open"));
+    p.op("rts", IMP);
+}
+
+fn remove_open_ordered(p: &mut Program) {
+    p.comment("Board::remove_open_ordered");
+    p.label("remove_open_ordered");
+    p.op("ldy", zp("selected_open_field"));
+    p.label("@shift");
+    p.op("iny", IMP);
+    p.op("cpy", zp("num_open"));
+    p.op("bcs", rel("@done"));
+    p.op("lda", abs_y("open"));
+    p.op("sta", abs_y("open").offset(-1));
+    p.op("jmp", abs("@shift"));
+    p.label("@done");
+    p.op("dec", zp("num_open"));
+    p.op("rts", IMP);
+}
+
+fn push_open(p: &mut Program) {
+    p.comment("Board::push_open");
+    p.label("push_open");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("lda", abs_x("field_stack"));
+    p.op("ldx", zp("num_open"));
+    p.op("sta", abs_x("open"));
+    p.op("inc", zp("num_open"));
+    p.op("rts", IMP);
+}
+
+fn select_candidate(p: &mut Program) {
+    p.comment("Board::select_candidate, returned in A");
+    p.label("select_candidate");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("ldy", abs_x("candidates_l_stack"));
+    p.op("beq", rel("@high"));
+    p.op("lda", abs_y("TRAILING_ZEROS"));
+    p.op("rts", IMP);
+    p.label("@high");
+    p.op("ldy", abs_x("candidates_h_stack"));
+    p.op("lda", abs_y("TRAILING_ZEROS"));
+    p.op("clc", IMP);
+    p.op("adc", imm(8));
+    p.op("rts", IMP);
+}
+
+fn apply_candidate(p: &mut Program) {
+    p.comment("Board::apply_candidate");
+    p.label("apply_candidate");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("lda", abs_x("num_stack"));
+    p.op("cmp", imm(8));
+    p.op("bcs", rel("@high"));
+    p.op("tay", IMP);
+    p.op("lda", abs_y("RESET_MASK"));
+    p.op("and", abs_x("candidates_l_stack"));
+    p.op("sta", abs_x("candidates_l_stack"));
+    p.op("rts", IMP);
+    p.label("@high");
+    // the carry is set
+    p.op("sbc", imm(8));
+    p.op("tay", IMP);
+    p.op("lda", abs_y("RESET_MASK"));
+    p.op("and", abs_x("candidates_h_stack"));
+    p.op("sta", abs_x("candidates_h_stack"));
+    p.op("rts", IMP);
+}
+
+fn reset_frame(p: &mut Program) {
+    p.comment("Board::reset_frame");
+    p.label("reset_frame");
+    p.op("ldx", zp("stack_ptr"));
+    p.op("lda", imm(CANDIDATES_L_UNDEFINED));
+    p.op("sta", abs_x("candidates_l_stack"));
+    p.op("lda", imm(CANDIDATES_H_UNDEFINED));
+    p.op("sta", abs_x("candidates_h_stack"));
+    p.op("lda", imm(0));
+    p.op("sta", abs_x("num_stack"));
+    p.op("lda", imm(FIELD_UNDEFINED));
+    p.op("sta", abs_x("field_stack"));
+    p.op("rts", IMP);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_tables() {
+        let program = program(Layout::default());
+        let table = |name| {
+            let i = program
+                .items
+                .iter()
+                .position(|item| *item == Item::Label(name));
+            match &program.items[i.unwrap() + 1] {
+                Item::Bytes(bytes) => bytes.clone(),
+                item => panic!("{name} is followed by {item:?}"),
+            }
+        };
+        assert_eq!(table("COUNT_ONES"), COUNT_ONES);
+        assert_eq!(table("TRAILING_ZEROS"), TRAILING_ZEROS);
+        assert_eq!(table("RESET_MASK"), RESET_MASK);
+        assert_eq!(table("F2B")[80], 8);
+
+        let source = program.source();
+        assert!(source.contains("\nTRAILING_ZEROS:\n    .byte $08,$00,$01,$00,$02,"));
+        assert!(source.contains("\n    sta open-1,y\n"));
+        assert!(source.contains("\n    inc num_steps+3\n"));
+        assert!(source.contains("\n    lda #$80\n"));
+    }
+    #[test]
+    fn test_labels() {
+        // every operand refers to a variable or a label defined in its scope
+        let program = program(Layout::default());
+        let symbols = program.layout.symbols();
+        let mut scope = "";
+        let mut globals = Vec::new();
+        let mut locals = Vec::new();
+        for item in &program.items {
+            if let Item::Label(name) = item {
+                match name.strip_prefix('@') {
+                    Some(_) => locals.push((scope, *name)),
+                    None => {
+                        scope = name;
+                        globals.push(*name);
+                    }
+                }
+            }
+        }
+        scope = "";
+        for item in &program.items {
+            let symbol = match item {
+                Item::Label(name) if !name.starts_with('@') => {
+                    scope = name;
+                    continue;
+                }
+                Item::Instruction(_, Operand::Relative(label)) => label,
+                Item::Instruction(
+                    _,
+                    Operand::ZeroPage(expr)
+                    | Operand::Absolute(expr)
+                    | Operand::AbsoluteX(expr)
+                    | Operand::AbsoluteY(expr),
+                ) => &expr.symbol,
+                _ => continue,
+            };
+            let defined = if symbol.starts_with('@') {
+                locals.contains(&(scope, *symbol))
+            } else {
+                globals.contains(symbol) || symbols.iter().any(|(name, _)| name == symbol)
+            };
+            assert!(defined, "{symbol} in {scope}");
+        }
+    }
+    #[test]
+    fn test_layout() {
+        let layout = Layout {
+            zero_page: 0x10,
+            ram: 0x1000,
+            code: 0xc000,
+        };
+        assert_eq!(layout.check(), Ok(()));
+        let symbols = layout.symbols();
+        assert_eq!(symbols[0], ("stack_ptr", 0x10));
+        assert!(symbols.contains(&("num_steps", 0x1a)));
+        assert!(symbols.contains(&("puzzle", 0x1000)));
+        assert!(symbols.contains(&("open", 0x1051)));
+        assert_eq!(symbols.last(), Some(&("field_stack", 0x1000 + 625 - 82)));
+        let source = program(layout).source();
+        assert!(source.contains("\nstack_ptr = $10\n"));
+        assert!(source.contains("\nfield_stack = $121f\n"));
+        assert!(source.contains("\n.org $c000\n"));
+
+        for layout in [
+            Layout {
+                zero_page: 0xf8,
+                ..layout
+            },
+            Layout {
+                ram: 0x0100,
+                ..layout
+            },
+            Layout {
+                code: 0x1100,
+                ..layout
+            },
+        ] {
+            assert!(layout.check().is_err(), "{layout:?}");
+        }
+        assert_eq!(parse_address("$0200"), Ok(0x200));
+        assert_eq!(parse_address("0xc000"), Ok(0xc000));
+        assert_eq!(parse_address("512"), Ok(512));
+        assert!(parse_address("$10000").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! A cost model of the search on a 6502: `Board` tallies the operations a port would execute,
+//! and a table of cycles per operation turns the tally into an estimate. This is much faster than
+//! the emulator and needs no port of a variant, so layouts can be compared before they are
+//! written, e.g. `--ordered-remove` against the default swap/remove.
+//!
+//! Only a `Board<true>` tallies, the solver itself is a `Board<false>` and does not pay for it.
+//!
+//! The defaults are the cycles of the corresponding code of `asm`, so with the default selection
+//! (which is what the port does) the estimate stays within a few percent of `--cross-check`. A
+//! port that rescans keeps no counts and one that scans the counts keeps no buckets, so their
+//! upkeep is only tallied for the selections that use them.
+//!
+//! `--estimate <file>` prints the estimated cycles of every puzzle of the file and where they go.
+//! `--costs=<op>=<cycles>,...` overrides the defaults, e.g. `--costs=lookup=5,shift=16`.
+
+use super::{board_from_args, Board, Limits};
+use std::env::args;
+use std::fs::File;
+use std::io::{self, BufRead};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// one iteration of the search loop, without the routines it calls
+    Step,
+    /// calling a routine: jsr and rts
+    Call,
+    /// reading one of the tables (F2H, COUNT_ONES, TRAILING_ZEROS, RESET_MASK, PEERS, ...) or
+    /// one of the arrays at an index
+    Lookup,
+    /// `bit_set` of a byte in RAM: read, or with the mask, write
+    BitSet,
+    /// `bit_reset`: read, and with the mask, write
+    BitReset,
+    /// combining the free bits of the houses of a field into its candidates, without the lookups
+    Candidates,
+    /// one open field in the `select_open_field` scan, without the lookups
+    ScanField,
+    /// a new minimum in the scan: storing it with its candidates
+    NewMin,
+    /// `remove_open`
+    SwapRemove,
+    /// `remove_open_ordered`, without the fields it moves
+    OrderedRemove,
+    /// moving one open field with `copy_within` in `remove_open_ordered`
+    Shift,
+    /// `push_open`
+    PushOpen,
+    /// the part of `set_field` and `clear_field` outside the houses
+    Field,
+    /// `select_candidate` without the lookup
+    SelectCandidate,
+    /// `apply_candidate` without the lookup and the bit_reset
+    ApplyCandidate,
+    /// `reset_frame`
+    ResetFrame,
+    /// updating the count of one field in `update_counts`
+    Count,
+    /// `bucket_link` or `bucket_unlink`
+    Link,
+}
+
+/// every operation with its name and default cycles
+pub const OPS: [(Op, &str, u64); 18] = [
+    (Op::Step, "step", 46),
+    (Op::Call, "call", 12),
+    (Op::Lookup, "lookup", 4),
+    (Op::BitSet, "bit_set", 12),
+    (Op::BitReset, "bit_reset", 12),
+    (Op::Candidates, "candidates", 37),
+    (Op::ScanField, "scan_field", 37),
+    (Op::NewMin, "new_min", 28),
+    (Op::SwapRemove, "swap_remove", 28),
+    (Op::OrderedRemove, "ordered_remove", 15),
+    (Op::Shift, "shift", 19),
+    (Op::PushOpen, "push_open", 20),
+    (Op::Field, "field", 29),
+    (Op::SelectCandidate, "select_candidate", 12),
+    (Op::ApplyCandidate, "apply_candidate", 15),
+    (Op::ResetFrame, "reset_frame", 31),
+    (Op::Count, "count", 14),
+    (Op::Link, "link", 40),
+];
+
+/// how often every operation ran, see `Op`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tally([usize; OPS.len()]);
+impl Tally {
+    pub fn add(&mut self, op: Op, n: usize) {
+        self.0[op as usize] += n;
+    }
+    pub fn get(&self, op: Op) -> usize {
+        self.0[op as usize]
+    }
+}
+
+/// cycles per operation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Costs([u64; OPS.len()]);
+impl Default for Costs {
+    fn default() -> Self {
+        Costs(OPS.map(|(_, _, cycles)| cycles))
+    }
+}
+impl Costs {
+    /// the defaults with the changes of `--costs=<op>=<cycles>,...`
+    pub fn from_args() -> Result<Costs, String> {
+        match args().find_map(|arg| arg.strip_prefix("--costs=").map(str::to_string)) {
+            Some(changes) => Costs::default().with(&changes),
+            None => Ok(Costs::default()),
+        }
+    }
+    fn with(mut self, changes: &str) -> Result<Costs, String> {
+        for change in changes.split(',') {
+            let (name, cycles) = change
+                .split_once('=')
+                .ok_or(format!("bad cost: {change}"))?;
+            let i = OPS
+                .iter()
+                .position(|(_, n, _)| *n == name)
+                .ok_or(format!("unknown operation: {name}"))?;
+            self.0[i] = cycles.parse().map_err(|_| format!("bad cost: {change}"))?;
+        }
+        Ok(self)
+    }
+    pub fn cycles(&self, tally: &Tally) -> u64 {
+        (0..OPS.len()).map(|i| self.0[i] * tally.0[i] as u64).sum()
+    }
+}
+
+/// solves the puzzles of `filename` and prints the estimated cycles of each, and of all of them
+/// by operation
+pub fn estimate(filename: &str) {
+    let costs = match Costs::from_args() {
+        Ok(costs) => costs,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+    println!("{filename}");
+    let file = File::open(filename).unwrap();
+    let mut total = Tally::default();
+    let (mut num_puzzles, mut num_steps) = (0, 0);
+    for (i, line) in io::BufReader::new(file).lines().enumerate() {
+        let mut board: Board<true> =
+            board_from_args(&line.unwrap()).unwrap_or_else(|err| panic!("{err}"));
+        board.solve(&Limits::default());
+        let cycles = costs.cycles(&board.tally);
+        println!(
+            "{}: steps: {}, estimated cycles: {cycles} ({:.2} s at 1 MHz)",
+            i + 1,
+            board.num_steps,
+            cycles as f64 / 1e6
+        );
+        for (sum, n) in total.0.iter_mut().zip(board.tally.0) {
+            *sum += n;
+        }
+        num_puzzles += 1;
+        num_steps += board.num_steps;
+    }
+    let cycles = costs.cycles(&total);
+    println!(
+        "{num_puzzles} puzzles, {num_steps} steps, estimated cycles: {cycles} ({:.1} s at 1 MHz)",
+        cycles as f64 / 1e6
+    );
+    for (op, name, _) in OPS {
+        let op_cycles = costs.0[op as usize] * total.get(op) as u64;
+        println!(
+            "{name:>16}: {:>12} x {:>3} = {op_cycles:>13} cycles ({:.1}%)",
+            total.get(op),
+            costs.0[op as usize],
+            100.0 * op_cycles as f64 / cycles.max(1) as f64
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::Layout;
+    use crate::port::Port;
+    use crate::Selection;
+    const LINE: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+    fn solve(selection: Selection, ordered_remove: bool) -> Board<true> {
+        let mut board = Board::parse(LINE, selection);
+        board.ordered_remove = ordered_remove;
+        board.solve(&Limits::default());
+        board
+    }
+    #[test]
+    fn test_estimate() {
+        // the defaults are close to the emulator for the port
+        let board = solve(Selection::Rescan, false);
+        let estimate = Costs::default().cycles(&board.tally) as f64;
+        let run = Port::new(Layout::default()).unwrap().run(LINE).unwrap();
+        assert!(
+            (estimate / run.cycles as f64 - 1.0).abs() < 0.05,
+            "{estimate} {run:?}"
+        );
+        assert_eq!(board.tally.get(Op::Step), board.num_steps);
+        let mut untallied = Board::from_line(LINE, Selection::Rescan);
+        untallied.solve(&Limits::default());
+        assert_eq!(untallied.tally, Tally::default());
+        assert_eq!(board.tally.get(Op::Count), 0);
+        assert_eq!(board.tally.get(Op::Link), 0);
+
+        // counts and buckets are only tallied when used
+        let counts = solve(Selection::Counts, false);
+        assert_eq!(counts.num_steps, board.num_steps);
+        assert!(counts.tally.get(Op::Count) > 0);
+        assert_eq!(counts.tally.get(Op::Link), 0);
+        assert!(solve(Selection::Bucket, false).tally.get(Op::Link) > 0);
+
+        // the ordered remove moves fields, but finds the same solution
+        let ordered = solve(Selection::Rescan, true);
+        assert_eq!(ordered.fields, board.fields);
+        assert!(ordered.tally.get(Op::Shift) > board.tally.get(Op::Shift));
+        assert_eq!(ordered.tally.get(Op::SwapRemove), 0);
+    }
+    #[test]
+    fn test_costs() {
+        let mut tally = Tally::default();
+        tally.add(Op::Lookup, 10);
+        tally.add(Op::Shift, 2);
+        assert_eq!(Costs::default().cycles(&tally), 10 * 4 + 2 * 19);
+        let costs = Costs::default().with("lookup=5,shift=0").unwrap();
+        assert_eq!(costs.cycles(&tally), 50);
+        assert!(Costs::default().with("lookups=5").is_err());
+        assert!(Costs::default().with("lookup").is_err());
+        assert!(Costs::default().with("lookup=-1").is_err());
+    }
+}
@@ -0,0 +1,647 @@
+//! A cycle counting NMOS 6502 with the documented opcodes and 64 KB of RAM, to run the port of
+//! `asm` without real hardware. Cycles follow the datasheet: one more for reads that cross a page
+//! with an index, and for taken branches one more plus one if the target is on another page.
+//! Undocumented opcodes stop the emulation with an error.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Adc,
+    And,
+    Asl,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Jmp,
+    Jsr,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rol,
+    Ror,
+    Rti,
+    Rts,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Sta,
+    Stx,
+    Sty,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+}
+impl Op {
+    /// the mnemonic in lower case, like the assembly of `asm`
+    pub fn name(self) -> String {
+        format!("{self:?}").to_lowercase()
+    }
+    // the indexed reads that take a cycle more when they cross a page. Stores and
+    // read-modify-write instructions always take the longer time.
+    fn page_penalty(self) -> bool {
+        !matches!(
+            self,
+            Op::Sta | Op::Asl | Op::Lsr | Op::Rol | Op::Ror | Op::Inc | Op::Dec
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+impl Mode {
+    /// the size of the operand in bytes
+    pub fn operand_size(self) -> u16 {
+        match self {
+            Mode::Implied | Mode::Accumulator => 0,
+            Mode::Absolute | Mode::AbsoluteX | Mode::AbsoluteY | Mode::Indirect => 2,
+            _ => 1,
+        }
+    }
+}
+
+use Mode::*;
+
+/// every documented opcode with its base number of cycles
+#[rustfmt::skip]
+pub const OPCODES: [(u8, Op, Mode, u8); 151] = [
+    (0x69, Op::Adc, Immediate, 2), (0x65, Op::Adc, ZeroPage, 3), (0x75, Op::Adc, ZeroPageX, 4),
+    (0x6d, Op::Adc, Absolute, 4), (0x7d, Op::Adc, AbsoluteX, 4), (0x79, Op::Adc, AbsoluteY, 4),
+    (0x61, Op::Adc, IndirectX, 6), (0x71, Op::Adc, IndirectY, 5),
+    (0x29, Op::And, Immediate, 2), (0x25, Op::And, ZeroPage, 3), (0x35, Op::And, ZeroPageX, 4),
+    (0x2d, Op::And, Absolute, 4), (0x3d, Op::And, AbsoluteX, 4), (0x39, Op::And, AbsoluteY, 4),
+    (0x21, Op::And, IndirectX, 6), (0x31, Op::And, IndirectY, 5),
+    (0x0a, Op::Asl, Accumulator, 2), (0x06, Op::Asl, ZeroPage, 5), (0x16, Op::Asl, ZeroPageX, 6),
+    (0x0e, Op::Asl, Absolute, 6), (0x1e, Op::Asl, AbsoluteX, 7),
+    (0x90, Op::Bcc, Relative, 2), (0xb0, Op::Bcs, Relative, 2), (0xf0, Op::Beq, Relative, 2),
+    (0x30, Op::Bmi, Relative, 2), (0xd0, Op::Bne, Relative, 2), (0x10, Op::Bpl, Relative, 2),
+    (0x50, Op::Bvc, Relative, 2), (0x70, Op::Bvs, Relative, 2),
+    (0x24, Op::Bit, ZeroPage, 3), (0x2c, Op::Bit, Absolute, 4),
+    (0x00, Op::Brk, Implied, 7),
+    (0x18, Op::Clc, Implied, 2), (0xd8, Op::Cld, Implied, 2), (0x58, Op::Cli, Implied, 2),
+    (0xb8, Op::Clv, Implied, 2),
+    (0xc9, Op::Cmp, Immediate, 2), (0xc5, Op::Cmp, ZeroPage, 3), (0xd5, Op::Cmp, ZeroPageX, 4),
+    (0xcd, Op::Cmp, Absolute, 4), (0xdd, Op::Cmp, AbsoluteX, 4), (0xd9, Op::Cmp, AbsoluteY, 4),
+    (0xc1, Op::Cmp, IndirectX, 6), (0xd1, Op::Cmp, IndirectY, 5),
+    (0xe0, Op::Cpx, Immediate, 2), (0xe4, Op::Cpx, ZeroPage, 3), (0xec, Op::Cpx, Absolute, 4),
+    (0xc0, Op::Cpy, Immediate, 2), (0xc4, Op::Cpy, ZeroPage, 3), (0xcc, Op::Cpy, Absolute, 4),
+    (0xc6, Op::Dec, ZeroPage, 5), (0xd6, Op::Dec, ZeroPageX, 6), (0xce, Op::Dec, Absolute, 6),
+    (0xde, Op::Dec, AbsoluteX, 7),
+    (0xca, Op::Dex, Implied, 2), (0x88, Op::Dey, Implied, 2),
+    (0x49, Op::Eor, Immediate, 2), (0x45, Op::Eor, ZeroPage, 3), (0x55, Op::Eor, ZeroPageX, 4),
+    (0x4d, Op::Eor, Absolute, 4), (0x5d, Op::Eor, AbsoluteX, 4), (0x59, Op::Eor, AbsoluteY, 4),
+    (0x41, Op::Eor, IndirectX, 6), (0x51, Op::Eor, IndirectY, 5),
+    (0xe6, Op::Inc, ZeroPage, 5), (0xf6, Op::Inc, ZeroPageX, 6), (0xee, Op::Inc, Absolute, 6),
+    (0xfe, Op::Inc, AbsoluteX, 7),
+    (0xe8, Op::Inx, Implied, 2), (0xc8, Op::Iny, Implied, 2),
+    (0x4c, Op::Jmp, Absolute, 3), (0x6c, Op::Jmp, Indirect, 5),
+    (0x20, Op::Jsr, Absolute, 6),
+    (0xa9, Op::Lda, Immediate, 2), (0xa5, Op::Lda, ZeroPage, 3), (0xb5, Op::Lda, ZeroPageX, 4),
+    (0xad, Op::Lda, Absolute, 4), (0xbd, Op::Lda, AbsoluteX, 4), (0xb9, Op::Lda, AbsoluteY, 4),
+    (0xa1, Op::Lda, IndirectX, 6), (0xb1, Op::Lda, IndirectY, 5),
+    (0xa2, Op::Ldx, Immediate, 2), (0xa6, Op::Ldx, ZeroPage, 3), (0xb6, Op::Ldx, ZeroPageY, 4),
+    (0xae, Op::Ldx, Absolute, 4), (0xbe, Op::Ldx, AbsoluteY, 4),
+    (0xa0, Op::Ldy, Immediate, 2), (0xa4, Op::Ldy, ZeroPage, 3), (0xb4, Op::Ldy, ZeroPageX, 4),
+    (0xac, Op::Ldy, Absolute, 4), (0xbc, Op::Ldy, AbsoluteX, 4),
+    (0x4a, Op::Lsr, Accumulator, 2), (0x46, Op::Lsr, ZeroPage, 5), (0x56, Op::Lsr, ZeroPageX, 6),
+    (0x4e, Op::Lsr, Absolute, 6), (0x5e, Op::Lsr, AbsoluteX, 7),
+    (0xea, Op::Nop, Implied, 2),
+    (0x09, Op::Ora, Immediate, 2), (0x05, Op::Ora, ZeroPage, 3), (0x15, Op::Ora, ZeroPageX, 4),
+    (0x0d, Op::Ora, Absolute, 4), (0x1d, Op::Ora, AbsoluteX, 4), (0x19, Op::Ora, AbsoluteY, 4),
+    (0x01, Op::Ora, IndirectX, 6), (0x11, Op::Ora, IndirectY, 5),
+    (0x48, Op::Pha, Implied, 3), (0x08, Op::Php, Implied, 3), (0x68, Op::Pla, Implied, 4),
+    (0x28, Op::Plp, Implied, 4),
+    (0x2a, Op::Rol, Accumulator, 2), (0x26, Op::Rol, ZeroPage, 5), (0x36, Op::Rol, ZeroPageX, 6),
+    (0x2e, Op::Rol, Absolute, 6), (0x3e, Op::Rol, AbsoluteX, 7),
+    (0x6a, Op::Ror, Accumulator, 2), (0x66, Op::Ror, ZeroPage, 5), (0x76, Op::Ror, ZeroPageX, 6),
+    (0x6e, Op::Ror, Absolute, 6), (0x7e, Op::Ror, AbsoluteX, 7),
+    (0x40, Op::Rti, Implied, 6), (0x60, Op::Rts, Implied, 6),
+    (0xe9, Op::Sbc, Immediate, 2), (0xe5, Op::Sbc, ZeroPage, 3), (0xf5, Op::Sbc, ZeroPageX, 4),
+    (0xed, Op::Sbc, Absolute, 4), (0xfd, Op::Sbc, AbsoluteX, 4), (0xf9, Op::Sbc, AbsoluteY, 4),
+    (0xe1, Op::Sbc, IndirectX, 6), (0xf1, Op::Sbc, IndirectY, 5),
+    (0x38, Op::Sec, Implied, 2), (0xf8, Op::Sed, Implied, 2), (0x78, Op::Sei, Implied, 2),
+    (0x85, Op::Sta, ZeroPage, 3), (0x95, Op::Sta, ZeroPageX, 4), (0x8d, Op::Sta, Absolute, 4),
+    (0x9d, Op::Sta, AbsoluteX, 5), (0x99, Op::Sta, AbsoluteY, 5), (0x81, Op::Sta, IndirectX, 6),
+    (0x91, Op::Sta, IndirectY, 6),
+    (0x86, Op::Stx, ZeroPage, 3), (0x96, Op::Stx, ZeroPageY, 4), (0x8e, Op::Stx, Absolute, 4),
+    (0x84, Op::Sty, ZeroPage, 3), (0x94, Op::Sty, ZeroPageX, 4), (0x8c, Op::Sty, Absolute, 4),
+    (0xaa, Op::Tax, Implied, 2), (0xa8, Op::Tay, Implied, 2), (0xba, Op::Tsx, Implied, 2),
+    (0x8a, Op::Txa, Implied, 2), (0x9a, Op::Txs, Implied, 2), (0x98, Op::Tya, Implied, 2),
+];
+
+// OPCODES by opcode
+const DECODE: [Option<(Op, Mode, u8)>; 256] = gen_decode();
+
+const fn gen_decode() -> [Option<(Op, Mode, u8)>; 256] {
+    let mut decode = [None; 256];
+    let mut i = 0;
+    while i < OPCODES.len() {
+        let (code, op, mode, cycles) = OPCODES[i];
+        decode[code as usize] = Some((op, mode, cycles));
+        i += 1;
+    }
+    decode
+}
+
+/// the opcode of `mnemonic` in `mode`, if it has one
+pub fn encode(mnemonic: &str, mode: Mode) -> Option<u8> {
+    OPCODES
+        .iter()
+        .find(|(_, op, m, _)| *m == mode && op.name() == mnemonic)
+        .map(|(code, ..)| *code)
+}
+
+const CARRY: u8 = 0b00000001;
+const ZERO: u8 = 0b00000010;
+const INTERRUPT: u8 = 0b00000100;
+const DECIMAL: u8 = 0b00001000;
+const BREAK: u8 = 0b00010000;
+const UNUSED: u8 = 0b00100000;
+const OVERFLOW: u8 = 0b01000000;
+const NEGATIVE: u8 = 0b10000000;
+
+#[derive(Clone)]
+pub struct Cpu {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    pub status: u8,
+    pub cycles: u64,
+    pub memory: Vec<u8>,
+}
+impl Default for Cpu {
+    /// the state after a reset, with cleared memory
+    fn default() -> Self {
+        Cpu {
+            a: 0,
+            x: 0,
+            y: 0,
+            sp: 0xfd,
+            pc: 0,
+            status: UNUSED | INTERRUPT,
+            cycles: 0,
+            memory: vec![0; 0x10000],
+        }
+    }
+}
+impl Cpu {
+    pub fn load(&mut self, address: u16, bytes: &[u8]) {
+        self.memory[address as usize..address as usize + bytes.len()].copy_from_slice(bytes);
+    }
+    /// runs from the current `pc` until it reaches `halt`, or fails after `max_cycles`
+    pub fn run(&mut self, halt: u16, max_cycles: u64) -> Result<(), String> {
+        while self.pc != halt {
+            if self.cycles >= max_cycles {
+                return Err(format!(
+                    "still running at ${:04x} after {max_cycles} cycles",
+                    self.pc
+                ));
+            }
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+    fn write(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+    fn read16(&self, address: u16) -> u16 {
+        u16::from_le_bytes([self.read(address), self.read(address.wrapping_add(1))])
+    }
+    // a pointer in zero page, whose high byte wraps around to $00
+    fn read16_zero_page(&self, address: u8) -> u16 {
+        u16::from_le_bytes([
+            self.read(address as u16),
+            self.read(address.wrapping_add(1) as u16),
+        ])
+    }
+    fn fetch(&mut self) -> u8 {
+        let value = self.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+        value
+    }
+    fn fetch16(&mut self) -> u16 {
+        u16::from_le_bytes([self.fetch(), self.fetch()])
+    }
+    fn push(&mut self, value: u8) {
+        self.write(0x100 | self.sp as u16, value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+    fn pull(&mut self) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        self.read(0x100 | self.sp as u16)
+    }
+    fn flag(&self, flag: u8) -> bool {
+        self.status & flag != 0
+    }
+    fn set_flag(&mut self, flag: u8, on: bool) {
+        if on {
+            self.status |= flag;
+        } else {
+            self.status &= !flag;
+        }
+    }
+    fn set_zn(&mut self, value: u8) {
+        self.set_flag(ZERO, value == 0);
+        self.set_flag(NEGATIVE, value & 0x80 != 0);
+    }
+
+    // the effective address of the operand, and whether indexing crossed a page
+    fn address(&mut self, mode: Mode) -> (u16, bool) {
+        let indexed = |base: u16, index: u8| {
+            let address = base.wrapping_add(index as u16);
+            (address, address & 0xff00 != base & 0xff00)
+        };
+        match mode {
+            Implied | Accumulator => (0, false),
+            Immediate | Relative => {
+                let address = self.pc;
+                self.pc = self.pc.wrapping_add(1);
+                (address, false)
+            }
+            ZeroPage => (self.fetch() as u16, false),
+            ZeroPageX => (self.fetch().wrapping_add(self.x) as u16, false),
+            ZeroPageY => (self.fetch().wrapping_add(self.y) as u16, false),
+            Absolute => (self.fetch16(), false),
+            AbsoluteX => {
+                let base = self.fetch16();
+                indexed(base, self.x)
+            }
+            AbsoluteY => {
+                let base = self.fetch16();
+                indexed(base, self.y)
+            }
+            Indirect => {
+                // the pointer never crosses a page: JMP ($10ff) reads $10ff and $1000
+                let pointer = self.fetch16();
+                let high = (pointer & 0xff00) | (pointer.wrapping_add(1) & 0x00ff);
+                (
+                    u16::from_le_bytes([self.read(pointer), self.read(high)]),
+                    false,
+                )
+            }
+            IndirectX => {
+                let pointer = self.fetch().wrapping_add(self.x);
+                (self.read16_zero_page(pointer), false)
+            }
+            IndirectY => {
+                let pointer = self.fetch();
+                let base = self.read16_zero_page(pointer);
+                indexed(base, self.y)
+            }
+        }
+    }
+
+    /// executes one instruction
+    pub fn step(&mut self) -> Result<(), String> {
+        let pc = self.pc;
+        let opcode = self.fetch();
+        let Some((op, mode, cycles)) = DECODE[opcode as usize] else {
+            return Err(format!("undocumented opcode ${opcode:02x} at ${pc:04x}"));
+        };
+        self.cycles += cycles as u64;
+        let (address, crossed) = self.address(mode);
+        if crossed && op.page_penalty() {
+            self.cycles += 1;
+        }
+        match op {
+            Op::Lda => {
+                self.a = self.read(address);
+                self.set_zn(self.a);
+            }
+            Op::Ldx => {
+                self.x = self.read(address);
+                self.set_zn(self.x);
+            }
+            Op::Ldy => {
+                self.y = self.read(address);
+                self.set_zn(self.y);
+            }
+            Op::Sta => self.write(address, self.a),
+            Op::Stx => self.write(address, self.x),
+            Op::Sty => self.write(address, self.y),
+            Op::Tax => {
+                self.x = self.a;
+                self.set_zn(self.x);
+            }
+            Op::Tay => {
+                self.y = self.a;
+                self.set_zn(self.y);
+            }
+            Op::Tsx => {
+                self.x = self.sp;
+                self.set_zn(self.x);
+            }
+            Op::Txa => {
+                self.a = self.x;
+                self.set_zn(self.a);
+            }
+            Op::Txs => self.sp = self.x,
+            Op::Tya => {
+                self.a = self.y;
+                self.set_zn(self.a);
+            }
+            Op::Pha => self.push(self.a),
+            Op::Php => self.push(self.status | BREAK | UNUSED),
+            Op::Pla => {
+                self.a = self.pull();
+                self.set_zn(self.a);
+            }
+            Op::Plp => self.status = (self.pull() & !BREAK) | UNUSED,
+            Op::And => {
+                self.a &= self.read(address);
+                self.set_zn(self.a);
+            }
+            Op::Ora => {
+                self.a |= self.read(address);
+                self.set_zn(self.a);
+            }
+            Op::Eor => {
+                self.a ^= self.read(address);
+                self.set_zn(self.a);
+            }
+            Op::Bit => {
+                let value = self.read(address);
+                self.set_flag(ZERO, self.a & value == 0);
+                self.set_flag(NEGATIVE, value & NEGATIVE != 0);
+                self.set_flag(OVERFLOW, value & OVERFLOW != 0);
+            }
+            Op::Adc => self.adc(self.read(address)),
+            Op::Sbc => self.sbc(self.read(address)),
+            Op::Cmp => self.compare(self.a, self.read(address)),
+            Op::Cpx => self.compare(self.x, self.read(address)),
+            Op::Cpy => self.compare(self.y, self.read(address)),
+            Op::Inc | Op::Dec => {
+                let value = self.read(address);
+                let value = if op == Op::Inc {
+                    value.wrapping_add(1)
+                } else {
+                    value.wrapping_sub(1)
+                };
+                self.write(address, value);
+                self.set_zn(value);
+            }
+            Op::Inx => {
+                self.x = self.x.wrapping_add(1);
+                self.set_zn(self.x);
+            }
+            Op::Iny => {
+                self.y = self.y.wrapping_add(1);
+                self.set_zn(self.y);
+            }
+            Op::Dex => {
+                self.x = self.x.wrapping_sub(1);
+                self.set_zn(self.x);
+            }
+            Op::Dey => {
+                self.y = self.y.wrapping_sub(1);
+                self.set_zn(self.y);
+            }
+            Op::Asl | Op::Lsr | Op::Rol | Op::Ror => {
+                let value = match mode {
+                    Accumulator => self.a,
+                    _ => self.read(address),
+                };
+                let carry = self.flag(CARRY) as u8;
+                let (result, carry_out) = match op {
+                    Op::Asl => (value << 1, value & 0x80 != 0),
+                    Op::Lsr => (value >> 1, value & 0x01 != 0),
+                    Op::Rol => ((value << 1) | carry, value & 0x80 != 0),
+                    _ => ((value >> 1) | (carry << 7), value & 0x01 != 0),
+                };
+                self.set_flag(CARRY, carry_out);
+                self.set_zn(result);
+                match mode {
+                    Accumulator => self.a = result,
+                    _ => self.write(address, result),
+                }
+            }
+            Op::Bcc => self.branch(address, !self.flag(CARRY)),
+            Op::Bcs => self.branch(address, self.flag(CARRY)),
+            Op::Beq => self.branch(address, self.flag(ZERO)),
+            Op::Bne => self.branch(address, !self.flag(ZERO)),
+            Op::Bmi => self.branch(address, self.flag(NEGATIVE)),
+            Op::Bpl => self.branch(address, !self.flag(NEGATIVE)),
+            Op::Bvs => self.branch(address, self.flag(OVERFLOW)),
+            Op::Bvc => self.branch(address, !self.flag(OVERFLOW)),
+            Op::Jmp => self.pc = address,
+            Op::Jsr => {
+                // the return address points to the last byte of the jsr
+                let [low, high] = self.pc.wrapping_sub(1).to_le_bytes();
+                self.push(high);
+                self.push(low);
+                self.pc = address;
+            }
+            Op::Rts => {
+                let low = self.pull();
+                let high = self.pull();
+                self.pc = u16::from_le_bytes([low, high]).wrapping_add(1);
+            }
+            Op::Brk => {
+                let [low, high] = self.pc.wrapping_add(1).to_le_bytes();
+                self.push(high);
+                self.push(low);
+                self.push(self.status | BREAK | UNUSED);
+                self.set_flag(INTERRUPT, true);
+                self.pc = self.read16(0xfffe);
+            }
+            Op::Rti => {
+                self.status = (self.pull() & !BREAK) | UNUSED;
+                let low = self.pull();
+                let high = self.pull();
+                self.pc = u16::from_le_bytes([low, high]);
+            }
+            Op::Clc => self.set_flag(CARRY, false),
+            Op::Cld => self.set_flag(DECIMAL, false),
+            Op::Cli => self.set_flag(INTERRUPT, false),
+            Op::Clv => self.set_flag(OVERFLOW, false),
+            Op::Sec => self.set_flag(CARRY, true),
+            Op::Sed => self.set_flag(DECIMAL, true),
+            Op::Sei => self.set_flag(INTERRUPT, true),
+            Op::Nop => (),
+        }
+        Ok(())
+    }
+
+    fn branch(&mut self, address: u16, taken: bool) {
+        if !taken {
+            return;
+        }
+        let offset = self.read(address) as i8;
+        let target = self.pc.wrapping_add(offset as u16);
+        self.cycles += 1 + (target & 0xff00 != self.pc & 0xff00) as u64;
+        self.pc = target;
+    }
+    fn compare(&mut self, register: u8, value: u8) {
+        self.set_flag(CARRY, register >= value);
+        self.set_zn(register.wrapping_sub(value));
+    }
+    fn adc(&mut self, value: u8) {
+        let carry = self.flag(CARRY) as u16;
+        let binary = self.a as u16 + value as u16 + carry;
+        if !self.flag(DECIMAL) {
+            self.set_flag(CARRY, binary > 0xff);
+            self.set_flag(
+                OVERFLOW,
+                (self.a ^ binary as u8) & (value ^ binary as u8) & 0x80 != 0,
+            );
+            self.a = binary as u8;
+            self.set_zn(self.a);
+            return;
+        }
+        // NMOS decimal mode: Z comes from the binary sum, N and V from the sum before the high
+        // digit is adjusted
+        let mut low = (self.a & 0x0f) as u16 + (value & 0x0f) as u16 + carry;
+        let mut high = (self.a >> 4) as u16 + (value >> 4) as u16;
+        if low > 9 {
+            low += 6;
+        }
+        if low > 0x0f {
+            high += 1;
+        }
+        self.set_flag(ZERO, binary as u8 == 0);
+        self.set_flag(NEGATIVE, high & 0x08 != 0);
+        self.set_flag(
+            OVERFLOW,
+            (self.a ^ value) & 0x80 == 0 && (self.a ^ (high << 4) as u8) & 0x80 != 0,
+        );
+        if high > 9 {
+            high += 6;
+        }
+        self.set_flag(CARRY, high > 0x0f);
+        self.a = ((high << 4) | (low & 0x0f)) as u8;
+    }
+    fn sbc(&mut self, value: u8) {
+        let borrow = !self.flag(CARRY) as i16;
+        let binary = self.a as i16 - value as i16 - borrow;
+        let result = binary as u8;
+        // NMOS decimal mode sets the flags like binary mode
+        self.set_flag(CARRY, binary >= 0);
+        self.set_flag(OVERFLOW, (self.a ^ value) & (self.a ^ result) & 0x80 != 0);
+        self.set_zn(result);
+        if !self.flag(DECIMAL) {
+            self.a = result;
+            return;
+        }
+        let mut low = (self.a & 0x0f) as i16 - (value & 0x0f) as i16 - borrow;
+        let mut high = (self.a >> 4) as i16 - (value >> 4) as i16;
+        if low < 0 {
+            low -= 6;
+            high -= 1;
+        }
+        if high < 0 {
+            high -= 6;
+        }
+        self.a = ((high << 4) | (low & 0x0f)) as u8;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    // runs `program` at $0200 until the brk at its end
+    fn run(program: &[u8]) -> Cpu {
+        let mut cpu = Cpu::default();
+        cpu.load(0x0200, program);
+        cpu.pc = 0x0200;
+        let halt = 0x0200 + program.len() as u16 - 1;
+        cpu.run(halt, 10_000).unwrap();
+        cpu
+    }
+    #[test]
+    fn test_opcodes() {
+        let mut codes = OPCODES.map(|(code, ..)| code);
+        codes.sort();
+        codes.windows(2).for_each(|w| assert_ne!(w[0], w[1]));
+        assert_eq!(encode("lda", AbsoluteY), Some(0xb9));
+        assert_eq!(encode("stx", AbsoluteX), None);
+        assert_eq!(Op::Jsr.name(), "jsr");
+    }
+    #[test]
+    fn test_cycles() {
+        // lda #$05; ldx #$ff; lda $02f0,x (crosses a page, loads 0); sta $02f0,x; beq +0 (taken); brk
+        let cpu = run(&[
+            0xa9, 0x05, 0xa2, 0xff, 0xbd, 0xf0, 0x02, 0x9d, 0xf0, 0x02, 0xf0, 0x00, 0x00,
+        ]);
+        assert_eq!(cpu.cycles, 2 + 2 + 5 + 5 + 3);
+        assert_eq!(cpu.x, 0xff);
+        // a loop of 10: ldx #10; dex; bne -3; brk
+        let cpu = run(&[0xa2, 0x0a, 0xca, 0xd0, 0xfd, 0x00]);
+        assert_eq!(cpu.cycles, 2 + 10 * 2 + 9 * 3 + 2);
+        assert!(cpu.flag(ZERO));
+    }
+    #[test]
+    fn test_arithmetic() {
+        // clc; lda #$7f; adc #$01 -> $80 with overflow
+        let cpu = run(&[0x18, 0xa9, 0x7f, 0x69, 0x01, 0x00]);
+        assert_eq!(cpu.a, 0x80);
+        assert!(cpu.flag(OVERFLOW) && cpu.flag(NEGATIVE) && !cpu.flag(CARRY));
+        // sec; lda #$10; sbc #$20 -> $f0 with borrow
+        let cpu = run(&[0x38, 0xa9, 0x10, 0xe9, 0x20, 0x00]);
+        assert_eq!(cpu.a, 0xf0);
+        assert!(!cpu.flag(CARRY));
+        // sed; clc; lda #$19; adc #$28 -> $47; sec; sbc #$48 -> $99 with borrow
+        let cpu = run(&[0xf8, 0x18, 0xa9, 0x19, 0x69, 0x28, 0x38, 0xe9, 0x48, 0x00]);
+        assert_eq!(cpu.a, 0x99);
+        assert!(!cpu.flag(CARRY));
+        // jsr $020a; ... ; $020a: ldy #$42; rol a; rts
+        let mut program = vec![0xa9, 0x81, 0x18, 0x20, 0x0a, 0x02, 0x00];
+        program.resize(10, 0xea);
+        program.extend([0xa0, 0x42, 0x2a, 0x60]);
+        let mut cpu = Cpu::default();
+        cpu.load(0x0200, &program);
+        cpu.pc = 0x0200;
+        cpu.run(0x0206, 100).unwrap();
+        assert_eq!((cpu.a, cpu.y, cpu.sp), (0x02, 0x42, 0xfd));
+        assert!(cpu.flag(CARRY));
+    }
+}
@@ -0,0 +1,1375 @@
+use std::env::args;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cost::{Op, Tally};
+use report::{Format, Record, Status};
+
+mod asm;
+mod cost;
+mod cpu;
+mod port;
+mod report;
+mod snapshot;
+mod trace;
+
+// one frame per open field plus the frame that finds the board solved
+const STACK_SIZE: usize = 9 * 9 + 1;
+const FIELD_UNDEFINED: u8 = u8::MAX;
+const CANDIDATES_L_UNDEFINED: u8 = 0b00000000;
+const CANDIDATES_H_UNDEFINED: u8 = 0b10000000;
+
+#[rustfmt::skip]
+const F2H : [usize; 9 * 9] = [
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+    0,1,2,3,4,5,6,7,8, 
+];
+
+#[rustfmt::skip]
+const F2V : [usize; 9 * 9] = [
+    0,0,0,0,0,0,0,0,0,
+    1,1,1,1,1,1,1,1,1,
+    2,2,2,2,2,2,2,2,2,
+    3,3,3,3,3,3,3,3,3,
+    4,4,4,4,4,4,4,4,4,
+    5,5,5,5,5,5,5,5,5,
+    6,6,6,6,6,6,6,6,6,
+    7,7,7,7,7,7,7,7,7,
+    8,8,8,8,8,8,8,8,8,
+];
+
+#[rustfmt::skip]
+const F2B : [usize; 9 * 9] = [
+    0,0,0,1,1,1,2,2,2,
+    0,0,0,1,1,1,2,2,2,
+    0,0,0,1,1,1,2,2,2,
+    3,3,3,4,4,4,5,5,5,
+    3,3,3,4,4,4,5,5,5,
+    3,3,3,4,4,4,5,5,5,
+    6,6,6,7,7,7,8,8,8,
+    6,6,6,7,7,7,8,8,8,
+    6,6,6,7,7,7,8,8,8,
+];
+
+#[rustfmt::skip]
+const COUNT_ONES : [u8; 256] = [
+    0,1,1,2,1,2,2,3,1,2,2,3,2,3,3,4, 
+    1,2,2,3,2,3,3,4,2,3,3,4,3,4,4,5, 
+    1,2,2,3,2,3,3,4,2,3,3,4,3,4,4,5, 
+    2,3,3,4,3,4,4,5,3,4,4,5,4,5,5,6, 
+    1,2,2,3,2,3,3,4,2,3,3,4,3,4,4,5, 
+    2,3,3,4,3,4,4,5,3,4,4,5,4,5,5,6, 
+    2,3,3,4,3,4,4,5,3,4,4,5,4,5,5,6, 
+    3,4,4,5,4,5,5,6,4,5,5,6,5,6,6,7, 
+    1,2,2,3,2,3,3,4,2,3,3,4,3,4,4,5, 
+    2,3,3,4,3,4,4,5,3,4,4,5,4,5,5,6, 
+    2,3,3,4,3,4,4,5,3,4,4,5,4,5,5,6, 
+    3,4,4,5,4,5,5,6,4,5,5,6,5,6,6,7,
+    2,3,3,4,3,4,4,5,3,4,4,5,4,5,5,6, 
+    3,4,4,5,4,5,5,6,4,5,5,6,5,6,6,7, 
+    3,4,4,5,4,5,5,6,4,5,5,6,5,6,6,7, 
+    4,5,5,6,5,6,6,7,5,6,6,7,6,7,7,8,
+];
+
+#[rustfmt::skip]
+const TRAILING_ZEROS: [u8; 256] = [
+    8,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    5,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    6,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    5,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    7,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    5,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    6,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    5,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+    4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
+];
+
+#[rustfmt::skip]
+const SET_MASK: [u8; 8] = [
+    0b00000001,
+    0b00000010,
+    0b00000100,
+    0b00001000,
+    0b00010000,
+    0b00100000,
+    0b01000000,
+    0b10000000,
+];
+
+#[rustfmt::skip]
+const RESET_MASK: [u8; 8] = [
+    0b11111110,
+    0b11111101,
+    0b11111011,
+    0b11110111,
+    0b11101111,
+    0b11011111,
+    0b10111111,
+    0b01111111,
+];
+
+#[rustfmt::skip]
+const OPEN_INITIAL: [u8; 9 * 9] = [
+     0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+    10,11,12,13,14,15,16,17,18,19,
+    20,21,22,23,24,25,26,27,28,29,
+    30,31,32,33,34,35,36,37,38,39,
+    40,41,42,43,44,45,46,47,48,49,
+    50,51,52,53,54,55,56,57,58,59,
+    60,61,62,63,64,65,66,67,68,69,
+    70,71,72,73,74,75,76,77,78,79,
+    80
+];
+
+// for each field the 20 other fields sharing a row, column or box with it
+const PEERS: [[u8; 20]; 9 * 9] = gen_peers();
+
+const fn gen_peers() -> [[u8; 20]; 9 * 9] {
+    let mut peers = [[0u8; 20]; 9 * 9];
+    let mut f = 0;
+    while f < 9 * 9 {
+        let mut n = 0;
+        let mut p = 0;
+        while p < 9 * 9 {
+            if p != f && (F2H[p] == F2H[f] || F2V[p] == F2V[f] || F2B[p] == F2B[f]) {
+                peers[f][n] = p as u8;
+                n += 1;
+            }
+            p += 1;
+        }
+        f += 1;
+    }
+    peers
+}
+
+const NUM_BUCKETS: usize = 10;
+
+/// How `select_open_field` finds the open field with the fewest candidates. `Counts` and `Bucket`
+/// keep their state up to date in every `set_field` / `clear_field`, which costs more than the
+/// scan saves on 9x9 as long as the scan stops early at a field with a single candidate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Selection {
+    /// recompute the candidates of every open field (the original behaviour)
+    #[default]
+    Rescan,
+    /// scan the open fields using the incrementally maintained `counts`. Selects exactly the same
+    /// fields as `Rescan`.
+    Counts,
+    /// take the head of the lowest non-empty bucket. Ties are broken differently, so step counts
+    /// differ from the scanning variants.
+    Bucket,
+}
+
+/// Bounds on `Board::solve`. The default is unbounded.
+#[derive(Clone, Debug, Default)]
+struct Limits {
+    max_steps: Option<usize>,
+    deadline: Option<Instant>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+impl Limits {
+    // the deadline and the cancellation token are only checked every this many steps
+    const CHECK_INTERVAL: usize = 1024;
+
+    /// parses `--max-steps=<n>` and `--timeout=<ms>` from the command line. The deadline is
+    /// relative to the time of the call.
+    fn from_args() -> Limits {
+        let arg = |prefix: &str| {
+            args().find_map(|arg| {
+                arg.strip_prefix(prefix)
+                    .map(|v| v.parse::<u64>().expect("bad limit"))
+            })
+        };
+        Limits {
+            max_steps: arg("--max-steps=").map(|steps| steps as usize),
+            deadline: arg("--timeout=").map(|ms| Instant::now() + Duration::from_millis(ms)),
+            cancel: None,
+        }
+    }
+    /// whether a limit is hit after `num_steps`. The deadline and the cancellation token are
+    /// checked every `CHECK_INTERVAL` steps from `start`, the steps when the search was entered.
+    fn exceeded(&self, num_steps: usize, start: usize) -> bool {
+        if self.max_steps.is_some_and(|max| num_steps >= max) {
+            return true;
+        }
+        (num_steps - start).is_multiple_of(Self::CHECK_INTERVAL)
+            && (self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+                || self
+                    .cancel
+                    .as_ref()
+                    .is_some_and(|cancel| cancel.load(Ordering::Relaxed)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SolveOutcome {
+    Solved,
+    Unsolvable,
+    /// a limit was hit. The board is back in the state it was in before `solve`.
+    Aborted {
+        steps: usize,
+        depth: usize,
+    },
+}
+
+struct Checkpoint {
+    path: PathBuf,
+    every: usize,
+}
+
+// `TALLY` turns on the tally of the cost model. It is a parameter rather than a field so that the
+// tally compiles away everywhere but in --estimate.
+#[derive(Clone)]
+struct Board<const TALLY: bool = false> {
+    open: [u8; 9 * 9],
+    num_open: u8,
+    fields: [u8; 9 * 9],
+    h_free_l: [u8; 9],
+    v_free_l: [u8; 9],
+    b_free_l: [u8; 9],
+    h_free_h: [u8; 9],
+    v_free_h: [u8; 9],
+    b_free_h: [u8; 9],
+    candidates_l_stack: [u8; STACK_SIZE],
+    candidates_h_stack: [u8; STACK_SIZE],
+    num_stack: [u8; STACK_SIZE],
+    field_stack: [u8; STACK_SIZE],
+    stack_ptr: usize,
+    max_depth: usize,
+    num_steps: usize,
+    num_solutions: usize,
+    min: u8,
+    selected_open_field: u8,
+    tmp_open_field: u8,
+    candidates_l: u8,
+    candidates_h: u8,
+    // number of candidates of every field, kept up to date for the field and its peers by
+    // set_field / clear_field. Only with Counts and Bucket.
+    counts: [u8; 9 * 9],
+    // index of each open field in `open`. Only with Bucket.
+    open_pos: [u8; 9 * 9],
+    // open fields in doubly linked lists, one per candidate count. Only with Bucket.
+    bucket_head: [u8; NUM_BUCKETS],
+    bucket_next: [u8; 9 * 9],
+    bucket_prev: [u8; 9 * 9],
+    bucket: [u8; 9 * 9],
+    selection: Selection,
+    // remove_open_ordered instead of remove_open in the search, to compare their cost
+    ordered_remove: bool,
+    // stop selecting at the first field with a single candidate, see select_open_field_rescan.
+    // Bucket always takes a field of the lowest bucket and ignores it.
+    early_exit: bool,
+    // the operations so far, for the cost model. Only with TALLY.
+    tally: Tally,
+}
+impl<const TALLY: bool> Default for Board<TALLY> {
+    fn default() -> Self {
+        Self {
+            open: OPEN_INITIAL,
+            num_open: 9 * 9,
+            fields: [FIELD_UNDEFINED; 9 * 9],
+            h_free_l: [0b11111111; 9],
+            v_free_l: [0b11111111; 9],
+            b_free_l: [0b11111111; 9],
+            h_free_h: [0b00000001; 9],
+            v_free_h: [0b00000001; 9],
+            b_free_h: [0b00000001; 9],
+            candidates_l_stack: [CANDIDATES_L_UNDEFINED; STACK_SIZE],
+            candidates_h_stack: [CANDIDATES_H_UNDEFINED; STACK_SIZE],
+            num_stack: [0u8; STACK_SIZE],
+            field_stack: [FIELD_UNDEFINED; STACK_SIZE],
+            stack_ptr: 0,
+            max_depth: 0,
+            num_steps: 0,
+            num_solutions: 0,
+            min: 0,
+            selected_open_field: 0,
+            tmp_open_field: 0,
+            candidates_l: 0,
+            candidates_h: 0,
+            counts: [9; 9 * 9],
+            open_pos: OPEN_INITIAL,
+            bucket_head: [FIELD_UNDEFINED; NUM_BUCKETS],
+            bucket_next: [FIELD_UNDEFINED; 9 * 9],
+            bucket_prev: [FIELD_UNDEFINED; 9 * 9],
+            bucket: [FIELD_UNDEFINED; 9 * 9],
+            selection: Selection::default(),
+            ordered_remove: false,
+            early_exit: true,
+            tally: Tally::default(),
+        }
+    }
+}
+impl Board {
+    pub fn from_line(line: &str, selection: Selection) -> Board {
+        Self::parse(line, selection)
+    }
+}
+impl<const TALLY: bool> Board<TALLY> {
+    /// `from_line` for either `TALLY`
+    fn parse(line: &str, selection: Selection) -> Self {
+        Self::try_parse(line, selection).unwrap_or_else(|err| panic!("{err}"))
+    }
+    fn try_parse(line: &str, selection: Selection) -> Result<Self, String> {
+        let mut board = Self {
+            selection,
+            ..Default::default()
+        };
+        for field in (0..9 * 9).rev() {
+            board.bucket_link(field);
+        }
+        let givens = line.split_whitespace().next().unwrap_or_default();
+        let len = givens.chars().count();
+        if len != 9 * 9 {
+            return Err(format!("{len} fields instead of 81"));
+        }
+        // region maps and the other constraints are only solved by iterative_generic
+        if let Some(part) = line.split_whitespace().nth(1) {
+            let key = part.split_once('=').map_or("regions", |(key, _)| key);
+            return Err(format!("unsupported constraint: {key}"));
+        }
+        let mut line = givens.chars();
+        board.field_stack[0] = 0;
+        while board.field_stack[0] < 9 * 9 {
+            let c = line.next().ok_or("line ended early")?;
+
+            if c.is_numeric() {
+                board.num_stack[0] = c.to_digit(10).ok_or(format!("bad num in input: {c}"))? as u8;
+                if board.num_stack[0] < 1 {
+                    return Err(format!("bad num in input: {}", board.num_stack[0]));
+                }
+                board.num_stack[0] -= 1;
+                board.selected_open_field = 0;
+                while board.selected_open_field < board.num_open {
+                    if board.open[board.selected_open_field as usize] == board.field_stack[0] {
+                        assert!(board.selected_open_field < u8::MAX);
+                        board.remove_open_ordered();
+                        break;
+                    }
+                    board.selected_open_field += 1;
+                }
+
+                assert!(board.num_stack[0] < 9);
+                board.set_field();
+            }
+            board.field_stack[0] += 1;
+        }
+        Ok(board)
+    }
+    fn dump(&self) {
+        fn dump_bin(v: &[u8]) {
+            for i in v {
+                print!("{:08b} ", i);
+            }
+            println!();
+        }
+        println!("feilds: {:?}", self.fields);
+        println!(
+            "open: {}\n{:?}",
+            self.num_open,
+            // &self.open[0..(self.num_open as usize)]
+            &self.open
+        );
+        println!("h low: ");
+        dump_bin(&self.h_free_l);
+
+        println!("h high: ");
+        dump_bin(&self.h_free_h);
+
+        println!("v low: ");
+        dump_bin(&self.v_free_l);
+
+        println!("v high: ");
+        dump_bin(&self.v_free_h);
+
+        println!("b low: ");
+        dump_bin(&self.b_free_l);
+
+        println!("b high: ");
+        dump_bin(&self.b_free_h);
+    }
+
+    fn set_field(&mut self) {
+        let cur_field = self.field_stack[self.stack_ptr];
+        let cur_num = self.num_stack[self.stack_ptr];
+        assert_eq!(self.fields[cur_field as usize], FIELD_UNDEFINED);
+        self.tally(Op::Call, 1);
+        self.tally(Op::Field, 1);
+        self.tally(Op::Lookup, 4);
+        self.tally(Op::BitReset, 3);
+        if self.selection != Selection::Rescan {
+            self.update_counts(cur_field, cur_num, false);
+        }
+        if cur_num < 8 {
+            bit_reset(&mut self.h_free_l[F2H[cur_field as usize]], cur_num);
+            bit_reset(&mut self.v_free_l[F2V[cur_field as usize]], cur_num);
+            bit_reset(&mut self.b_free_l[F2B[cur_field as usize]], cur_num);
+        } else {
+            let cur_num = cur_num - 8;
+            bit_reset(&mut self.h_free_h[F2H[cur_field as usize]], cur_num);
+            bit_reset(&mut self.v_free_h[F2V[cur_field as usize]], cur_num);
+            bit_reset(&mut self.b_free_h[F2B[cur_field as usize]], cur_num);
+        }
+        self.fields[cur_field as usize] = cur_num;
+    }
+    fn clear_field(&mut self) {
+        let cur_field = self.field_stack[self.stack_ptr];
+        assert_ne!(self.fields[cur_field as usize], FIELD_UNDEFINED);
+        self.tally(Op::Call, 1);
+        self.tally(Op::Field, 1);
+        self.tally(Op::Lookup, 4);
+        self.tally(Op::BitSet, 3);
+        let cur_num = self.fields[cur_field as usize];
+        if cur_num < 8 {
+            bit_set(&mut self.h_free_l[F2H[cur_field as usize]], cur_num);
+            bit_set(&mut self.v_free_l[F2V[cur_field as usize]], cur_num);
+            bit_set(&mut self.b_free_l[F2B[cur_field as usize]], cur_num);
+        } else {
+            let cur_num = cur_num - 8;
+            bit_set(&mut self.h_free_h[F2H[cur_field as usize]], cur_num);
+            bit_set(&mut self.v_free_h[F2V[cur_field as usize]], cur_num);
+            bit_set(&mut self.b_free_h[F2B[cur_field as usize]], cur_num);
+        }
+        if self.selection != Selection::Rescan {
+            self.update_counts(cur_field, self.fields[cur_field as usize], true);
+        }
+        self.fields[cur_field as usize] = FIELD_UNDEFINED;
+    }
+
+    #[cfg(test)]
+    fn has_candidate(&self, field: u8, num: u8) -> bool {
+        let field = field as usize;
+        if num < 8 {
+            self.h_free_l[F2H[field]]
+                & self.v_free_l[F2V[field]]
+                & self.b_free_l[F2B[field]]
+                & SET_MASK[num as usize]
+                != 0
+        } else {
+            self.h_free_h[F2H[field]]
+                & self.v_free_h[F2V[field]]
+                & self.b_free_h[F2B[field]]
+                & SET_MASK[num as usize - 8]
+                != 0
+        }
+    }
+    // called before a number is removed from the houses of field (set_field) / after it was given
+    // back (clear_field): every field in these houses that has num as candidate loses / gains one.
+    // Rescan keeps no counts, the callers skip the call for it so it costs nothing there.
+    fn update_counts(&mut self, field: u8, num: u8, inc: bool) {
+        self.tally(Op::Call, 1);
+        let (h_free, v_free, b_free, mask) = if num < 8 {
+            (
+                self.h_free_l,
+                self.v_free_l,
+                self.b_free_l,
+                SET_MASK[num as usize],
+            )
+        } else {
+            (
+                self.h_free_h,
+                self.v_free_h,
+                self.b_free_h,
+                SET_MASK[num as usize - 8],
+            )
+        };
+        let mut i = 0;
+        while i <= PEERS[0].len() {
+            let peer = if i < PEERS[0].len() {
+                PEERS[field as usize][i]
+            } else {
+                field
+            };
+            let p = peer as usize;
+            // the peer, its houses and the mask
+            self.tally(Op::Lookup, 5);
+            self.tally(Op::Candidates, 1);
+            let has = (h_free[F2H[p]] & v_free[F2V[p]] & b_free[F2B[p]] & mask != 0) as u8;
+            if self.selection == Selection::Counts {
+                // whether a peer has num is hard to predict, so without buckets to relink
+                // adding 0 is cheaper than a branch
+                self.tally(Op::Count, has as usize);
+                if inc {
+                    self.counts[p] += has;
+                } else {
+                    self.counts[p] -= has;
+                }
+            } else if has != 0 {
+                self.tally(Op::Count, 1);
+                let linked = self.bucket[p] != FIELD_UNDEFINED;
+                if linked {
+                    self.bucket_unlink(peer);
+                }
+                if inc {
+                    self.counts[p] += 1;
+                } else {
+                    self.counts[p] -= 1;
+                }
+                if linked {
+                    self.bucket_link(peer);
+                }
+            }
+            i += 1;
+        }
+    }
+    fn bucket_link(&mut self, field: u8) {
+        if self.selection != Selection::Bucket {
+            return;
+        }
+        self.tally(Op::Link, 1);
+        let count = self.counts[field as usize];
+        let head = self.bucket_head[count as usize];
+        self.bucket_next[field as usize] = head;
+        self.bucket_prev[field as usize] = FIELD_UNDEFINED;
+        if head != FIELD_UNDEFINED {
+            self.bucket_prev[head as usize] = field;
+        }
+        self.bucket_head[count as usize] = field;
+        self.bucket[field as usize] = count;
+    }
+    fn bucket_unlink(&mut self, field: u8) {
+        if self.selection != Selection::Bucket {
+            return;
+        }
+        self.tally(Op::Link, 1);
+        let prev = self.bucket_prev[field as usize];
+        let next = self.bucket_next[field as usize];
+        if prev != FIELD_UNDEFINED {
+            self.bucket_next[prev as usize] = next;
+        } else {
+            self.bucket_head[self.bucket[field as usize] as usize] = next;
+        }
+        if next != FIELD_UNDEFINED {
+            self.bucket_prev[next as usize] = prev;
+        }
+        self.bucket[field as usize] = FIELD_UNDEFINED;
+    }
+
+    pub fn candidates_for_tmp_field(&mut self) {
+        self.tally(Op::Call, 1);
+        self.tally(Op::Candidates, 1);
+        self.tally(Op::Lookup, 6);
+        let field = self.open[self.tmp_open_field as usize];
+        self.candidates_l = self.h_free_l[F2H[field as usize]]
+            & self.v_free_l[F2V[field as usize]]
+            & self.b_free_l[F2B[field as usize]];
+        self.candidates_h = self.h_free_h[F2H[field as usize]]
+            & self.v_free_h[F2V[field as usize]]
+            & self.b_free_h[F2B[field as usize]];
+    }
+    pub fn push_open(&mut self) {
+        let field = self.field_stack[self.stack_ptr];
+        assert!(self.num_open < 9 * 9);
+        self.tally(Op::Call, 1);
+        self.tally(Op::PushOpen, 1);
+        self.open[self.num_open as usize] = field;
+        self.num_open += 1;
+        if self.selection == Selection::Bucket {
+            self.open_pos[field as usize] = self.num_open - 1;
+            self.bucket_link(field);
+        }
+    }
+    pub fn remove_open(&mut self) {
+        let i = self.selected_open_field;
+        assert!(i < self.num_open);
+        self.tally(Op::Call, 1);
+        self.tally(Op::SwapRemove, 1);
+        self.field_stack[self.stack_ptr] = self.open[i as usize];
+        self.num_open -= 1;
+        self.open[i as usize] = self.open[self.num_open as usize];
+        if self.selection == Selection::Bucket {
+            self.bucket_unlink(self.field_stack[self.stack_ptr]);
+            self.open_pos[self.open[i as usize] as usize] = i;
+        }
+    }
+    pub fn remove_open_ordered(&mut self) -> u8 {
+        let i = self.selected_open_field;
+        // purely to keep it exactly equal to 'high level' versions for verification. 6502 port can use swap/remove version.
+
+        assert!(i < self.num_open);
+        self.tally(Op::Call, 1);
+        self.tally(Op::OrderedRemove, 1);
+        self.tally(Op::Shift, (self.num_open - i) as usize - 1);
+        let field = self.open[i as usize];
+        self.open
+            .copy_within((i as usize + 1)..(self.num_open as usize), i as usize);
+
+        self.num_open -= 1;
+        if self.selection == Selection::Bucket {
+            let mut j = i;
+            while j < self.num_open {
+                self.open_pos[self.open[j as usize] as usize] = j;
+                j += 1;
+            }
+            self.bucket_unlink(field);
+        }
+        field
+    }
+    pub fn to_line(&self) -> String {
+        self.fields
+            .iter()
+            .map(|field| match *field {
+                FIELD_UNDEFINED => '.',
+                num => (b'1' + num) as char,
+            })
+            .collect()
+    }
+    pub fn print(&self) {
+        for y in 0..9 {
+            for x in 0..9 {
+                match self.fields[y * 9 + x] {
+                    FIELD_UNDEFINED => print!(". "),
+                    num => print!("{} ", num + 1),
+                }
+            }
+            println!();
+        }
+    }
+
+    fn select_candidate(&mut self) -> u8 {
+        self.tally(Op::Call, 1);
+        self.tally(Op::SelectCandidate, 1);
+        self.tally(Op::Lookup, 1);
+        let low = self.candidates_l_stack[self.stack_ptr];
+        let high = self.candidates_h_stack[self.stack_ptr];
+        if low != 0 {
+            TRAILING_ZEROS[low as usize]
+        } else {
+            8 + TRAILING_ZEROS[high as usize]
+        }
+    }
+    fn apply_candidate(&mut self) {
+        let bit = self.num_stack[self.stack_ptr];
+        self.tally(Op::Call, 1);
+        self.tally(Op::ApplyCandidate, 1);
+        self.tally(Op::Lookup, 1);
+        self.tally(Op::BitReset, 1);
+        if bit < 8 {
+            bit_reset(&mut self.candidates_l_stack[self.stack_ptr], bit);
+        } else {
+            bit_reset(&mut self.candidates_h_stack[self.stack_ptr], bit - 8);
+        }
+    }
+    fn tally(&mut self, op: Op, n: usize) {
+        if TALLY {
+            self.tally.add(op, n);
+        }
+    }
+    fn select_open_field(&mut self) {
+        self.tally(Op::Call, 1);
+        match self.selection {
+            Selection::Rescan => self.select_open_field_rescan(),
+            Selection::Counts => self.select_open_field_counts(),
+            Selection::Bucket => self.select_open_field_bucket(),
+        }
+    }
+    fn select_open_field_counts(&mut self) {
+        let early_exit = self.early_exit;
+        self.selected_open_field = u8::MAX;
+        self.min = u8::MAX;
+        self.tmp_open_field = 0;
+        while self.tmp_open_field < self.num_open {
+            let num = self.counts[self.open[self.tmp_open_field as usize] as usize];
+            self.tally(Op::ScanField, 1);
+            self.tally(Op::Lookup, 2);
+            if num < self.min {
+                self.tally(Op::NewMin, 1);
+                self.selected_open_field = self.tmp_open_field;
+                self.min = num;
+            }
+            // same early exit as select_open_field_rescan
+            if early_exit && self.min == 1 {
+                break;
+            }
+            self.tmp_open_field += 1;
+        }
+        if self.selected_open_field == u8::MAX {
+            panic!("no minimal candidate found. should be impossible.")
+        }
+        self.store_selected_candidates();
+    }
+    fn select_open_field_bucket(&mut self) {
+        self.min = 0;
+        self.tally(Op::Lookup, 2);
+        while self.bucket_head[self.min as usize] == FIELD_UNDEFINED {
+            self.tally(Op::ScanField, 1);
+            self.min += 1;
+            if self.min as usize == NUM_BUCKETS {
+                panic!("no minimal candidate found. should be impossible.")
+            }
+        }
+        self.selected_open_field = self.open_pos[self.bucket_head[self.min as usize] as usize];
+        self.store_selected_candidates();
+    }
+    fn store_selected_candidates(&mut self) {
+        self.tmp_open_field = self.selected_open_field;
+        self.candidates_for_tmp_field();
+        self.candidates_l_stack[self.stack_ptr] = self.candidates_l;
+        self.candidates_h_stack[self.stack_ptr] = self.candidates_h;
+    }
+    fn select_open_field_rescan(&mut self) {
+        // a local, as the loop writes to self and would read the flag again in every iteration
+        let early_exit = self.early_exit;
+        self.selected_open_field = u8::MAX;
+        self.min = u8::MAX;
+        self.tmp_open_field = 0;
+        // println!("open: {:?}", open_slice);
+        while self.tmp_open_field < self.num_open {
+            self.candidates_for_tmp_field();
+            let num =
+                COUNT_ONES[self.candidates_l as usize] + COUNT_ONES[self.candidates_h as usize];
+            self.tally(Op::ScanField, 1);
+            self.tally(Op::Lookup, 2);
+            if num < self.min {
+                self.tally(Op::NewMin, 1);
+                self.selected_open_field = self.tmp_open_field;
+                self.min = num;
+                self.candidates_l_stack[self.stack_ptr] = self.candidates_l;
+                self.candidates_h_stack[self.stack_ptr] = self.candidates_h;
+            }
+            // fun fact: this check seems to make it worse... not sure why. There may be bias in the
+            // input puzzles to be harder when starting in the top left corner.
+            // keep it for consistency, --no-early-exit turns it off.
+            if early_exit && self.min == 1 {
+                break;
+            }
+            self.tmp_open_field += 1;
+        }
+        if self.selected_open_field == u8::MAX {
+            panic!("no minimal candidate found. should be impossible.")
+        }
+    }
+    fn solve(&mut self, limits: &Limits) -> SolveOutcome {
+        self.solve_traced(limits, |_| ())
+    }
+    /// like `solve`, calling `trace` once in every step, see `trace::Record`
+    fn solve_traced(&mut self, limits: &Limits, mut trace: impl FnMut(&Self)) -> SolveOutcome {
+        let start = self.num_steps;
+        loop {
+            if limits.exceeded(self.num_steps, start) {
+                return self.abort();
+            }
+            if let Some(outcome) = self.step_traced(&mut trace) {
+                return outcome;
+            }
+        }
+    }
+    // one iteration of the search loop. Some once a solution was found or nothing is left to try.
+    fn step(&mut self) -> Option<SolveOutcome> {
+        self.step_traced(&mut |_| ())
+    }
+    fn step_traced(&mut self, trace: &mut impl FnMut(&Self)) -> Option<SolveOutcome> {
+        self.max_depth = self.max_depth.max(self.stack_ptr + 1);
+        self.num_steps += 1;
+        self.tally(Op::Step, 1);
+
+        if self.candidates_h_stack[self.stack_ptr] == CANDIDATES_H_UNDEFINED {
+            if self.num_open == 0 {
+                trace(self);
+                return Some(SolveOutcome::Solved);
+            }
+            self.select_open_field();
+            if self.ordered_remove {
+                self.field_stack[self.stack_ptr] = self.remove_open_ordered();
+            } else {
+                self.remove_open();
+            }
+        } else {
+            assert_eq!(
+                self.fields[self.field_stack[self.stack_ptr] as usize],
+                self.num_stack[self.stack_ptr]
+            );
+            self.clear_field();
+        };
+        self.num_stack[self.stack_ptr] = self.select_candidate();
+        trace(self);
+        if self.num_stack[self.stack_ptr] < 9 {
+            // test candidate field:
+            // 1. knock out lowest bit
+            // 2. 'recursion'
+            // cur_candidates.bit_reset(*cur_num as usize);
+            // bit_reset88(cur_candidates_l, cur_candidates_h, *cur_num);
+            self.apply_candidate();
+            self.set_field();
+
+            self.stack_ptr += 1;
+            self.reset_frame();
+        } else {
+            // unsolvable -> return / backtrack
+            self.push_open();
+            if self.stack_ptr == 0 {
+                // nothing left to try for the first field
+                self.reset_frame();
+                return Some(SolveOutcome::Unsolvable);
+            }
+            self.stack_ptr -= 1;
+        }
+        None
+    }
+    /// like `solve`, but with `all` counts every solution in `num_solutions` instead of stopping at
+    /// the first one. With a checkpoint the board is saved every `checkpoint.every` steps and
+    /// before aborting, so the search can be picked up again with `--resume`.
+    fn enumerate(
+        &mut self,
+        limits: &Limits,
+        all: bool,
+        checkpoint: Option<&Checkpoint>,
+    ) -> io::Result<SolveOutcome> {
+        let start = self.num_steps;
+        loop {
+            if let Some(checkpoint) = checkpoint {
+                if self.num_steps.is_multiple_of(checkpoint.every) {
+                    self.save_snapshot(&checkpoint.path)?;
+                }
+            }
+            if limits.exceeded(self.num_steps, start) {
+                if let Some(checkpoint) = checkpoint {
+                    self.save_snapshot(&checkpoint.path)?;
+                }
+                return Ok(self.abort());
+            }
+            match self.step() {
+                Some(SolveOutcome::Solved) => {
+                    self.num_solutions += 1;
+                    if !all {
+                        return Ok(SolveOutcome::Solved);
+                    }
+                    if !self.skip_solution() {
+                        return Ok(SolveOutcome::Unsolvable);
+                    }
+                }
+                Some(outcome) => return Ok(outcome),
+                None => (),
+            }
+        }
+    }
+    // after step found a solution: backtrack out of it, so the next step continues with the next
+    // candidate. false if there is nothing to backtrack into (all fields were given).
+    fn skip_solution(&mut self) -> bool {
+        if self.stack_ptr == 0 {
+            return false;
+        }
+        self.stack_ptr -= 1;
+        true
+    }
+    fn reset_frame(&mut self) {
+        self.tally(Op::Call, 1);
+        self.tally(Op::ResetFrame, 1);
+        self.candidates_l_stack[self.stack_ptr] = CANDIDATES_L_UNDEFINED;
+        self.candidates_h_stack[self.stack_ptr] = CANDIDATES_H_UNDEFINED;
+        self.num_stack[self.stack_ptr] = 0u8;
+        self.field_stack[self.stack_ptr] = FIELD_UNDEFINED;
+    }
+    // undo all fields set by solve, leaving the board as it was before the search
+    fn abort(&mut self) -> SolveOutcome {
+        let depth = self.stack_ptr + 1;
+        if self.candidates_h_stack[self.stack_ptr] == CANDIDATES_H_UNDEFINED {
+            // fresh frame: no field selected yet
+            if self.stack_ptr == 0 {
+                return SolveOutcome::Aborted {
+                    steps: self.num_steps,
+                    depth,
+                };
+            }
+            self.stack_ptr -= 1;
+        }
+        loop {
+            self.clear_field();
+            self.push_open();
+            self.reset_frame();
+            if self.stack_ptr == 0 {
+                break;
+            }
+            self.stack_ptr -= 1;
+        }
+        SolveOutcome::Aborted {
+            steps: self.num_steps,
+            depth,
+        }
+    }
+}
+
+fn bit_set(v: &mut u8, bit: u8) {
+    *v |= SET_MASK[bit as usize]
+}
+fn bit_reset(v: &mut u8, bit: u8) {
+    *v &= RESET_MASK[bit as usize]
+}
+
+// the board of `line` with --counts, --bucket, --ordered-remove and --no-early-exit
+fn board_from_args<const TALLY: bool>(line: &str) -> Result<Board<TALLY>, String> {
+    let selection = if args().any(|arg| arg == "--counts") {
+        Selection::Counts
+    } else if args().any(|arg| arg == "--bucket") {
+        Selection::Bucket
+    } else {
+        Selection::Rescan
+    };
+    let mut board = Board::try_parse(line, selection)?;
+    board.ordered_remove = args().any(|arg| arg == "--ordered-remove");
+    board.early_exit = !args().any(|arg| arg == "--no-early-exit");
+    Ok(board)
+}
+
+// solves with `enumerate` and prints the result, for --all, --checkpoint and --resume
+fn enumerate_and_print(board: &mut Board, limits: &Limits, all: bool) {
+    let checkpoint = args()
+        .find_map(|arg| arg.strip_prefix("--checkpoint=").map(PathBuf::from))
+        .map(|path| Checkpoint {
+            path,
+            every: args()
+                .find_map(|arg| {
+                    arg.strip_prefix("--checkpoint-every=")
+                        .map(|every| every.parse().expect("bad checkpoint interval"))
+                })
+                .unwrap_or(1 << 20),
+        });
+    match board
+        .enumerate(limits, all, checkpoint.as_ref())
+        .expect("failed to write checkpoint")
+    {
+        SolveOutcome::Solved => {
+            board.print();
+            println!("max depth: {}, steps: {}", board.max_depth, board.num_steps);
+            println!("solved.");
+        }
+        SolveOutcome::Unsolvable if all => {
+            println!("max depth: {}, steps: {}", board.max_depth, board.num_steps);
+            println!("solutions: {}", board.num_solutions);
+        }
+        SolveOutcome::Unsolvable => println!("unsolvable"),
+        SolveOutcome::Aborted { steps, depth } => {
+            println!("aborted after {steps} steps at depth {depth}")
+        }
+    }
+}
+
+fn main() {
+    let format = Format::from_args();
+    let text = format == Format::Text;
+    // records are only written for plain solving and counting with --all
+    let unsupported = [
+        "--asm",
+        "--diff",
+        "--resume",
+        "--checkpoint",
+        "--cross-check",
+        "--estimate",
+        "--trace",
+        "-d",
+    ];
+    let option = |arg: &String| unsupported.contains(&arg.split('=').next().unwrap());
+    if let (false, Some(arg)) = (text, args().find(option)) {
+        println!("--format doesn't work with {arg}");
+        return;
+    }
+    // other sizes, box shapes and extra houses are only solved by iterative_generic
+    let generic_only = ["--size=", "--box=", "--variant="];
+    if let Some(arg) = args().find(|arg| generic_only.iter().any(|o| arg.starts_with(o))) {
+        println!("{arg} needs iterative_generic");
+        return;
+    }
+    if let Some(arg) = args().find(|arg| arg == "--asm" || arg.starts_with("--asm=")) {
+        let layout = match asm::Layout::from_args() {
+            Ok(layout) => layout,
+            Err(err) => {
+                println!("{err}");
+                return;
+            }
+        };
+        let source = asm::program(layout).source();
+        match arg.strip_prefix("--asm=") {
+            Some(path) => std::fs::write(path, source).expect("failed to write the assembly"),
+            None => print!("{source}"),
+        }
+        return;
+    }
+    if let Some(i) = args().position(|arg| arg == "--diff") {
+        let (Some(a), Some(b)) = (args().nth(i + 1), args().nth(i + 2)) else {
+            println!("--diff needs two traces");
+            return;
+        };
+        if let Err(err) = trace::diff(&a, &b) {
+            println!("{err}");
+        }
+        return;
+    }
+    let all = args().any(|arg| arg == "--all");
+    let resume = args().find_map(|arg| arg.strip_prefix("--resume=").map(PathBuf::from));
+    let enumerate = all || resume.is_some() || args().any(|arg| arg.starts_with("--checkpoint="));
+    if let Some(resume) = resume {
+        let mut board = Board::load_snapshot(&resume).expect("failed to load snapshot");
+        println!(
+            "resuming {} at step {} with {} solutions",
+            resume.display(),
+            board.num_steps,
+            board.num_solutions
+        );
+        enumerate_and_print(&mut board, &Limits::from_args(), all);
+        return;
+    }
+    let filename = {
+        let mut args = args();
+        if args.len() < 2 {
+            println!("missing filename");
+            return;
+        }
+
+        args.next_back().unwrap()
+    };
+    if args().any(|arg| arg == "--cross-check") {
+        port::cross_check(&filename);
+        return;
+    }
+    let dump = args().any(|arg| arg == "-d");
+    if args().any(|arg| arg == "--estimate") {
+        cost::estimate(&filename);
+        return;
+    }
+
+    let tracing = match trace::Tracing::from_args().transpose() {
+        Ok(tracing) => tracing,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+
+    if text {
+        println!("{filename}");
+    }
+    format.print_header();
+    let file = File::open(filename).unwrap();
+    for (i, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line.unwrap();
+        let start = Instant::now();
+        let mut board: Board = match board_from_args(&line) {
+            Ok(board) => board,
+            Err(err) if !text => {
+                eprintln!("{line}: {err}");
+                Record {
+                    puzzle: &line,
+                    solution: None,
+                    status: Status::ParseError,
+                    max_depth: 0,
+                    num_steps: 0,
+                    time: start.elapsed(),
+                }
+                .print(format);
+                continue;
+            }
+            Err(err) => panic!("{err}"),
+        };
+        if dump {
+            board.dump();
+        }
+        if text {
+            println!("=========================\nsolving:\n");
+            board.print();
+        }
+        // each puzzle gets the full timeout
+        let limits = Limits::from_args();
+        if enumerate && text {
+            enumerate_and_print(&mut board, &limits, all);
+            continue;
+        }
+        if let Some(tracing) = &tracing {
+            if let Err(err) = tracing.solve(i + 1, &line, &mut board, &limits) {
+                println!("{err}");
+            }
+            continue;
+        }
+        let initial = (all && !text).then(|| board.clone());
+        let outcome = match all {
+            true => board
+                .enumerate(&limits, true, None)
+                .expect("failed to write checkpoint"),
+            false => board.solve(&limits),
+        };
+        if !text {
+            let status = match (outcome, all, board.num_solutions) {
+                (SolveOutcome::Aborted { .. }, _, _) => Status::Aborted,
+                (_, true, 0) | (SolveOutcome::Unsolvable, false, _) => Status::Unsolvable,
+                (_, true, 1) | (SolveOutcome::Solved, false, _) => Status::Solved,
+                (_, true, _) => Status::Multiple,
+            };
+            let solution = match (status, initial) {
+                // enumerating leaves the board as it was, so the single solution is searched again
+                (Status::Solved, Some(mut initial)) => {
+                    initial.solve(&Limits::default());
+                    Some(initial.to_line())
+                }
+                (Status::Solved, None) => Some(board.to_line()),
+                _ => None,
+            };
+            Record {
+                puzzle: &line,
+                solution,
+                status,
+                max_depth: board.max_depth,
+                num_steps: board.num_steps,
+                time: start.elapsed(),
+            }
+            .print(format);
+            continue;
+        }
+        match outcome {
+            SolveOutcome::Solved => {
+                board.print();
+                println!("max depth: {}, steps: {}", board.max_depth, board.num_steps);
+                println!("solved.");
+            }
+            SolveOutcome::Unsolvable => println!("unsolvable"),
+            SolveOutcome::Aborted { steps, depth } => {
+                println!("aborted after {steps} steps at depth {depth}")
+            }
+        }
+    }
+    if text {
+        println!("end");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn gen_count_ones() {
+        let out = (0u8..=255u8).map(|i| i.count_ones()).collect::<Vec<_>>();
+        println!("{:?}", out);
+    }
+    #[test]
+    fn gen_trailing_zeros() {
+        let out = (0u8..=255u8)
+            .map(|i| i.trailing_zeros())
+            .collect::<Vec<_>>();
+        println!("{:?}", out);
+    }
+    #[test]
+    fn test_counts() {
+        use super::*;
+        let mut board = Board::from_line(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+            Selection::Bucket,
+        );
+        for step in 0..200 {
+            for field in 0..9 * 9 {
+                let num = (0..9)
+                    .filter(|n| board.has_candidate(field as u8, *n))
+                    .count();
+                assert_eq!(board.counts[field] as usize, num);
+            }
+            for i in 0..board.num_open {
+                let field = board.open[i as usize];
+                assert_eq!(board.open_pos[field as usize], i);
+                assert_eq!(board.bucket[field as usize], board.counts[field as usize]);
+            }
+            if step % 3 == 2 && board.stack_ptr > 0 {
+                // backtrack one level
+                board.stack_ptr -= 1;
+                board.clear_field();
+                board.push_open();
+            } else if board.num_open > 0 {
+                board.select_open_field();
+                board.remove_open();
+                if board.min == 0 {
+                    board.push_open();
+                    continue;
+                }
+                board.num_stack[board.stack_ptr] = board.select_candidate();
+                board.set_field();
+                board.stack_ptr += 1;
+            }
+        }
+    }
+    #[test]
+    fn test_counts_steps() {
+        use super::*;
+        // Counts has to select exactly the fields Rescan selects
+        for filename in ["top95.txt", "hardest.txt"] {
+            let file = File::open(filename).unwrap();
+            for line in io::BufReader::new(file).lines() {
+                let line = line.unwrap();
+                let mut rescan = Board::from_line(&line, Selection::Rescan);
+                let mut counts = Board::from_line(&line, Selection::Counts);
+                let outcome = rescan.solve(&Limits::default());
+                assert_eq!(counts.solve(&Limits::default()), outcome, "{line}");
+                assert_eq!(counts.num_steps, rescan.num_steps, "{line}");
+                assert_eq!(counts.fields, rescan.fields, "{line}");
+            }
+        }
+    }
+    #[test]
+    fn test_no_early_exit() {
+        use super::*;
+        let file = File::open("top95.txt").unwrap();
+        for line in io::BufReader::new(file).lines().take(10) {
+            let line = line.unwrap();
+            let mut early = Board::from_line(&line, Selection::Rescan);
+            assert_eq!(early.solve(&Limits::default()), SolveOutcome::Solved);
+            for selection in [Selection::Rescan, Selection::Counts] {
+                let mut board = Board::from_line(&line, selection);
+                board.early_exit = false;
+                assert_eq!(board.solve(&Limits::default()), SolveOutcome::Solved);
+                assert_eq!(board.fields, early.fields, "{line}");
+            }
+        }
+    }
+    #[test]
+    fn test_other_sizes() {
+        use super::*;
+        for line in ["51.........216......4..5.....16..25.", &"1".repeat(144)] {
+            let err = Board::<false>::try_parse(line, Selection::Rescan).err();
+            assert_eq!(err, Some(format!("{} fields instead of 81", line.len())));
+        }
+    }
+    #[test]
+    fn test_constraints() {
+        use super::*;
+        let jigsaw = "9......8....17...9..............54.1......79....4...2......2...6..93.....8......7 111333333111222223144552263147522663147555866447558869477858669447888869777999999";
+        let err = Board::<false>::try_parse(jigsaw, Selection::Rescan).err();
+        assert_eq!(err.as_deref(), Some("unsupported constraint: regions"));
+        let killer = format!("{} cages=a/3", ".".repeat(81));
+        let err = Board::<false>::try_parse(&killer, Selection::Rescan).err();
+        assert_eq!(err.as_deref(), Some("unsupported constraint: cages"));
+    }
+    #[test]
+    fn test_bad_num() {
+        use super::*;
+        let line = format!("0{}", ".".repeat(80));
+        let err = Board::<false>::try_parse(&line, Selection::Rescan).err();
+        assert_eq!(err.as_deref(), Some("bad num in input: 0"));
+    }
+    #[test]
+    fn test_limits() {
+        use super::*;
+        let line =
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+        let initial = Board::from_line(line, Selection::default());
+        let mut board = initial.clone();
+        let limits = Limits {
+            max_steps: Some(5000),
+            ..Default::default()
+        };
+        let outcome = board.solve(&limits);
+        assert!(matches!(outcome, SolveOutcome::Aborted { steps: 5000, .. }));
+        assert_eq!(board.fields, initial.fields);
+        assert_eq!(board.counts, initial.counts);
+        assert_eq!(board.num_open, initial.num_open);
+        assert_eq!(board.h_free_l, initial.h_free_l);
+        assert_eq!(board.stack_ptr, 0);
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let limits = Limits {
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let outcome = board.solve(&limits);
+        assert_eq!(
+            outcome,
+            SolveOutcome::Aborted {
+                steps: 5000,
+                depth: 1
+            }
+        );
+        cancel.store(false, Ordering::Relaxed);
+        assert_eq!(board.solve(&limits), SolveOutcome::Solved);
+
+        let line =
+            "12345678.........9...............................................................";
+        let mut board = Board::from_line(line, Selection::default());
+        assert_eq!(board.solve(&Limits::default()), SolveOutcome::Unsolvable);
+        assert_eq!(board.num_open, 81 - 9);
+    }
+    #[test]
+    fn test_snapshot_resume() {
+        use super::*;
+        // 5 solutions, so the enumeration takes a while
+        let line =
+            "9.6.7.4.3...4..2...7..23.1.5.....1...4.2.8.6...3.....5.3.7...5...7..5...4.5.1.7.8";
+        let mut reference = Board::from_line(line, Selection::Bucket);
+        reference.early_exit = false;
+        reference.ordered_remove = true;
+        let outcome = reference.enumerate(&Limits::default(), true, None).unwrap();
+        assert_eq!(outcome, SolveOutcome::Unsolvable);
+
+        let mut board = Board::from_line(line, Selection::Bucket);
+        board.early_exit = false;
+        board.ordered_remove = true;
+        while board.num_solutions < 2 {
+            if board.step() == Some(SolveOutcome::Solved) {
+                board.num_solutions += 1;
+                board.skip_solution();
+            }
+        }
+        let mut bytes = Vec::new();
+        board.write_snapshot(&mut bytes).unwrap();
+        let mut resumed = Board::read_snapshot(&mut &bytes[..]).unwrap();
+        assert!(!resumed.early_exit);
+        assert!(resumed.ordered_remove);
+        resumed.enumerate(&Limits::default(), true, None).unwrap();
+        assert_eq!(resumed.num_solutions, reference.num_solutions);
+        assert_eq!(resumed.num_steps, reference.num_steps);
+        assert_eq!(resumed.max_depth, reference.max_depth);
+
+        // the first entry of fields, after the header and open
+        let mut corrupt = bytes.clone();
+        corrupt[4 + 4 + 81] = 250;
+        assert!(Board::read_snapshot(&mut &corrupt[..]).is_err());
+        corrupt[4 + 4 + 81] = FIELD_UNDEFINED;
+        assert!(Board::read_snapshot(&mut &corrupt[..]).is_ok());
+
+        bytes[0] = b'X';
+        assert!(Board::read_snapshot(&mut &bytes[..]).is_err());
+    }
+    #[test]
+    fn test_snapshot_after_backtrack() {
+        use super::*;
+        let mut board = Board::from_line(&".".repeat(81), Selection::Rescan);
+        // enumerate until a frame ran out of candidates and was left
+        let exhausted = 8 + TRAILING_ZEROS[0];
+        while !board.num_stack[board.stack_ptr + 1..].contains(&exhausted) {
+            if board.step() == Some(SolveOutcome::Solved) {
+                board.num_solutions += 1;
+                board.skip_solution();
+            }
+        }
+        let mut bytes = Vec::new();
+        board.write_snapshot(&mut bytes).unwrap();
+        let mut resumed = Board::read_snapshot(&mut &bytes[..]).unwrap();
+        let limits = Limits {
+            max_steps: Some(board.num_steps + 10000),
+            ..Default::default()
+        };
+        resumed.enumerate(&limits, true, None).unwrap();
+        board.enumerate(&limits, true, None).unwrap();
+        assert_eq!(resumed.num_solutions, board.num_solutions);
+        assert_eq!(resumed.num_steps, board.num_steps);
+
+        // a frame below the stack pointer can't have run out of candidates
+        let mut live = resumed.clone();
+        live.num_stack[0] = exhausted;
+        let mut bytes = Vec::new();
+        live.write_snapshot(&mut bytes).unwrap();
+        assert!(Board::read_snapshot(&mut &bytes[..]).is_err());
+    }
+}
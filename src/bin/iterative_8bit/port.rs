@@ -0,0 +1,259 @@
+//! Runs the 6502 port of `asm` on the emulator of `cpu` and checks it against `Board::solve`.
+//!
+//! `--cross-check <file>` solves every puzzle of the file both ways, compares the solution, the
+//! steps and the depth, and reports the cycles the port took. The layout options of `asm`
+//! apply, so different layouts can be checked as well.
+
+use super::asm::{self, Image, Layout};
+use super::cpu::Cpu;
+use super::{Board, Limits, Selection, SolveOutcome, FIELD_UNDEFINED};
+use std::fs::File;
+use std::io::{self, BufRead};
+
+// far more than any puzzle takes, to stop a port that never halts
+const MAX_CYCLES: u64 = 1 << 40;
+
+/// How a puzzle was solved: what the port leaves in memory, or the same fields of `Board`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+    pub solved: bool,
+    pub fields: [u8; 9 * 9],
+    pub max_depth: usize,
+    pub num_steps: usize,
+    /// 0 for `Board`
+    pub cycles: u64,
+}
+impl Run {
+    fn reference(line: &str) -> Run {
+        let mut board = Board::from_line(line, Selection::default());
+        let outcome = board.solve(&Limits::default());
+        Run {
+            solved: outcome == SolveOutcome::Solved,
+            fields: board.fields,
+            max_depth: board.max_depth,
+            num_steps: board.num_steps,
+            cycles: 0,
+        }
+    }
+    // the differences to the run of the reference
+    fn differences(&self, reference: &Run) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.solved != reference.solved {
+            differences.push(format!("solved {} != {}", self.solved, reference.solved));
+        }
+        if self.fields != reference.fields {
+            differences.push("fields differ".to_string());
+        }
+        if self.max_depth != reference.max_depth {
+            differences.push(format!(
+                "max depth {} != {}",
+                self.max_depth, reference.max_depth
+            ));
+        }
+        if self.num_steps != reference.num_steps {
+            differences.push(format!(
+                "steps {} != {}",
+                self.num_steps, reference.num_steps
+            ));
+        }
+        differences
+    }
+}
+
+/// The assembled port, ready to be run on fresh machines.
+pub struct Port {
+    image: Image,
+}
+impl Port {
+    pub fn new(layout: Layout) -> Result<Port, String> {
+        Ok(Port {
+            image: asm::program(layout).assemble()?,
+        })
+    }
+    /// loads the program and the puzzle of `line` (like `Board::from_line`) into a machine that
+    /// was just reset and runs `rs_main` until it reaches `halt`
+    pub fn run(&self, line: &str) -> Result<Run, String> {
+        let mut cpu = Cpu::default();
+        cpu.load(self.image.origin, &self.image.bytes);
+        cpu.load(self.image.address("puzzle"), &puzzle(line)?);
+        cpu.pc = self.image.address("rs_main");
+        cpu.run(self.image.address("halt"), MAX_CYCLES)?;
+        Ok(self.result(&cpu))
+    }
+    /// like `run`, calling `trace` with the state of the machine at the same points of every
+    /// step as `Board::solve_traced`
+    pub fn run_traced(&self, line: &str, mut trace: impl FnMut(&Board)) -> Result<Run, String> {
+        let mut cpu = Cpu::default();
+        cpu.load(self.image.origin, &self.image.bytes);
+        cpu.load(self.image.address("puzzle"), &puzzle(line)?);
+        cpu.pc = self.image.address("rs_main");
+        let halt = self.image.address("halt");
+        let points = [
+            self.image.address("rs_solve@tried"),
+            self.image.address("rs_solve@solved"),
+        ];
+        while cpu.pc != halt {
+            if points.contains(&cpu.pc) {
+                trace(&self.board(&cpu));
+            }
+            if cpu.cycles >= MAX_CYCLES {
+                return Err(format!("still running after {MAX_CYCLES} cycles"));
+            }
+            cpu.step()?;
+        }
+        Ok(self.result(&cpu))
+    }
+
+    fn bytes<'a>(&self, cpu: &'a Cpu, name: &str, len: usize) -> &'a [u8] {
+        let address = self.image.address(name) as usize;
+        &cpu.memory[address..address + len]
+    }
+    fn result(&self, cpu: &Cpu) -> Run {
+        Run {
+            solved: self.bytes(cpu, "result", 1)[0] == 0,
+            fields: self.bytes(cpu, "fields", 9 * 9).try_into().unwrap(),
+            max_depth: self.bytes(cpu, "max_depth", 1)[0] as usize,
+            num_steps: u32::from_le_bytes(self.bytes(cpu, "num_steps", 4).try_into().unwrap())
+                as usize,
+            cycles: cpu.cycles,
+        }
+    }
+    /// the state of the machine as a `Board`. The port keeps no counts and buckets, so these
+    /// are left at their defaults.
+    pub fn board(&self, cpu: &Cpu) -> Board {
+        let byte = |name| self.bytes(cpu, name, 1)[0];
+        let mut board = Board {
+            num_open: byte("num_open"),
+            stack_ptr: byte("stack_ptr") as usize,
+            max_depth: byte("max_depth") as usize,
+            num_steps: u32::from_le_bytes(self.bytes(cpu, "num_steps", 4).try_into().unwrap())
+                as usize,
+            min: byte("min"),
+            selected_open_field: byte("selected_open_field"),
+            tmp_open_field: byte("tmp_open_field"),
+            candidates_l: byte("candidates_l"),
+            candidates_h: byte("candidates_h"),
+            selection: Selection::Rescan,
+            ..Default::default()
+        };
+        for (name, array) in [
+            ("open", &mut board.open[..]),
+            ("fields", &mut board.fields),
+            ("h_free_l", &mut board.h_free_l),
+            ("v_free_l", &mut board.v_free_l),
+            ("b_free_l", &mut board.b_free_l),
+            ("h_free_h", &mut board.h_free_h),
+            ("v_free_h", &mut board.v_free_h),
+            ("b_free_h", &mut board.b_free_h),
+            ("candidates_l_stack", &mut board.candidates_l_stack),
+            ("candidates_h_stack", &mut board.candidates_h_stack),
+            ("num_stack", &mut board.num_stack),
+            ("field_stack", &mut board.field_stack),
+        ] {
+            array.copy_from_slice(self.bytes(cpu, name, array.len()));
+        }
+        board
+    }
+}
+
+// the givens of a line for rs_init
+fn puzzle(line: &str) -> Result<[u8; 9 * 9], String> {
+    let mut puzzle = [FIELD_UNDEFINED; 9 * 9];
+    let mut chars = line.chars();
+    for field in puzzle.iter_mut() {
+        match chars.next() {
+            Some('0') => return Err("bad num in input: 0".to_string()),
+            Some(c) if c.is_ascii_digit() => *field = c as u8 - b'1',
+            Some(_) => (),
+            None => return Err("line ended early".to_string()),
+        }
+    }
+    Ok(puzzle)
+}
+
+/// solves the puzzles of `filename` on the port and with `Board`, and prints the cycles per
+/// puzzle and every difference
+pub fn cross_check(filename: &str) {
+    let port = match Layout::from_args().and_then(Port::new) {
+        Ok(port) => port,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+    println!("{filename}");
+    let file = File::open(filename).unwrap();
+    let (mut num_puzzles, mut num_mismatches, mut total_cycles) = (0, 0, 0);
+    for (i, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line.unwrap();
+        let reference = Run::reference(&line);
+        let differences = match port.run(&line) {
+            Ok(run) => {
+                total_cycles += run.cycles;
+                print!(
+                    "{}: steps: {}, max depth: {}, cycles: {} ({:.2} s at 1 MHz)",
+                    i + 1,
+                    run.num_steps,
+                    run.max_depth,
+                    run.cycles,
+                    run.cycles as f64 / 1e6
+                );
+                run.differences(&reference)
+            }
+            Err(err) => {
+                print!("{}: {err}", i + 1);
+                vec!["failed".to_string()]
+            }
+        };
+        num_puzzles += 1;
+        if differences.is_empty() {
+            println!(", ok");
+        } else {
+            num_mismatches += 1;
+            println!(", MISMATCH: {}", differences.join(", "));
+        }
+    }
+    println!(
+        "{num_puzzles} puzzles, {num_mismatches} mismatches, {total_cycles} cycles ({:.1} s at 1 MHz)",
+        total_cycles as f64 / 1e6
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_port() {
+        let port = Port::new(Layout::default()).unwrap();
+        for line in [
+            // solved without backtracking
+            "..3.2.6..9..3.5..1..18.64....81.29..7.......8..67.82....26.95..8..2.3..9..5.1.3..",
+            // needs a few hundred steps
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+            "12345678.........9...............................................................",
+        ] {
+            let run = port.run(line).unwrap();
+            let reference = Run::reference(line);
+            assert_eq!(run.differences(&reference), Vec::<String>::new(), "{line}");
+            assert!(run.cycles > 100 * run.num_steps as u64);
+        }
+
+        // the layout moves the state, not the search
+        let line =
+            "..3.2.6..9..3.5..1..18.64....81.29..7.......8..67.82....26.95..8..2.3..9..5.1.3..";
+        let layout = Layout {
+            zero_page: 0x02,
+            ram: 0xc000,
+            code: 0x0800,
+        };
+        let run = Port::new(layout).unwrap().run(line).unwrap();
+        assert_eq!(run.differences(&Run::reference(line)), Vec::<String>::new());
+        let overlapping = Layout {
+            ram: 0x0900,
+            ..layout
+        };
+        assert!(Port::new(overlapping).is_err());
+
+        assert!(port.run("123").is_err());
+    }
+}
@@ -0,0 +1,174 @@
+//! Results for batch runs. `--format=json|csv|line` prints one record per puzzle line instead of
+//! the boards: the puzzle, its solution, what became of it, the search statistics and the time.
+
+use serde_json::json;
+use std::env::args;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// the boards and messages as always
+    #[default]
+    Text,
+    /// one JSON object per line
+    Json,
+    /// comma separated values after a header line
+    Csv,
+    /// tab separated values, without a header
+    Line,
+}
+impl Format {
+    pub fn from_name(name: &str) -> Result<Format, String> {
+        match name {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "line" => Ok(Format::Line),
+            _ => Err(format!("unknown format: {name}")),
+        }
+    }
+    /// `--format=<name>`, text without it
+    pub fn from_args() -> Format {
+        args()
+            .find_map(|arg| arg.strip_prefix("--format=").map(Format::from_name))
+            .map(|format| format.unwrap_or_else(|err| panic!("{err}")))
+            .unwrap_or_default()
+    }
+    pub fn print_header(self) {
+        if self == Format::Csv {
+            println!("puzzle,solution,status,max_depth,num_steps,time_ms");
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Solved,
+    Unsolvable,
+    /// more than one solution
+    Multiple,
+    /// a step or time limit was hit
+    Aborted,
+    /// the puzzle line couldn't be read
+    ParseError,
+}
+impl Status {
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Solved => "solved",
+            Status::Unsolvable => "unsolvable",
+            Status::Multiple => "multiple",
+            Status::Aborted => "aborted",
+            Status::ParseError => "parse error",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Record<'a> {
+    pub puzzle: &'a str,
+    /// the (first) solution in the notation of the puzzle line
+    pub solution: Option<String>,
+    pub status: Status,
+    pub max_depth: usize,
+    pub num_steps: usize,
+    pub time: Duration,
+}
+impl Record<'_> {
+    /// the record in `format`, None for text, which is printed while solving
+    pub fn format(&self, format: Format) -> Option<String> {
+        let time_ms = self.time.as_secs_f64() * 1000.0;
+        let solution = self.solution.as_deref();
+        match format {
+            Format::Text => None,
+            Format::Json => Some(
+                json!({
+                    "puzzle": self.puzzle,
+                    "solution": solution,
+                    "status": self.status.name(),
+                    "max_depth": self.max_depth,
+                    "num_steps": self.num_steps,
+                    "time_ms": (time_ms * 1000.0).round() / 1000.0,
+                })
+                .to_string(),
+            ),
+            Format::Csv => Some(
+                [
+                    csv_field(self.puzzle),
+                    csv_field(solution.unwrap_or_default()),
+                    self.status.name().to_string(),
+                    self.max_depth.to_string(),
+                    self.num_steps.to_string(),
+                    format!("{time_ms:.3}"),
+                ]
+                .join(","),
+            ),
+            Format::Line => Some(format!(
+                "{}\t{}\t{}\t{}\t{}\t{time_ms:.3}",
+                self.puzzle,
+                solution.unwrap_or("-"),
+                self.status.name(),
+                self.max_depth,
+                self.num_steps
+            )),
+        }
+    }
+    pub fn print(&self, format: Format) {
+        if let Some(record) = self.format(format) {
+            println!("{record}");
+        }
+    }
+}
+
+// quotes a value holding commas or quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_record() {
+        let record = Record {
+            puzzle: "1...,x",
+            solution: Some("1234".to_string()),
+            status: Status::Solved,
+            max_depth: 5,
+            num_steps: 12,
+            time: Duration::from_micros(1500),
+        };
+        assert_eq!(record.format(Format::Text), None);
+        assert_eq!(
+            record.format(Format::Csv).unwrap(),
+            "\"1...,x\",1234,solved,5,12,1.500"
+        );
+        assert_eq!(
+            record.format(Format::Line).unwrap(),
+            "1...,x\t1234\tsolved\t5\t12\t1.500"
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&record.format(Format::Json).unwrap());
+        let json = json.unwrap();
+        assert_eq!(json["solution"], "1234");
+        assert_eq!(json["num_steps"], 12);
+        assert_eq!(json["time_ms"], 1.5);
+
+        let record = Record {
+            solution: None,
+            status: Status::ParseError,
+            ..record
+        };
+        assert!(record
+            .format(Format::Line)
+            .unwrap()
+            .contains("\t-\tparse error\t"));
+        let json = serde_json::from_str::<serde_json::Value>(&record.format(Format::Json).unwrap());
+        assert!(json.unwrap()["solution"].is_null());
+        assert_eq!(Format::from_name("csv"), Ok(Format::Csv));
+        assert!(Format::from_name("xml").is_err());
+    }
+}
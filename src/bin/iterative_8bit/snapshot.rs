@@ -0,0 +1,193 @@
+//! Binary snapshots of a `Board` in the middle of a search. All search state already lives in the
+//! board's flat arrays, so a snapshot is just those arrays in a fixed order (about 1 KB) and a
+//! restored board continues with exactly the steps the original would have taken.
+
+use super::{Board, Selection, FIELD_UNDEFINED, NUM_BUCKETS, STACK_SIZE, TRAILING_ZEROS};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"RS8S";
+const VERSION: u8 = 3;
+
+// bits of the flags byte
+const EARLY_EXIT: u8 = 1;
+const ORDERED_REMOVE: u8 = 2;
+
+// what `select_candidate` returns for a frame without candidates left. After backtracking it stays
+// in `num_stack` above the stack pointer.
+const EXHAUSTED: u8 = 8 + TRAILING_ZEROS[0];
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn write_usize(w: &mut impl Write, v: usize) -> io::Result<()> {
+    w.write_all(&(v as u64).to_le_bytes())
+}
+
+fn read_usize(r: &mut impl Read) -> io::Result<usize> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    usize::try_from(u64::from_le_bytes(buf)).map_err(|_| invalid("value out of range"))
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn is_field(v: u8) -> bool {
+    (v as usize) < 9 * 9
+}
+
+fn is_field_or_undefined(v: u8) -> bool {
+    v == FIELD_UNDEFINED || is_field(v)
+}
+
+// the search indexes its tables with the entries of the arrays, so a bad one would panic later on
+fn check(array: &[u8], valid: impl Fn(u8) -> bool, name: &str) -> io::Result<()> {
+    if array.iter().all(|&v| valid(v)) {
+        Ok(())
+    } else {
+        Err(invalid(&format!("bad {name} in snapshot")))
+    }
+}
+
+impl<const TALLY: bool> Board<TALLY> {
+    pub fn write_snapshot(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        let mut flags = 0;
+        if self.early_exit {
+            flags |= EARLY_EXIT;
+        }
+        if self.ordered_remove {
+            flags |= ORDERED_REMOVE;
+        }
+        w.write_all(&[VERSION, self.selection as u8, flags, self.num_open])?;
+        for array in [
+            &self.open[..],
+            &self.fields,
+            &self.h_free_l,
+            &self.v_free_l,
+            &self.b_free_l,
+            &self.h_free_h,
+            &self.v_free_h,
+            &self.b_free_h,
+            &self.candidates_l_stack,
+            &self.candidates_h_stack,
+            &self.num_stack,
+            &self.field_stack,
+            &self.counts,
+            &self.open_pos,
+            &self.bucket_head,
+            &self.bucket_next,
+            &self.bucket_prev,
+            &self.bucket,
+        ] {
+            w.write_all(array)?;
+        }
+        for v in [
+            self.stack_ptr,
+            self.max_depth,
+            self.num_steps,
+            self.num_solutions,
+        ] {
+            write_usize(w, v)?;
+        }
+        Ok(())
+    }
+
+    /// writes to a temporary file next to `path` first, so a crash while saving never destroys the
+    /// previous checkpoint.
+    pub fn save_snapshot(&self, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        {
+            let mut w = BufWriter::new(File::create(&tmp)?);
+            self.write_snapshot(&mut w)?;
+            w.flush()?;
+        }
+        fs::rename(tmp, path)
+    }
+}
+
+impl Board {
+    pub fn read_snapshot(r: &mut impl Read) -> io::Result<Board> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a rusdoku snapshot"));
+        }
+        if read_u8(r)? != VERSION {
+            return Err(invalid("unsupported snapshot version"));
+        }
+        let selection = read_u8(r)?;
+        let flags = read_u8(r)?;
+        if flags & !(EARLY_EXIT | ORDERED_REMOVE) != 0 {
+            return Err(invalid("bad flags"));
+        }
+        let mut board = Board {
+            selection: match selection {
+                0 => Selection::Rescan,
+                1 => Selection::Counts,
+                2 => Selection::Bucket,
+                _ => return Err(invalid("bad selection")),
+            },
+            early_exit: flags & EARLY_EXIT != 0,
+            ordered_remove: flags & ORDERED_REMOVE != 0,
+            num_open: read_u8(r)?,
+            ..Default::default()
+        };
+        for array in [
+            &mut board.open[..],
+            &mut board.fields,
+            &mut board.h_free_l,
+            &mut board.v_free_l,
+            &mut board.b_free_l,
+            &mut board.h_free_h,
+            &mut board.v_free_h,
+            &mut board.b_free_h,
+            &mut board.candidates_l_stack,
+            &mut board.candidates_h_stack,
+            &mut board.num_stack,
+            &mut board.field_stack,
+            &mut board.counts,
+            &mut board.open_pos,
+            &mut board.bucket_head,
+            &mut board.bucket_next,
+            &mut board.bucket_prev,
+            &mut board.bucket,
+        ] {
+            r.read_exact(array)?;
+        }
+        board.stack_ptr = read_usize(r)?;
+        board.max_depth = read_usize(r)?;
+        board.num_steps = read_usize(r)?;
+        board.num_solutions = read_usize(r)?;
+        if board.stack_ptr >= STACK_SIZE || board.num_open as usize > 9 * 9 {
+            return Err(invalid("corrupt snapshot"));
+        }
+        check(&board.open, is_field, "open")?;
+        check(&board.fields, |v| v == FIELD_UNDEFINED || v < 9, "fields")?;
+        let (frames, left) = board.num_stack.split_at(board.stack_ptr + 1);
+        check(frames, |v| v < 9, "num_stack")?;
+        check(left, |v| v < 9 || v == EXHAUSTED, "num_stack")?;
+        check(&board.field_stack, is_field_or_undefined, "field_stack")?;
+        check(&board.counts, |v| (v as usize) < NUM_BUCKETS, "counts")?;
+        check(&board.open_pos, is_field, "open_pos")?;
+        check(&board.bucket_head, is_field_or_undefined, "bucket_head")?;
+        check(&board.bucket_next, is_field_or_undefined, "bucket_next")?;
+        check(&board.bucket_prev, is_field_or_undefined, "bucket_prev")?;
+        check(
+            &board.bucket,
+            |v| v == FIELD_UNDEFINED || (v as usize) < NUM_BUCKETS,
+            "bucket",
+        )?;
+        Ok(board)
+    }
+
+    pub fn load_snapshot(path: &Path) -> io::Result<Board> {
+        Board::read_snapshot(&mut BufReader::new(File::open(path)?))
+    }
+}
@@ -0,0 +1,456 @@
+//! Step by step traces of the search, to find the step where the 6502 port and `Board` part ways.
+//! A trace has a `Record` for every step, in compact binary or as CSV. It also says which solver
+//! wrote it for which puzzle, so `--diff` can solve the puzzle again up to the first step where
+//! two traces differ and `dump` the board of either side.
+//!
+//! `--trace=<file>` writes the trace of every puzzle to `<file>` with the number of the puzzle
+//! added to the name (`trace.csv` becomes `trace-1.csv`, `trace-2.csv`, ...), in
+//! `--trace-format=binary` (the default) or `csv`. With `--port` the trace comes from the port
+//! on the emulator instead of `Board`. `--diff <a> <b>` compares two traces.
+
+use super::asm::Layout;
+use super::port::Port;
+use super::{Board, Limits, Selection, SolveOutcome};
+use std::env::args;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"RS8T";
+const VERSION: u8 = 1;
+const CSV_HEADER: &str =
+    "step,stack_ptr,selected_open_field,num_open,candidates_l,candidates_h,digit";
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// A step once it picked the digit to try: `digit` is 9 or more when no candidate is left and
+/// the step backtracks. The step that finds the board solved is recorded with its fresh frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub stack_ptr: u8,
+    pub selected_open_field: u8,
+    pub num_open: u8,
+    pub candidates_l: u8,
+    pub candidates_h: u8,
+    pub digit: u8,
+}
+impl Record {
+    pub fn of(board: &Board) -> Record {
+        Record {
+            stack_ptr: board.stack_ptr as u8,
+            selected_open_field: board.selected_open_field,
+            num_open: board.num_open,
+            candidates_l: board.candidates_l_stack[board.stack_ptr],
+            candidates_h: board.candidates_h_stack[board.stack_ptr],
+            digit: board.num_stack[board.stack_ptr],
+        }
+    }
+    fn to_bytes(self) -> [u8; 6] {
+        [
+            self.stack_ptr,
+            self.selected_open_field,
+            self.num_open,
+            self.candidates_l,
+            self.candidates_h,
+            self.digit,
+        ]
+    }
+    fn from_bytes(bytes: &[u8]) -> Record {
+        Record {
+            stack_ptr: bytes[0],
+            selected_open_field: bytes[1],
+            num_open: bytes[2],
+            candidates_l: bytes[3],
+            candidates_h: bytes[4],
+            digit: bytes[5],
+        }
+    }
+}
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "stack_ptr: {}, selected_open_field: {}, num_open: {}, candidates: {:08b} {:08b}, digit: {}",
+            self.stack_ptr,
+            self.selected_open_field,
+            self.num_open,
+            self.candidates_h,
+            self.candidates_l,
+            self.digit
+        )
+    }
+}
+
+/// which solver wrote a trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Board,
+    Port,
+}
+impl Source {
+    fn name(self) -> &'static str {
+        match self {
+            Source::Board => "board",
+            Source::Port => "port",
+        }
+    }
+    fn from_name(name: &str) -> io::Result<Source> {
+        match name {
+            "board" => Ok(Source::Board),
+            "port" => Ok(Source::Port),
+            _ => Err(invalid("unknown trace source")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Binary,
+    Csv,
+}
+
+/// Writes a trace while the solver runs.
+pub struct Writer<W: Write> {
+    w: W,
+    format: Format,
+    num_records: usize,
+}
+impl<W: Write> Writer<W> {
+    pub fn new(mut w: W, format: Format, source: Source, line: &str) -> io::Result<Writer<W>> {
+        match format {
+            Format::Binary => {
+                let line = line.as_bytes();
+                let len = u8::try_from(line.len()).map_err(|_| invalid("line too long"))?;
+                w.write_all(MAGIC)?;
+                w.write_all(&[VERSION, source as u8, len])?;
+                w.write_all(line)?;
+            }
+            Format::Csv => {
+                writeln!(w, "# source: {}", source.name())?;
+                writeln!(w, "# puzzle: {line}")?;
+                writeln!(w, "{CSV_HEADER}")?;
+            }
+        }
+        Ok(Writer {
+            w,
+            format,
+            num_records: 0,
+        })
+    }
+    pub fn record(&mut self, record: Record) -> io::Result<()> {
+        self.num_records += 1;
+        match self.format {
+            Format::Binary => self.w.write_all(&record.to_bytes()),
+            Format::Csv => {
+                write!(self.w, "{}", self.num_records)?;
+                for value in record.to_bytes() {
+                    write!(self.w, ",{value}")?;
+                }
+                writeln!(self.w)
+            }
+        }
+    }
+    /// flushes and returns the number of records
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.w.flush()?;
+        Ok(self.num_records)
+    }
+}
+
+/// A whole trace, as read back by `--diff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trace {
+    pub source: Source,
+    pub line: String,
+    pub records: Vec<Record>,
+}
+impl Trace {
+    /// reads either format
+    pub fn parse(bytes: &[u8]) -> io::Result<Trace> {
+        if let Some(rest) = bytes.strip_prefix(MAGIC) {
+            let [version, source, len, rest @ ..] = rest else {
+                return Err(invalid("truncated trace"));
+            };
+            if *version != VERSION {
+                return Err(invalid("unsupported trace version"));
+            }
+            let source = match source {
+                0 => Source::Board,
+                1 => Source::Port,
+                _ => return Err(invalid("unknown trace source")),
+            };
+            let (line, records) = rest
+                .split_at_checked(*len as usize)
+                .ok_or_else(|| invalid("truncated trace"))?;
+            if records.len() % 6 != 0 {
+                return Err(invalid("truncated trace"));
+            }
+            return Ok(Trace {
+                source,
+                line: String::from_utf8_lossy(line).into_owned(),
+                records: records.chunks(6).map(Record::from_bytes).collect(),
+            });
+        }
+        let text = std::str::from_utf8(bytes).map_err(|_| invalid("not a rusdoku trace"))?;
+        let mut lines = text.lines();
+        let mut header = |prefix: &str| {
+            lines
+                .next()
+                .and_then(|l| l.strip_prefix(prefix))
+                .map(str::to_string)
+                .ok_or_else(|| invalid("not a rusdoku trace"))
+        };
+        let source = Source::from_name(&header("# source: ")?)?;
+        let line = header("# puzzle: ")?;
+        header(CSV_HEADER)?;
+        let mut records = Vec::new();
+        for (i, row) in lines.enumerate() {
+            let values = row
+                .split(',')
+                .map(|v| v.trim().parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid("bad trace row"))?;
+            if values.len() != 7 || values[0] != i + 1 || values[1..].iter().any(|v| *v > 255) {
+                return Err(invalid("bad trace row"));
+            }
+            let bytes = values[1..].iter().map(|v| *v as u8).collect::<Vec<_>>();
+            records.push(Record::from_bytes(&bytes));
+        }
+        Ok(Trace {
+            source,
+            line,
+            records,
+        })
+    }
+    pub fn load(path: &Path) -> io::Result<Trace> {
+        Trace::parse(&fs::read(path)?)
+    }
+    /// the board at the record of `step` (counted from 1), by solving the puzzle again with the
+    /// solver that wrote the trace. None if the solver finishes before.
+    pub fn replay(&self, step: usize) -> Result<Option<Board>, String> {
+        let mut n = 0;
+        let mut state = None;
+        let trace = |board: &Board| {
+            n += 1;
+            if n == step {
+                state = Some(board.clone());
+            }
+        };
+        match self.source {
+            Source::Board => {
+                let limits = Limits {
+                    max_steps: Some(step),
+                    ..Default::default()
+                };
+                Board::from_line(&self.line, Selection::default()).solve_traced(&limits, trace);
+            }
+            Source::Port => {
+                Port::new(Layout::default())?.run_traced(&self.line, trace)?;
+            }
+        }
+        Ok(state)
+    }
+}
+
+/// the index of the first record where the traces differ, also where one of them ends early
+pub fn first_divergence(a: &[Record], b: &[Record]) -> Option<usize> {
+    (0..a.len().max(b.len())).find(|&i| a.get(i) != b.get(i))
+}
+
+/// compares the traces in the files `a` and `b` and prints the first step where they differ with
+/// the boards of both sides
+pub fn diff(a: &str, b: &str) -> Result<(), String> {
+    let load = |path: &str| Trace::load(Path::new(path)).map_err(|err| format!("{path}: {err}"));
+    let traces = [(a, load(a)?), (b, load(b)?)];
+    if traces[0].1.line != traces[1].1.line {
+        println!("the traces are of different puzzles");
+    }
+    let Some(i) = first_divergence(&traces[0].1.records, &traces[1].1.records) else {
+        println!(
+            "the traces agree for all {} steps",
+            traces[0].1.records.len()
+        );
+        return Ok(());
+    };
+    let step = i + 1;
+    println!("first divergence at step {step}");
+    for (path, trace) in &traces {
+        match trace.records.get(i) {
+            Some(record) => println!("{path} ({}): {record}", trace.source.name()),
+            None => println!("{path} ({}): ended after {i} steps", trace.source.name()),
+        }
+    }
+    for (path, trace) in &traces {
+        println!("\nboard of {path} at step {step}:");
+        match trace.replay(step)? {
+            Some(board) => board.dump(),
+            None => println!("solved or unsolvable before"),
+        }
+    }
+    Ok(())
+}
+
+/// What `--trace=<file>` writes, see the module documentation.
+pub struct Tracing {
+    path: PathBuf,
+    format: Format,
+    port: Option<Port>,
+}
+impl Tracing {
+    /// None without `--trace=<file>`
+    pub fn from_args() -> Option<Result<Tracing, String>> {
+        let arg =
+            |prefix: &str| args().find_map(|arg| arg.strip_prefix(prefix).map(str::to_string));
+        let path = PathBuf::from(arg("--trace=")?);
+        let format = match arg("--trace-format=").as_deref() {
+            None | Some("binary") => Format::Binary,
+            Some("csv") => Format::Csv,
+            Some(format) => return Some(Err(format!("unknown trace format: {format}"))),
+        };
+        let port = match args().any(|arg| arg == "--port") {
+            true => match Layout::from_args().and_then(Port::new) {
+                Ok(port) => Some(port),
+                Err(err) => return Some(Err(err)),
+            },
+            false => None,
+        };
+        Some(Ok(Tracing { path, format, port }))
+    }
+    // the file of the `n`th puzzle
+    fn path(&self, n: usize) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(extension) => format!("{stem}-{n}.{}", extension.to_string_lossy()),
+            None => format!("{stem}-{n}"),
+        };
+        self.path.with_file_name(name)
+    }
+    /// solves `board`, the `n`th puzzle of the file, or its `line` on the port, writes the trace
+    /// and prints the outcome
+    pub fn solve(
+        &self,
+        n: usize,
+        line: &str,
+        board: &mut Board,
+        limits: &Limits,
+    ) -> Result<(), String> {
+        let path = self.path(n);
+        let error = |err: io::Error| format!("{}: {err}", path.display());
+        let file = BufWriter::new(File::create(&path).map_err(error)?);
+        let source = match self.port {
+            Some(_) => Source::Port,
+            None => Source::Board,
+        };
+        let mut writer = Writer::new(file, self.format, source, line).map_err(error)?;
+        let mut result = Ok(());
+        let trace = |board: &Board| {
+            if result.is_ok() {
+                result = writer.record(Record::of(board));
+            }
+        };
+        match &self.port {
+            Some(port) => {
+                let run = port.run_traced(line, trace)?;
+                println!(
+                    "port: {}, max depth: {}, steps: {}, cycles: {}",
+                    if run.solved { "solved" } else { "unsolvable" },
+                    run.max_depth,
+                    run.num_steps,
+                    run.cycles
+                );
+            }
+            None => match board.solve_traced(limits, trace) {
+                SolveOutcome::Solved => {
+                    board.print();
+                    println!("max depth: {}, steps: {}", board.max_depth, board.num_steps);
+                    println!("solved.");
+                }
+                SolveOutcome::Unsolvable => println!("unsolvable"),
+                SolveOutcome::Aborted { steps, depth } => {
+                    println!("aborted after {steps} steps at depth {depth}")
+                }
+            },
+        }
+        result.map_err(error)?;
+        let num_records = writer.finish().map_err(error)?;
+        println!("{num_records} steps traced to {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    const LINE: &str =
+        "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......";
+    fn trace(source: Source, format: Format) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new(), format, source, LINE).unwrap();
+        let trace = |board: &Board| writer.record(Record::of(board)).unwrap();
+        match source {
+            Source::Board => {
+                let mut board = Board::from_line(LINE, Selection::default());
+                board.solve_traced(&Limits::default(), trace);
+            }
+            Source::Port => {
+                let port = Port::new(Layout::default()).unwrap();
+                port.run_traced(LINE, trace).unwrap();
+            }
+        }
+        writer.w
+    }
+    #[test]
+    fn test_lockstep() {
+        // the port takes exactly the steps of the reference
+        let board = Trace::parse(&trace(Source::Board, Format::Binary)).unwrap();
+        let port = Trace::parse(&trace(Source::Port, Format::Csv)).unwrap();
+        assert_eq!((board.source, port.source), (Source::Board, Source::Port));
+        assert_eq!(board.line, LINE);
+        assert_eq!(first_divergence(&board.records, &port.records), None);
+
+        let mut reference = Board::from_line(LINE, Selection::default());
+        reference.solve(&Limits::default());
+        assert_eq!(board.records.len(), reference.num_steps);
+        // the first step tries the first candidate of the field with the fewest
+        let first = board.records[0];
+        assert_eq!((first.stack_ptr, first.num_open), (0, 81 - 17 - 1));
+        assert_eq!(first.digit, first.candidates_l.trailing_zeros() as u8);
+        // the last one finds the board solved
+        let last = board.records.last().unwrap();
+        assert_eq!((last.num_open, last.candidates_h), (0, 0x80));
+
+        let mut changed = port.records.clone();
+        changed[100].digit += 1;
+        assert_eq!(first_divergence(&board.records, &changed), Some(100));
+        assert_eq!(first_divergence(&board.records, &changed[..50]), Some(50));
+
+        // both sides replay to the same board
+        let replayed = [board.replay(101).unwrap(), port.replay(101).unwrap()].map(Option::unwrap);
+        assert_eq!(Record::of(&replayed[0]), board.records[100]);
+        assert_eq!(replayed[0].fields, replayed[1].fields);
+        assert_eq!(replayed[0].open, replayed[1].open);
+        assert_eq!(replayed[0].field_stack, replayed[1].field_stack);
+        assert_eq!(replayed[0].b_free_h, replayed[1].b_free_h);
+        assert!(board.replay(board.records.len() + 1).unwrap().is_none());
+    }
+    #[test]
+    fn test_formats() {
+        let binary = trace(Source::Board, Format::Binary);
+        let csv = trace(Source::Board, Format::Csv);
+        let parsed = Trace::parse(&binary).unwrap();
+        assert_eq!(parsed, Trace::parse(&csv).unwrap());
+        // 6 bytes per step after the header
+        assert_eq!(binary.len(), 4 + 3 + LINE.len() + 6 * parsed.records.len());
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(&format!(
+            "# source: board\n# puzzle: {LINE}\n{CSV_HEADER}\n1,0,"
+        )));
+
+        assert!(Trace::parse(&binary[..binary.len() - 1]).is_err());
+        assert!(Trace::parse(csv.replace("\n2,", "\n3,").as_bytes()).is_err());
+        assert!(Trace::parse(b"RS8S").is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Printable booklets: the puzzles of a file laid out on A4 pages, a few per page with their
+//! number and difficulty, followed by an answer key with the solutions. Every page is composed
+//! as an SVG picture of the pictures from `svg` and converted to PDF by svg2pdf, so no external
+//! tools are needed.
+//!
+//! `booklet` as the first argument solves the puzzles of the file and writes the booklet, see
+//! `Booklet`.
+
+use super::svg::{self, FONT};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
+use std::cell::RefCell;
+use std::env::args;
+use std::path::PathBuf;
+
+// A4 in points
+const WIDTH: f32 = 595.0;
+const HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+// the space of the page title on top and of the page number at the bottom
+const HEADER: f32 = 40.0;
+const FOOTER: f32 = 20.0;
+// the space of the caption above every picture
+const CAPTION: f32 = 24.0;
+const ANSWERS_PER_PAGE: usize = 12;
+
+/// How hard a puzzle was for the search: the number of steps per open field. Without any
+/// backtracking every open field takes one step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Fiendish,
+}
+impl Difficulty {
+    pub fn from_steps(num_steps: usize, open: usize) -> Difficulty {
+        match num_steps {
+            steps if steps <= open => Difficulty::Easy,
+            steps if steps <= 10 * open => Difficulty::Medium,
+            steps if steps <= 100 * open => Difficulty::Hard,
+            _ => Difficulty::Fiendish,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Fiendish => "Fiendish",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Entry {
+    puzzle: String,
+    solution: Option<String>,
+    difficulty: Difficulty,
+}
+
+/// What `booklet <options> <file>` writes: the puzzles, `--per-page=<n>` per page (4 by
+/// default), under `--title=<text>` (the file name by default), to `--out=<file>`
+/// (`booklet.pdf` by default).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Booklet {
+    pub per_page: usize,
+    pub title: Option<String>,
+    pub out: PathBuf,
+    // the pictures of the puzzles solved so far
+    entries: RefCell<Vec<Entry>>,
+}
+impl Booklet {
+    /// None unless the first argument is `booklet`
+    pub fn from_args() -> Option<Booklet> {
+        if args().nth(1).as_deref() != Some("booklet") {
+            return None;
+        }
+        let arg =
+            |prefix: &str| args().find_map(|arg| arg.strip_prefix(prefix).map(str::to_string));
+        Some(Booklet {
+            per_page: arg("--per-page=").map_or(4, |n| n.parse().expect("bad puzzles per page")),
+            title: arg("--title="),
+            out: PathBuf::from(arg("--out=").unwrap_or_else(|| "booklet.pdf".to_string())),
+            entries: RefCell::default(),
+        })
+    }
+    /// adds a puzzle with the picture of its solution, if it has one
+    pub fn add(&self, puzzle: String, solution: Option<String>, difficulty: Difficulty) {
+        self.entries.borrow_mut().push(Entry {
+            puzzle,
+            solution,
+            difficulty,
+        });
+    }
+    /// the SVG pictures of all pages, the puzzles first and then the answer key
+    fn pages(&self, title: &str) -> Vec<String> {
+        let entries = self.entries.borrow();
+        let per_page = self.per_page.max(1);
+        let mut pages = Vec::new();
+        for (p, chunk) in entries.chunks(per_page).enumerate() {
+            let slots = chunk.iter().enumerate().map(|(i, entry)| {
+                let number = p * per_page + i + 1;
+                let caption = format!("Puzzle {number} · {}", entry.difficulty.name());
+                (caption, Some(entry.puzzle.as_str()))
+            });
+            pages.push(page(title, pages.len() + 1, per_page, slots.collect()));
+        }
+        for (p, chunk) in entries.chunks(ANSWERS_PER_PAGE).enumerate() {
+            let slots = chunk.iter().enumerate().map(|(i, entry)| {
+                let caption = format!("Answer {}", p * ANSWERS_PER_PAGE + i + 1);
+                (caption, entry.solution.as_deref())
+            });
+            let heading = format!("{title} · Answers");
+            pages.push(page(
+                &heading,
+                pages.len() + 1,
+                ANSWERS_PER_PAGE,
+                slots.collect(),
+            ));
+        }
+        pages
+    }
+    /// the booklet as a PDF document with a page for every page picture
+    pub fn pdf(&self, title: &str) -> Result<Vec<u8>, String> {
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let mut next = Ref::new(3);
+        let mut pdf = Pdf::new();
+        let mut kids = Vec::new();
+        for picture in self.pages(title) {
+            let tree = svg::tree(&picture)?;
+            let (page_id, content_id, picture_id) =
+                (next, Ref::new(next.get() + 1), Ref::new(next.get() + 2));
+            next = svg2pdf::convert_tree_into(
+                &tree,
+                svg2pdf::Options::default(),
+                &mut pdf,
+                picture_id,
+            );
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, WIDTH, HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+            page.resources().x_objects().pair(Name(b"P"), picture_id);
+            page.finish();
+            // the picture is a form one point wide and high, stretched over the page
+            let mut content = Content::new();
+            content
+                .transform([WIDTH, 0.0, 0.0, HEIGHT, 0.0, 0.0])
+                .x_object(Name(b"P"));
+            pdf.stream(content_id, &content.finish());
+            kids.push(page_id);
+        }
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id).count(kids.len() as i32).kids(kids);
+        Ok(pdf.finish())
+    }
+    /// writes the booklet of the puzzles added so far and prints the file name
+    pub fn write(&self, title: &str) -> Result<(), String> {
+        let title = self.title.as_deref().unwrap_or(title);
+        std::fs::write(&self.out, self.pdf(title)?)
+            .map_err(|err| format!("{}: {err}", self.out.display()))?;
+        println!("{}", self.out.display());
+        Ok(())
+    }
+}
+
+// a page with a heading, the pictures under their captions in a grid of `per_page` slots, and
+// its number at the bottom. A slot without a picture says so.
+fn page(
+    heading: &str,
+    number: usize,
+    per_page: usize,
+    slots: Vec<(String, Option<&str>)>,
+) -> String {
+    let columns = (1..).find(|c| c * c >= per_page).unwrap();
+    let rows = per_page.div_ceil(columns);
+    let (slot_w, slot_h) = (
+        (WIDTH - 2.0 * MARGIN) / columns as f32,
+        (HEIGHT - 2.0 * MARGIN - HEADER - FOOTER) / rows as f32,
+    );
+    // the pictures are square, with some room between them
+    let size = (slot_w - 12.0).min(slot_h - CAPTION - 12.0);
+    let mut svg = vec![
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="{FONT}">"#
+        ),
+        format!(r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>"#),
+        text(MARGIN, MARGIN + 20.0, 20.0, "start", heading),
+        text(
+            WIDTH / 2.0,
+            HEIGHT - MARGIN,
+            10.0,
+            "middle",
+            &number.to_string(),
+        ),
+    ];
+    for (i, (caption, picture)) in slots.into_iter().enumerate() {
+        let x = MARGIN + (i % columns) as f32 * slot_w + (slot_w - size) / 2.0;
+        let y = MARGIN + HEADER + (i / columns) as f32 * slot_h;
+        svg.push(text(x, y + CAPTION - 8.0, 12.0, "start", &caption));
+        match picture {
+            Some(picture) => {
+                // nested pictures keep their own view box, scaled to the slot
+                let picture = picture.replacen(
+                    "<svg ",
+                    &format!(r#"<svg x="{x:.1}" y="{:.1}" "#, y + CAPTION),
+                    1,
+                );
+                let picture = replace_size(&picture, size);
+                svg.push(picture);
+            }
+            None => svg.push(text(x, y + CAPTION + 20.0, 12.0, "start", "no solution")),
+        }
+    }
+    svg.push("</svg>".to_string());
+    svg.join("\n") + "\n"
+}
+
+// the picture with its outer width and height set to `size`
+fn replace_size(picture: &str, size: f32) -> String {
+    let (tag, rest) = picture.split_once('>').unwrap();
+    let mut tag = tag.to_string();
+    for attribute in ["width", "height"] {
+        let start = tag.find(&format!(" {attribute}=\"")).unwrap() + attribute.len() + 3;
+        let end = start + tag[start..].find('"').unwrap();
+        tag.replace_range(start..end, &format!("{size:.1}"));
+    }
+    format!("{tag}>{rest}")
+}
+
+fn text(x: f32, y: f32, size: f32, anchor: &str, content: &str) -> String {
+    let content = content
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<text x="{x:.1}" y="{y:.1}" font-size="{size:.1}" text-anchor="{anchor}" fill="black">{content}</text>"#
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grid::Symbols;
+    use crate::render::Cell;
+    #[test]
+    fn test_difficulty() {
+        assert_eq!(Difficulty::from_steps(50, 50), Difficulty::Easy);
+        assert_eq!(Difficulty::from_steps(51, 50), Difficulty::Medium);
+        assert_eq!(Difficulty::from_steps(5000, 50), Difficulty::Hard);
+        assert_eq!(Difficulty::from_steps(5001, 50).name(), "Fiendish");
+    }
+    #[test]
+    fn test_booklet() {
+        let regions = [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3];
+        let picture = |cell| {
+            let cells = vec![cell; 16];
+            svg::svg(4, "1", &cells, &regions, &Symbols::detect(4, ""), false).unwrap()
+        };
+        let booklet = Booklet {
+            per_page: 4,
+            ..Default::default()
+        };
+        for i in 0..5 {
+            let solution = (i != 3).then(|| picture(Cell::Solved(1)));
+            booklet.add(picture(Cell::Given(0)), solution, Difficulty::Hard);
+        }
+        let pages = booklet.pages("Test");
+        // two pages of puzzles and one of answers
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].matches("<svg").count(), 5);
+        assert!(pages[1].contains("Puzzle 5 · Hard") && pages[1].contains(">2</text>"));
+        assert!(pages[2].contains("Test · Answers") && pages[2].contains("no solution"));
+        // a picture fills the half of the page width
+        assert!(pages[0].contains(r#"<svg x="46.0" y="104.0" "#));
+        assert!(pages[0].contains(r#"width="245.5" height="245.5""#));
+
+        let pdf = booklet.pdf("Test").unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert_eq!(pdf.matches("/Type /Page\n").count(), 3);
+        assert!(pdf.contains("/Count 3"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Markers between orthogonally adjacent fields: Greater-Than signs, Kropki dots and XV. With a
+//! negative constraint an edge without a Kropki dot (or without an X or V) also says something:
+//! its two numbers are neither consecutive nor in a 1:2 ratio (nor add up to 10 or 5).
+
+use super::relations::Relation;
+use std::env::args;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    /// `<`: the left (upper) field holds the smaller number
+    Less,
+    /// `>`: the left (upper) field holds the larger number
+    Greater,
+    /// `w`: white Kropki dot
+    White,
+    /// `b`: black Kropki dot
+    Black,
+    /// `x`: sum 10
+    X,
+    /// `v`: sum 5
+    V,
+}
+impl Marker {
+    fn from_char(c: char) -> Result<Option<Marker>, String> {
+        match c.to_ascii_lowercase() {
+            '.' => Ok(None),
+            '<' => Ok(Some(Marker::Less)),
+            '>' => Ok(Some(Marker::Greater)),
+            'w' => Ok(Some(Marker::White)),
+            'b' => Ok(Some(Marker::Black)),
+            'x' => Ok(Some(Marker::X)),
+            'v' => Ok(Some(Marker::V)),
+            _ => Err(format!("bad edge marker: {c}")),
+        }
+    }
+    /// how the number of the right (lower) field relates to the left (upper) one
+    fn relation(self) -> Relation {
+        match self {
+            Marker::Less => Relation::Greater,
+            Marker::Greater => Relation::Less,
+            Marker::White => Relation::Consecutive,
+            Marker::Black => Relation::Double,
+            Marker::X => Relation::Sum(10),
+            Marker::V => Relation::Sum(5),
+        }
+    }
+}
+
+/// Marker kinds whose absence counts, given as `--negative=kropki,xv`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Negative {
+    Kropki,
+    Xv,
+}
+impl Negative {
+    pub fn from_name(name: &str) -> Result<Negative, String> {
+        match name {
+            "kropki" => Ok(Negative::Kropki),
+            "xv" => Ok(Negative::Xv),
+            _ => Err(format!("unknown negative constraint: {name}")),
+        }
+    }
+    pub fn from_args() -> Vec<Negative> {
+        args()
+            .filter_map(|arg| arg.strip_prefix("--negative=").map(str::to_string))
+            .flat_map(|names| {
+                names
+                    .split(',')
+                    .map(|name| Negative::from_name(name).unwrap_or_else(|err| panic!("{err}")))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    fn covers(self, marker: Marker) -> bool {
+        match self {
+            Negative::Kropki => matches!(marker, Marker::White | Marker::Black),
+            Negative::Xv => matches!(marker, Marker::X | Marker::V),
+        }
+    }
+    // what an edge without a marker of this kind says
+    fn relations(self) -> [Relation; 2] {
+        match self {
+            Negative::Kropki => [Relation::NonConsecutive, Relation::NotDouble],
+            Negative::Xv => [Relation::NotSum(10), Relation::NotSum(5)],
+        }
+    }
+}
+
+/// parses the markers of an N×N grid, given as `<horizontal>/<vertical>`: the N - 1 edges
+/// between the fields of every row, row by row, then the N edges between every two rows. Returns
+/// the related field pairs (`y * N + x`) with the relation of the second field to the first, one
+/// per marker and per negative constraint that applies.
+pub fn parse_edges(
+    spec: &str,
+    n: usize,
+    negative: &[Negative],
+) -> Result<Vec<(usize, usize, Relation)>, String> {
+    let (horizontal, vertical) = spec
+        .split_once('/')
+        .ok_or("edges need a '/' between the horizontal and vertical ones")?;
+    let horizontal = horizontal.chars().collect::<Vec<_>>();
+    let vertical = vertical.chars().collect::<Vec<_>>();
+    if horizontal.len() != n * (n - 1) || vertical.len() != n * (n - 1) {
+        return Err(format!(
+            "{}/{} edges instead of {}/{}",
+            horizontal.len(),
+            vertical.len(),
+            n * (n - 1),
+            n * (n - 1)
+        ));
+    }
+    let pairs = (0..n)
+        .flat_map(|y| (0..n - 1).map(move |x| (y * n + x, y * n + x + 1)))
+        .chain((0..n - 1).flat_map(|y| (0..n).map(move |x| (y * n + x, (y + 1) * n + x))));
+    let mut related = Vec::new();
+    for ((a, b), c) in pairs.zip(horizontal.into_iter().chain(vertical)) {
+        let marker = Marker::from_char(c)?;
+        if let Some(marker) = marker {
+            related.push((a, b, marker.relation()));
+        }
+        for kind in negative {
+            if !marker.is_some_and(|marker| kind.covers(marker)) {
+                related.extend(kind.relations().map(|relation| (a, b, relation)));
+            }
+        }
+    }
+    Ok(related)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_parse_edges() {
+        // 3×3: 2 edges per row, then 3 per pair of rows
+        let related = parse_edges("<...bX/w.V...", 3, &[]).unwrap();
+        assert_eq!(
+            related,
+            [
+                (0, 1, Relation::Greater),
+                (6, 7, Relation::Double),
+                (7, 8, Relation::Sum(10)),
+                (0, 3, Relation::Consecutive),
+                (2, 5, Relation::Sum(5)),
+            ]
+        );
+        assert!(parse_edges("w.....", 2, &[Negative::Kropki]).is_err());
+        let negative = parse_edges("w./..", 2, &[Negative::Kropki]).unwrap();
+        assert_eq!(negative.len(), 1 + 3 * 2);
+        assert_eq!(negative[1], (2, 3, Relation::NonConsecutive));
+        assert!(parse_edges("<..../...", 3, &[]).is_err());
+        assert!(parse_edges("<...a./......", 3, &[]).is_err());
+    }
+}
@@ -0,0 +1,638 @@
+//! Puzzles exported from the f-puzzles editor, which SudokuPad reads and writes as well: one JSON
+//! object with the `size`, the `grid` (rows of cells with an optional `value`, `given` flag and
+//! `region`) and one key per constraint kind. `import` turns such an export into a puzzle line
+//! and the options it needs, `export` goes the other way and fills in a solution if there is
+//! one. Only plain JSON is read, not the compressed strings of editor links.
+//!
+//! Kinds without a counterpart here (extra regions, clones, between lines, cosmetic text, ...)
+//! are errors, rather than being dropped and leaving a different puzzle. The editor has no
+//! Greater-Than markers, German Whispers or Skyscrapers, and only sandwich sums on top and on the
+//! left, so such lines can't be exported. Negative black dots come with `nonconsecutive`, which
+//! the editor leaves out on edges with a white dot, and make `--negative=kropki`.
+
+use super::edges::{self, Negative};
+use super::grid::{box_height, Symbols, DIGITS_FIRST};
+use super::houses::Variant;
+use super::killer;
+use super::lines::{self, parse_field, Constraint, Line, LineKind};
+use super::relations::Relation;
+use super::{Options, SIZES};
+use serde_json::{json, Map, Value};
+
+// keys that don't change the solution
+const IGNORED: [&str; 8] = [
+    "size",
+    "grid",
+    "title",
+    "author",
+    "ruleset",
+    "solution",
+    "disabledlogic",
+    "truecandidatesoptions",
+];
+
+/// converts an f-puzzles export into a puzzle line and the options it needs: size, symbols,
+/// variants, relations and negative constraints
+pub fn import(json: &str) -> Result<(String, Options), String> {
+    let puzzle = serde_json::from_str::<Value>(json).map_err(|err| format!("bad JSON: {err}"))?;
+    let puzzle = puzzle.as_object().ok_or("the export is no JSON object")?;
+    let n = puzzle
+        .get("size")
+        .and_then(Value::as_u64)
+        .ok_or("the export has no size")? as usize;
+    if !SIZES.contains(&n) {
+        return Err(format!("unsupported size: {n}"));
+    }
+    let symbols = Symbols::new(&DIGITS_FIRST[..n]);
+    let mut givens = vec!['.'; n * n];
+    let mut regions = default_regions(n);
+    let rows = puzzle
+        .get("grid")
+        .and_then(Value::as_array)
+        .filter(|rows| rows.len() == n)
+        .ok_or_else(|| format!("the grid doesn't have {n} rows"))?;
+    for (y, row) in rows.iter().enumerate() {
+        let row = row
+            .as_array()
+            .filter(|row| row.len() == n)
+            .ok_or_else(|| format!("row {} doesn't have {n} cells", y + 1))?;
+        for (x, cell) in row.iter().enumerate() {
+            let at = || cell_name(y * n + x, n);
+            // values the solver entered don't count
+            if cell.get("given").and_then(Value::as_bool) == Some(true) {
+                let value = number(cell.get("value"))
+                    .filter(|value| (1..=n).contains(value))
+                    .ok_or_else(|| format!("bad given in {}", at()))?;
+                givens[y * n + x] = symbols.get(value - 1);
+            }
+            if let Some(region) = cell.get("region") {
+                regions[y * n + x] = region
+                    .as_u64()
+                    .ok_or_else(|| format!("{} is in no region", at()))?
+                    as usize;
+            }
+        }
+    }
+
+    let mut options = Options {
+        size: Some(n),
+        symbols: Some(symbols),
+        ..Options::default()
+    };
+    let mut diagonals = 0;
+    let mut nonconsecutive = false;
+    let mut negative_ratio = false;
+    let mut cages = Vec::new();
+    let mut edges = vec!['.'; 2 * n * (n - 1)];
+    let mut lines = Vec::new();
+    let mut sandwich = [vec![String::new(); n], vec![String::new(); n]];
+    let mut little_killers = Vec::new();
+    for (key, value) in puzzle {
+        if IGNORED.contains(&key.as_str()) || is_empty(value) {
+            continue;
+        }
+        match key.as_str() {
+            "diagonal+" | "diagonal-" => diagonals += 1,
+            "antiknight" => options.relations.push(Relation::AntiKnight),
+            "antiking" => options.relations.push(Relation::AntiKing),
+            "nonconsecutive" => nonconsecutive = true,
+            "disjointgroups" => options.variants.push(Variant::Disjoint),
+            "negative" => {
+                for kind in entries(value, key)? {
+                    match kind.as_str() {
+                        Some("ratio") => negative_ratio = true,
+                        Some("xv") => options.negative.push(Negative::Xv),
+                        _ => return Err(format!("unsupported negative constraint: {kind}")),
+                    }
+                }
+            }
+            "killercage" => {
+                for cage in entries(value, key)? {
+                    let fields = cells(cage.get("cells"), n)?;
+                    let sum = number(cage.get("value"))
+                        .ok_or_else(|| format!("killer cage without a sum: {cage}"))?;
+                    cages.push((fields, sum));
+                }
+            }
+            "thermometer" | "palindrome" | "arrow" => {
+                let kind = match key.as_str() {
+                    "thermometer" => LineKind::Thermo,
+                    "palindrome" => LineKind::Palindrome,
+                    _ => LineKind::Arrow,
+                };
+                for entry in entries(value, key)? {
+                    let paths = entries(entry.get("lines").unwrap_or(&Value::Null), key)?;
+                    let mut paths = paths
+                        .iter()
+                        .map(|path| cells(Some(path), n))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if kind == LineKind::Arrow {
+                        // the circle holds the sum of a single arrow starting next to it
+                        let circle = cells(entry.get("cells"), n)?;
+                        let ([circle], [path]) = (&circle[..], &mut paths[..]) else {
+                            return Err(format!("unsupported arrow: {entry}"));
+                        };
+                        if path.first() != Some(circle) {
+                            path.insert(0, *circle);
+                        }
+                    }
+                    for path in paths {
+                        lines.push((kind, Line::new(kind, path, n)?));
+                    }
+                }
+            }
+            "difference" | "ratio" | "xv" => {
+                for dot in entries(value, key)? {
+                    let marker = match (key.as_str(), number(dot.get("value"))) {
+                        ("difference", None | Some(1)) => 'w',
+                        ("ratio", None | Some(2)) => 'b',
+                        ("xv", _) => match dot.get("value").and_then(Value::as_str) {
+                            Some("X" | "x") => 'x',
+                            Some("V" | "v") => 'v',
+                            _ => return Err(format!("bad XV marker: {dot}")),
+                        },
+                        _ => return Err(format!("unsupported {key} dot: {dot}")),
+                    };
+                    let edge = match cells(dot.get("cells"), n)?[..] {
+                        [a, b] => edge(a.min(b), a.max(b), n),
+                        _ => None,
+                    };
+                    let edge = edge.ok_or_else(|| format!("{key} between no two neighbours"))?;
+                    if edges[edge] != '.' {
+                        return Err(format!("two markers on one edge: {dot}"));
+                    }
+                    edges[edge] = marker;
+                }
+            }
+            "sandwichsum" => {
+                for clue in entries(value, key)? {
+                    let sum = number(clue.get("value"));
+                    let (side, i) = match outside_cell(clue.get("cell"), n) {
+                        Some((0, x)) if (1..=n as isize).contains(&x) => (0, x),
+                        Some((y, 0)) if (1..=n as isize).contains(&y) => (1, y),
+                        _ => return Err(format!("bad sandwich sum: {clue}")),
+                    };
+                    sandwich[side][i as usize - 1] =
+                        sum.map(|sum| sum.to_string()).unwrap_or_default();
+                }
+            }
+            "littlekillersum" => {
+                for clue in entries(value, key)? {
+                    let Some(sum) = number(clue.get("value")) else {
+                        continue;
+                    };
+                    let direction = clue.get("direction").and_then(Value::as_str);
+                    let (direction, dx, dy) = match direction {
+                        Some("UR") => ("ne", 1, -1),
+                        Some("DR") => ("se", 1, 1),
+                        Some("DL") => ("sw", -1, 1),
+                        Some("UL") => ("nw", -1, -1),
+                        _ => return Err(format!("bad little killer direction: {clue}")),
+                    };
+                    let inside = |i: isize| (1..=n as isize).contains(&i);
+                    let first = outside_cell(clue.get("cell"), n)
+                        .map(|(y, x)| (y + dy, x + dx))
+                        .filter(|(y, x)| inside(*y) && inside(*x))
+                        .ok_or_else(|| format!("little killer pointing nowhere: {clue}"))?;
+                    little_killers.push(format!("r{}c{}:{direction}:{sum}", first.0, first.1));
+                }
+            }
+            _ => return Err(format!("unsupported f-puzzles constraint: {key}")),
+        }
+    }
+    match diagonals {
+        0 => {}
+        2 => options.variants.push(Variant::X),
+        _ => return Err("only both diagonals together are supported".to_string()),
+    }
+    match (nonconsecutive, negative_ratio) {
+        (true, true) => options.negative.push(Negative::Kropki),
+        (false, true) => return Err("negative ratio needs nonconsecutive as well".to_string()),
+        (true, false) if edges.contains(&'w') => {
+            return Err("nonconsecutive with white dots needs negative ratio".to_string())
+        }
+        (true, false) => options.relations.push(Relation::NonConsecutive),
+        (false, false) => {}
+    }
+
+    let mut parts = vec![givens.into_iter().collect::<String>()];
+    if regions != default_regions(n) {
+        parts.push(regions.into_iter().map(id).collect());
+    }
+    if !cages.is_empty() {
+        let mut ids = vec!['.'; n * n];
+        for (i, (fields, _)) in cages.iter().enumerate() {
+            for field in fields {
+                if ids[*field] != '.' {
+                    return Err(format!("{} is in two killer cages", cell_name(*field, n)));
+                }
+                ids[*field] = id(i);
+            }
+        }
+        let sums = cages.iter().map(|(_, sum)| sum.to_string());
+        let sums = sums.collect::<Vec<_>>().join(",");
+        parts.push(format!(
+            "cages={}/{sums}",
+            ids.into_iter().collect::<String>()
+        ));
+    }
+    if edges.iter().any(|marker| *marker != '.') {
+        let (horizontal, vertical) = edges.split_at(n * (n - 1));
+        let (horizontal, vertical) = (horizontal.iter(), vertical.iter());
+        parts.push(format!(
+            "edges={}/{}",
+            horizontal.collect::<String>(),
+            vertical.collect::<String>()
+        ));
+    }
+    for (kind, name) in [
+        (LineKind::Thermo, "thermo"),
+        (LineKind::Arrow, "arrow"),
+        (LineKind::Palindrome, "palindrome"),
+    ] {
+        let paths = lines
+            .iter()
+            .filter(|(k, _)| *k == kind)
+            .map(|(_, line)| path(line.fields(), n))
+            .collect::<Vec<_>>();
+        if !paths.is_empty() {
+            parts.push(format!("{name}={}", paths.join(";")));
+        }
+    }
+    if sandwich.iter().flatten().any(|sum| !sum.is_empty()) {
+        let [top, left] = sandwich.map(|side| side.join(","));
+        parts.push(format!("sandwich={top}/{left}"));
+    }
+    if !little_killers.is_empty() {
+        parts.push(format!("littlekiller={}", little_killers.join(";")));
+    }
+    Ok((parts.join(" "), options))
+}
+
+/// converts a puzzle line of an N×N grid with its options into an f-puzzles export. The numbers
+/// of `solution`, a line of N×N symbols like the givens, are filled in as entered by a solver.
+pub fn export(
+    n: usize,
+    line: &str,
+    symbols: &Symbols,
+    options: &Options,
+    solution: Option<&str>,
+) -> Result<String, String> {
+    let mut parts = line.split_whitespace();
+    let givens = parts.next().ok_or("empty puzzle line")?.chars();
+    let givens = givens
+        .map(|c| symbols.parse(c))
+        .collect::<Result<Vec<_>, _>>()?;
+    let solution = match solution {
+        Some(solution) => solution.chars().map(|c| symbols.parse(c)).collect(),
+        None => Ok(vec![None; n * n]),
+    }?;
+    if givens.len() != n * n || solution.len() != n * n {
+        return Err(format!("a {n}x{n} grid needs {} fields", n * n));
+    }
+    let mut puzzle = Map::new();
+    let mut regions = match &options.shape {
+        Some(shape) => {
+            let (w, h) = shape
+                .split_once('x')
+                .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                .filter(|(w, h): &(usize, usize)| w * h == n)
+                .ok_or_else(|| format!("bad box shape: {shape}"))?;
+            Some(boxes(n, w, h))
+        }
+        None => None,
+    };
+    let mut sandwich = Vec::new();
+    for part in parts {
+        let (name, spec) = part.split_once('=').unwrap_or(("", part));
+        match name {
+            "cages" => {
+                let cages = killer::parse_cages(spec, n)?;
+                let cages = cages.into_iter().map(|(fields, sum)| {
+                    json!({"cells": cell_names(&fields, n), "value": sum.to_string()})
+                });
+                puzzle.insert("killercage".to_string(), cages.collect());
+            }
+            "edges" => {
+                for (a, b, relation) in edges::parse_edges(spec, n, &[])? {
+                    let (key, value) = match relation {
+                        Relation::Consecutive => ("difference", ""),
+                        Relation::Double => ("ratio", ""),
+                        Relation::Sum(10) => ("xv", "X"),
+                        Relation::Sum(5) => ("xv", "V"),
+                        _ => return Err("f-puzzles has no Greater-Than markers".to_string()),
+                    };
+                    let dot = json!({"cells": cell_names(&[a, b], n), "value": value});
+                    push(&mut puzzle, key, dot);
+                }
+            }
+            "thermo" | "arrow" | "palindrome" => {
+                let kind = LineKind::from_name(name).unwrap();
+                for line in lines::parse_lines(kind, spec, n)? {
+                    let path = cell_names(line.fields(), n);
+                    let (key, entry) = match kind {
+                        LineKind::Thermo => ("thermometer", json!({"lines": [path]})),
+                        LineKind::Arrow => {
+                            let circle = [path[0].clone()];
+                            ("arrow", json!({"lines": [path], "cells": circle}))
+                        }
+                        _ => ("palindrome", json!({"lines": [path]})),
+                    };
+                    push(&mut puzzle, key, entry);
+                }
+            }
+            "sandwich" => {
+                for (side, spec) in spec.split('/').enumerate() {
+                    for (i, sum) in spec
+                        .split(',')
+                        .enumerate()
+                        .filter(|(_, sum)| !sum.is_empty())
+                    {
+                        let cell = match side {
+                            0 => format!("R0C{}", i + 1),
+                            1 => format!("R{}C0", i + 1),
+                            _ => return Err("f-puzzles has no sandwich sums below or right".into()),
+                        };
+                        sandwich.push(json!({"cell": cell, "value": sum}));
+                    }
+                }
+            }
+            "littlekiller" => {
+                for clue in spec.split(';') {
+                    let mut parts = clue.split(':');
+                    let (Some(first), Some(direction), Some(sum)) =
+                        (parts.next(), parts.next(), parts.next())
+                    else {
+                        return Err(format!("bad Little Killer clue: {clue}"));
+                    };
+                    let first = parse_field(first, n)?;
+                    let (direction, dx, dy) = match direction {
+                        "ne" => ("UR", 1, -1),
+                        "se" => ("DR", 1, 1),
+                        "sw" => ("DL", -1, 1),
+                        "nw" => ("UL", -1, -1),
+                        _ => return Err(format!("bad Little Killer direction: {direction}")),
+                    };
+                    // the clue sits outside the grid, one step before the first field
+                    let (y, x) = ((first / n) as isize + 1 - dy, (first % n) as isize + 1 - dx);
+                    let clue =
+                        json!({"cell": format!("R{y}C{x}"), "direction": direction, "value": sum});
+                    push(&mut puzzle, "littlekillersum", clue);
+                }
+            }
+            "" => {
+                // region ids, numbered by first appearance
+                let mut seen = Vec::new();
+                let ids = spec
+                    .chars()
+                    .map(|id| match seen.iter().position(|s| *s == id) {
+                        Some(region) => region,
+                        None => {
+                            seen.push(id);
+                            seen.len() - 1
+                        }
+                    });
+                let ids = ids.collect::<Vec<_>>();
+                if ids.len() != n * n {
+                    return Err(format!("{} region ids for {} fields", ids.len(), n * n));
+                }
+                regions = Some(ids);
+            }
+            _ => return Err(format!("f-puzzles has no {name} constraint")),
+        }
+    }
+    if !sandwich.is_empty() {
+        puzzle.insert("sandwichsum".to_string(), sandwich.into());
+    }
+    for variant in &options.variants {
+        match variant {
+            Variant::X => {
+                puzzle.insert("diagonal+".to_string(), true.into());
+                puzzle.insert("diagonal-".to_string(), true.into());
+            }
+            Variant::Disjoint => {
+                puzzle.insert("disjointgroups".to_string(), true.into());
+            }
+            _ => return Err(format!("f-puzzles has no {variant:?} variant")),
+        }
+    }
+    for relation in &options.relations {
+        let key = match relation {
+            Relation::AntiKnight => "antiknight",
+            Relation::AntiKing => "antiking",
+            Relation::NonConsecutive if !options.negative.contains(&Negative::Kropki) => {
+                "nonconsecutive"
+            }
+            _ => return Err(format!("f-puzzles has no {relation:?} variant")),
+        };
+        puzzle.insert(key.to_string(), true.into());
+    }
+    for negative in &options.negative {
+        let kind = match negative {
+            Negative::Kropki => {
+                puzzle.insert("nonconsecutive".to_string(), true.into());
+                "ratio"
+            }
+            Negative::Xv => "xv",
+        };
+        push(&mut puzzle, "negative", kind.into());
+    }
+
+    let grid = (0..n).map(|y| {
+        (0..n)
+            .map(|x| {
+                let i = y * n + x;
+                let mut cell = Map::new();
+                if let Some(num) = givens[i] {
+                    cell.insert("value".to_string(), (num + 1).into());
+                    cell.insert("given".to_string(), true.into());
+                } else if let Some(num) = solution[i] {
+                    cell.insert("value".to_string(), (num + 1).into());
+                }
+                if let Some(regions) = &regions {
+                    cell.insert("region".to_string(), regions[i].into());
+                }
+                Value::Object(cell)
+            })
+            .collect::<Value>()
+    });
+    puzzle.insert("size".to_string(), n.into());
+    puzzle.insert("grid".to_string(), grid.collect());
+    Ok(Value::Object(puzzle).to_string())
+}
+
+// appends `entry` to the array under `key`
+fn push(puzzle: &mut Map<String, Value>, key: &str, entry: Value) {
+    let entries = puzzle.entry(key).or_insert_with(|| json!([]));
+    entries.as_array_mut().unwrap().push(entry);
+}
+
+// the editor leaves out some kinds and writes others as false or empty lists
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(entries) => entries.is_empty(),
+        Value::String(s) => s.is_empty(),
+        _ => false,
+    }
+}
+
+fn entries<'a>(value: &'a Value, key: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("{key} is no list: {value}"))
+}
+
+// sums and givens are strings in some exports and numbers in others
+fn number(value: Option<&Value>) -> Option<usize> {
+    match value? {
+        Value::Number(number) => number.as_u64().map(|number| number as usize),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+// the fields of a list of cell names like `R1C1`
+fn cells(value: Option<&Value>, n: usize) -> Result<Vec<usize>, String> {
+    let names = value.and_then(Value::as_array).ok_or("missing cells")?;
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_str().ok_or_else(|| format!("bad cell: {name}"))?;
+            parse_field(&name.to_ascii_lowercase(), n)
+        })
+        .collect()
+}
+
+// row and column (counted from 1) of a cell name, which may lie just outside the grid
+fn outside_cell(value: Option<&Value>, n: usize) -> Option<(isize, isize)> {
+    let (y, x) = value?.as_str()?.strip_prefix('R')?.split_once('C')?;
+    let (y, x) = (y.parse().ok()?, x.parse().ok()?);
+    let on_canvas = |i: isize| (0..=n as isize + 1).contains(&i);
+    (on_canvas(y) && on_canvas(x)).then_some((y, x))
+}
+
+fn cell_name(field: usize, n: usize) -> String {
+    format!("R{}C{}", field / n + 1, field % n + 1)
+}
+
+fn cell_names(fields: &[usize], n: usize) -> Vec<String> {
+    fields.iter().map(|field| cell_name(*field, n)).collect()
+}
+
+// a line's fields in the notation of `lines::parse_lines`
+fn path(fields: &[usize], n: usize) -> String {
+    let names = fields
+        .iter()
+        .map(|field| cell_name(*field, n).to_lowercase());
+    names.collect::<Vec<_>>().join("-")
+}
+
+// the index of the edge between neighbours a < b in `edges::parse_edges` order
+fn edge(a: usize, b: usize, n: usize) -> Option<usize> {
+    if b == a + 1 && !b.is_multiple_of(n) {
+        Some(a / n * (n - 1) + a % n)
+    } else if b == a + n {
+        Some(n * (n - 1) + a)
+    } else {
+        None
+    }
+}
+
+// the box of every field, with boxes `w` fields wide and `h` high numbered row by row
+fn boxes(n: usize, w: usize, h: usize) -> Vec<usize> {
+    (0..n * n)
+        .map(|i| (i / n / h) * (n / w) + i % n / w)
+        .collect()
+}
+
+fn default_regions(n: usize) -> Vec<usize> {
+    boxes(n, n / box_height(n), box_height(n))
+}
+
+// cage and region ids of puzzle lines
+fn id(i: usize) -> char {
+    const IDS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    IDS.chars()
+        .nth(i)
+        .unwrap_or_else(|| char::from_u32(0x100 + i as u32).unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_import() {
+        let json = r#"{"size":4,"title":"test","grid":[
+            [{"value":1,"given":true},{},{},{}],
+            [{},{"value":3},{},{}],
+            [{},{},{},{}],
+            [{},{},{},{"value":"4","given":true}]],
+            "diagonal+":true,"diagonal-":true,"antiknight":false,"cage":[],
+            "killercage":[{"cells":["R1C1","R1C2"],"value":"5"}],
+            "arrow":[{"lines":[["R3C1","R4C1"]],"cells":["R2C1"]}],
+            "littlekillersum":[
+                {"cell":"R0C0","direction":"DR","value":"10"},
+                {"cell":"R5C2","direction":"UR","value":""}]}"#;
+        let (line, options) = import(json).unwrap();
+        assert_eq!(
+            line,
+            "1..............4 cages=aa............../5 arrow=r2c1-r3c1-r4c1 \
+             littlekiller=r1c1:se:10"
+        );
+        assert_eq!(options.size, Some(4));
+        assert_eq!(options.variants, [Variant::X]);
+        assert!(options.relations.is_empty());
+
+        let grid = r#""size":4,"grid":[[{},{},{},{}],[{},{},{},{}],[{},{},{},{}],[{},{},{},{}]]"#;
+        let with = |constraint: &str| import(&format!("{{{grid},{constraint}}}"));
+        assert!(with(r#""extraregion":[{"cells":["R1C1"]}]"#).is_err());
+        assert!(with(r#""diagonal+":true"#).is_err());
+        assert!(with(r#""negative":["ratio"]"#).is_err());
+        assert!(with(r#""killercage":[{"cells":["R1C1"]}]"#).is_err());
+        assert!(with(r#""ratio":[{"cells":["R1C1","R2C2"]}]"#).is_err());
+        assert!(with(r#""arrow":[{"lines":[["R1C2"],["R2C1"]],"cells":["R1C1"]}]"#).is_err());
+        assert!(import(r#"{"size":5,"grid":[]}"#).is_err());
+        assert!(import("[]").is_err());
+    }
+    #[test]
+    fn test_round_trip() {
+        let line = "1..........2.... aaabcabbccdbcddd cages=aa.b.b........../3,7 \
+                    edges=w..........x/....b......v thermo=r1c1-r2c2-r3c3 arrow=r4c1-r4c2 \
+                    palindrome=r1c4-r2c4-r3c4 sandwich=1,,,/,,2, \
+                    littlekiller=r1c2:se:6;r4c1:ne:10";
+        let options = Options {
+            variants: vec![Variant::X],
+            relations: vec![Relation::AntiKing, Relation::AntiKnight],
+            negative: vec![Negative::Xv, Negative::Kropki],
+            ..Options::default()
+        };
+        let symbols = Symbols::detect(4, line);
+        let solution = "1234341221434321";
+        let json = export(4, line, &symbols, &options, Some(solution)).unwrap();
+        let (imported, imported_options) = import(&json).unwrap();
+        assert_eq!(imported, line);
+        assert_eq!(imported_options.variants, options.variants);
+        assert_eq!(imported_options.relations, options.relations);
+        assert_eq!(imported_options.negative, options.negative);
+
+        // boxes of a non-default shape become regions
+        let options = Options {
+            shape: Some("2x3".to_string()),
+            ..Options::default()
+        };
+        let symbols = Symbols::detect(6, "");
+        let json = export(6, &".".repeat(36), &symbols, &options, None).unwrap();
+        let (imported, _) = import(&json).unwrap();
+        assert_eq!(
+            imported.split_once(' ').unwrap().1,
+            "aabbccaabbccaabbccddeeffddeeffddeeff"
+        );
+
+        let unsupported = |line: &str| export(4, line, &symbols, &Options::default(), None);
+        assert!(unsupported("................ edges=<.........../............").is_err());
+        assert!(unsupported("................ whisper=r1c1-r1c2").is_err());
+        assert!(unsupported("................ sandwich=,,,/,,,/1,,,").is_err());
+        assert!(unsupported("..............").is_err());
+    }
+}
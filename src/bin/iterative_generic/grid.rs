@@ -0,0 +1,267 @@
+//! Geometry and notation of an N×N grid. Everything the 9×9 solvers hard-code (box shape, lookup
+//! tables, the full candidate mask, the digits 1-9) is derived from N here, with the box shape
+//! overridable at runtime.
+
+/// candidate bitset, wide enough for N <= 32
+pub type Mask = u32;
+
+/// largest divisor of `n` that is at most its square root: the height of the boxes
+pub const fn box_height(n: usize) -> usize {
+    let mut h = 1;
+    let mut d = 1;
+    while d * d <= n {
+        if n.is_multiple_of(d) {
+            h = d;
+        }
+        d += 1;
+    }
+    h
+}
+
+/// Compile time properties of the N×N grid. The default boxes are the squarest ones that tile
+/// it, wider than high if they can't be square.
+pub struct Grid<const N: usize>;
+impl<const N: usize> Grid<N> {
+    // default box shape
+    pub const BOX_H: usize = box_height(N);
+    pub const BOX_W: usize = N / Self::BOX_H;
+    /// all N numbers as candidates
+    pub const ALL: Mask = {
+        assert!(
+            N >= 1 && N as u32 <= Mask::BITS,
+            "grid too large for the mask type"
+        );
+        if N as u32 == Mask::BITS {
+            Mask::MAX
+        } else {
+            (1 << N) - 1
+        }
+    };
+}
+
+/// Which box (or irregular region) every field of an N×N grid belongs to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout<const N: usize> {
+    boxes: [[u8; N]; N],
+}
+impl<const N: usize> Default for Layout<N> {
+    fn default() -> Self {
+        Self::rectangular(Grid::<N>::BOX_W, Grid::<N>::BOX_H).unwrap()
+    }
+}
+impl<const N: usize> Layout<N> {
+    /// boxes `w` fields wide and `h` fields high, numbered row by row
+    pub fn rectangular(w: usize, h: usize) -> Result<Layout<N>, String> {
+        if w * h != N {
+            return Err(format!("{w}x{h} boxes don't fit a {N}x{N} grid"));
+        }
+        let mut boxes = [[0u8; N]; N];
+        for (y, row) in boxes.iter_mut().enumerate() {
+            for (x, b) in row.iter_mut().enumerate() {
+                *b = ((y / h) * (N / w) + x / w) as u8;
+            }
+        }
+        Ok(Layout { boxes })
+    }
+    /// parses a box shape given as `<width>x<height>`
+    pub fn from_shape(shape: &str) -> Result<Layout<N>, String> {
+        let (w, h) = shape
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .ok_or_else(|| format!("bad box shape: {shape}"))?;
+        Layout::rectangular(w, h)
+    }
+    /// irregular (Jigsaw) regions, given as one id character per field, row by row. Every region
+    /// has to be N orthogonally connected fields. Regions are numbered by first appearance.
+    pub fn from_regions(ids: &str) -> Result<Layout<N>, String> {
+        let ids = ids.chars().collect::<Vec<_>>();
+        if ids.len() != N * N {
+            return Err(format!("{} region ids for {} fields", ids.len(), N * N));
+        }
+        let mut seen = Vec::new();
+        let mut boxes = [[0u8; N]; N];
+        for (i, id) in ids.iter().enumerate() {
+            let b = match seen.iter().position(|s| s == id) {
+                Some(b) => b,
+                None => {
+                    seen.push(*id);
+                    seen.len() - 1
+                }
+            };
+            if b >= N {
+                return Err(format!("more than {N} regions"));
+            }
+            boxes[i / N][i % N] = b as u8;
+        }
+        let layout = Layout { boxes };
+        for (b, id) in seen.iter().enumerate() {
+            let size = layout
+                .boxes
+                .iter()
+                .flatten()
+                .filter(|r| **r as usize == b)
+                .count();
+            if size != N {
+                return Err(format!("region {id} has {size} fields instead of {N}"));
+            }
+            if layout.connected_size(b) != N {
+                return Err(format!("region {id} is not connected"));
+            }
+        }
+        Ok(layout)
+    }
+    // number of fields of region `b` reachable from its first field
+    fn connected_size(&self, b: usize) -> usize {
+        let first = (0..N * N).find(|i| self.boxes[i / N][i % N] as usize == b);
+        let mut reached = vec![false; N * N];
+        let mut todo = first.into_iter().collect::<Vec<_>>();
+        let mut size = 0;
+        while let Some(i) = todo.pop() {
+            if reached[i] {
+                continue;
+            }
+            reached[i] = true;
+            size += 1;
+            let (x, y) = (i % N, i / N);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < N).then(|| i + 1),
+                (y > 0).then(|| i - N),
+                (y + 1 < N).then(|| i + N),
+            ];
+            todo.extend(
+                neighbours
+                    .into_iter()
+                    .flatten()
+                    .filter(|j| self.boxes[j / N][j % N] as usize == b),
+            );
+        }
+        size
+    }
+    pub fn region(&self, x: usize, y: usize) -> usize {
+        self.boxes[y][x] as usize
+    }
+    /// width and height of the boxes, None for irregular regions
+    pub fn box_shape(&self) -> Option<(usize, usize)> {
+        (1..=N)
+            .filter(|w| N.is_multiple_of(*w))
+            .map(|w| (w, N / w))
+            .find(|(w, h)| Layout::rectangular(*w, *h).is_ok_and(|l| l == *self))
+    }
+}
+
+/// The characters standing for the numbers 1..=N, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbols(Vec<char>);
+
+// 1-9 first, then letters, so 9×9 grids read as always and 16×16 ones as 1-9A-G
+pub const DIGITS_FIRST: &str = "123456789ABCDEFGHIJKLMNOPQRSTUVW";
+const HEX: &str = "0123456789ABCDEF";
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl Symbols {
+    pub fn new(chars: &str) -> Symbols {
+        Symbols(chars.chars().map(|c| c.to_ascii_uppercase()).collect())
+    }
+    /// picks the notation of a puzzle line: hex (0-F) for 16×16 grids using a 0 but no G,
+    /// letters only (A-Y) for 25×25 grids without digits, and 1-9 followed by letters otherwise.
+    pub fn detect(n: usize, line: &str) -> Symbols {
+        let has = |pred: fn(&char) -> bool| line.chars().any(|c| pred(&c));
+        let chars = if n == 16 && has(|c| *c == '0') && !has(|c| c.eq_ignore_ascii_case(&'G')) {
+            HEX
+        } else if n > 9 && n <= LETTERS.len() && !has(char::is_ascii_digit) {
+            &LETTERS[..n]
+        } else {
+            &DIGITS_FIRST[..n]
+        };
+        Symbols::new(chars)
+    }
+    /// the number (0-based) written as `c`. None for an empty field.
+    pub fn parse(&self, c: char) -> Result<Option<usize>, String> {
+        let upper = c.to_ascii_uppercase();
+        if let Some(num) = self.0.iter().position(|s| *s == upper) {
+            Ok(Some(num))
+        } else if matches!(c, '.' | '0' | '_' | '-') {
+            Ok(None)
+        } else {
+            Err(format!("bad symbol in input: {c}"))
+        }
+    }
+    pub fn get(&self, num: usize) -> char {
+        self.0[num]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_geometry() {
+        assert_eq!((Grid::<4>::BOX_W, Grid::<4>::BOX_H), (2, 2));
+        assert_eq!((Grid::<9>::BOX_W, Grid::<9>::BOX_H), (3, 3));
+        assert_eq!((Grid::<16>::BOX_W, Grid::<16>::BOX_H), (4, 4));
+        assert_eq!((Grid::<25>::BOX_W, Grid::<25>::BOX_H), (5, 5));
+        assert_eq!(Grid::<9>::ALL, 0b111111111);
+        assert_eq!(Grid::<25>::ALL.count_ones(), 25);
+        assert_eq!((Grid::<6>::BOX_W, Grid::<6>::BOX_H), (3, 2));
+        assert_eq!((Grid::<12>::BOX_W, Grid::<12>::BOX_H), (4, 3));
+
+        assert_eq!(Layout::<9>::default().region(7, 4), 5);
+        assert_eq!(Layout::<16>::default().region(15, 15), 15);
+        assert_eq!(Layout::<16>::default().region(8, 5), 6);
+        let six = Layout::<6>::default();
+        assert_eq!(six.region(2, 1), 0);
+        assert_eq!(six.region(3, 1), 1);
+        assert_eq!(six.region(0, 2), 2);
+        let tall = Layout::<6>::from_shape("2x3").unwrap();
+        assert_eq!(tall.region(2, 1), 1);
+        assert_eq!(tall.region(0, 3), 3);
+        assert_eq!(Layout::<12>::from_shape("3x4").unwrap().region(11, 11), 11);
+        assert_eq!(tall.box_shape(), Some((2, 3)));
+        assert!(Layout::<12>::from_shape("3x3").is_err());
+        assert!(Layout::<12>::from_shape("3").is_err());
+    }
+    #[test]
+    fn test_regions() {
+        let jigsaw = Layout::<9>::from_regions(
+            "111123333412222536411255536441253336741255666744259666747959998747999898777888888",
+        )
+        .unwrap();
+        assert_eq!(jigsaw.region(0, 1), 3);
+        assert_eq!(jigsaw.region(8, 8), 8);
+        assert_eq!(jigsaw.box_shape(), None);
+        let boxes = "AABB\
+                     AABB\
+                     CCDD\
+                     CCDD";
+        assert_eq!(Layout::<4>::from_regions(boxes), Ok(Layout::default()));
+
+        let bad_size = Layout::<4>::from_regions("AAABBBBBCCDDCCDD");
+        assert_eq!(
+            bad_size,
+            Err("region A has 3 fields instead of 4".to_string())
+        );
+        let split = Layout::<4>::from_regions("ABBAABBACCDDCCDD");
+        assert_eq!(split, Err("region A is not connected".to_string()));
+        assert!(Layout::<4>::from_regions("ABCDEABCDABCDABC").is_err());
+        assert!(Layout::<4>::from_regions("AABB").is_err());
+    }
+    #[test]
+    fn test_symbols() {
+        let nine = Symbols::detect(9, "1.3");
+        assert_eq!(nine.parse('9'), Ok(Some(8)));
+        assert_eq!(nine.parse('0'), Ok(None));
+        assert!(nine.parse('A').is_err());
+
+        let hex = Symbols::detect(16, "0.F.a");
+        assert_eq!(hex.parse('0'), Ok(Some(0)));
+        assert_eq!(hex.parse('f'), Ok(Some(15)));
+        let alnum = Symbols::detect(16, "0.G.1");
+        assert_eq!(alnum.parse('G'), Ok(Some(15)));
+        assert_eq!(alnum.parse('0'), Ok(None));
+
+        let letters = Symbols::detect(25, "A.Y..");
+        assert_eq!(letters.parse('y'), Ok(Some(24)));
+        assert_eq!(letters.get(0), 'A');
+    }
+}
@@ -0,0 +1,110 @@
+//! Variants that only add all-different houses to the rows, columns and boxes. Each house is a
+//! list of field indices (`y * N + x`) and gets a free mask on the board like a box.
+
+use super::grid::Layout;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// Sudoku X: both main diagonals
+    X,
+    /// Hyper Sudoku / Windoku: the windows between the boxes, one field off each box corner
+    Windoku,
+    /// the centre field of every box
+    CentreDot,
+    /// Disjoint Groups: every position within a box, across all boxes
+    Disjoint,
+}
+impl Variant {
+    pub fn from_name(name: &str) -> Result<Variant, String> {
+        match name {
+            "x" | "diagonal" => Ok(Variant::X),
+            "windoku" | "hyper" => Ok(Variant::Windoku),
+            "centre-dot" | "center-dot" => Ok(Variant::CentreDot),
+            "disjoint" => Ok(Variant::Disjoint),
+            _ => Err(format!("unknown variant: {name}")),
+        }
+    }
+    /// the houses the variant adds to a grid with `layout`
+    pub fn houses<const N: usize>(self, layout: &Layout<N>) -> Result<Vec<Vec<usize>>, String> {
+        let shape = layout.box_shape();
+        let within_boxes = |(bx, by): (usize, usize)| {
+            let (w, h) = shape.ok_or(format!("{self:?} needs regular boxes"))?;
+            let (w, h) = (w as isize, h as isize);
+            let (bx, by) = (bx as isize, by as isize);
+            // one field per box, `(bx, by)` from its top left corner
+            Ok::<_, String>(
+                (0..N as isize / h)
+                    .flat_map(|y| (0..N as isize / w).map(move |x| (x, y)))
+                    .map(|(x, y)| ((y * h + by) * N as isize + x * w + bx) as usize)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        match self {
+            Variant::X => Ok(vec![
+                (0..N).map(|i| i * N + i).collect(),
+                (0..N).map(|i| i * N + N - 1 - i).collect(),
+            ]),
+            Variant::Windoku => match shape {
+                Some((w, h)) if w == h => {
+                    // windows start one field into every box but the last, in both directions
+                    let starts = (0..w - 1).map(|i| 1 + i * (w + 1)).collect::<Vec<_>>();
+                    Ok(starts
+                        .iter()
+                        .flat_map(|y0| starts.iter().map(move |x0| (*x0, *y0)))
+                        .map(|(x0, y0)| {
+                            (y0..y0 + w)
+                                .flat_map(|y| (x0..x0 + w).map(move |x| y * N + x))
+                                .collect()
+                        })
+                        .collect())
+                }
+                _ => Err("Windoku needs square boxes".to_string()),
+            },
+            Variant::CentreDot => match shape {
+                Some((w, h)) if !w.is_multiple_of(2) && !h.is_multiple_of(2) => {
+                    Ok(vec![within_boxes((w / 2, h / 2))?])
+                }
+                _ => Err("Centre-dot needs boxes with a centre field".to_string()),
+            },
+            Variant::Disjoint => {
+                let (w, h) = shape.ok_or("Disjoint Groups need regular boxes")?;
+                (0..h)
+                    .flat_map(|y| (0..w).map(move |x| (x, y)))
+                    .map(within_boxes)
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_houses() {
+        let layout = Layout::<9>::default();
+        let x = Variant::X.houses(&layout).unwrap();
+        assert_eq!(x[1], [8, 16, 24, 32, 40, 48, 56, 64, 72]);
+        let windows = Variant::Windoku.houses(&layout).unwrap();
+        assert_eq!(windows.len(), 4);
+        assert_eq!(windows[3], [50, 51, 52, 59, 60, 61, 68, 69, 70]);
+        let centres = Variant::CentreDot.houses(&layout).unwrap();
+        assert_eq!(centres, [[10, 13, 16, 37, 40, 43, 64, 67, 70]]);
+        let centres = [10, 13, 16, 37, 40, 43, 64, 67, 70];
+        let disjoint = Variant::Disjoint.houses(&layout).unwrap();
+        assert_eq!(disjoint.len(), 9);
+        assert_eq!(disjoint[4], centres);
+        assert_eq!(disjoint[5], centres.map(|i| i + 1));
+
+        let six = Layout::<6>::default();
+        assert_eq!(
+            Variant::Disjoint.houses(&six).unwrap()[5],
+            [8, 11, 20, 23, 32, 35]
+        );
+        assert!(Variant::Windoku.houses(&six).is_err());
+        assert!(Variant::CentreDot.houses(&six).is_err());
+        let jigsaw = Layout::<4>::from_regions("AABBACBBACDDCCDD").unwrap();
+        assert!(Variant::Disjoint.houses(&jigsaw).is_err());
+        assert_eq!(Variant::X.houses(&jigsaw).unwrap()[0], [0, 5, 10, 15]);
+    }
+}
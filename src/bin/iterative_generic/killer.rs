@@ -0,0 +1,235 @@
+//! Killer Sudoku: cages whose fields hold distinct numbers adding up to the cage sum. Which numbers
+//! an open field of a cage can still take follows from a table of all digit combinations per
+//! (size, sum), filtered by the numbers already placed in the cage.
+
+use super::grid::Mask;
+
+/// For every number of fields and sum the masks of all sets of distinct numbers 1..=n with that
+/// size and sum.
+#[derive(Debug)]
+pub struct Combinations {
+    // indexed by size, then sum
+    masks: Vec<Vec<Vec<Mask>>>,
+}
+impl Combinations {
+    // every subset of 1..=n is enumerated, so this stays well below a second up to 16×16 grids
+    pub const MAX_N: usize = 16;
+
+    pub fn new(n: usize) -> Combinations {
+        assert!(
+            n <= Self::MAX_N,
+            "no cages on grids larger than {}",
+            Self::MAX_N
+        );
+        let mut masks = (0..=n)
+            .map(|_| vec![Vec::new(); n * (n + 1) / 2 + 1])
+            .collect::<Vec<_>>();
+        for mask in 0..1 << n {
+            let mask = mask as Mask;
+            let sum = (0..n)
+                .filter(|i| mask >> i & 1 == 1)
+                .map(|i| i + 1)
+                .sum::<usize>();
+            masks[mask.count_ones() as usize][sum].push(mask);
+        }
+        Combinations { masks }
+    }
+    /// numbers appearing in any combination of `size` numbers from `free` adding up to `sum`
+    pub fn union(&self, size: usize, sum: usize, free: Mask) -> Mask {
+        let Some(masks) = self.masks.get(size).and_then(|by_sum| by_sum.get(sum)) else {
+            return 0;
+        };
+        masks
+            .iter()
+            .filter(|mask| *mask & !free == 0)
+            .fold(0, |union, mask| union | mask)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Cage {
+    sum: usize,
+    // numbers not placed in the cage yet
+    free: Mask,
+    placed_sum: usize,
+    open: usize,
+    // candidates of the open fields, kept up to date by `place` and `remove`
+    allowed: Mask,
+}
+impl Cage {
+    /// a cage of `size` fields, all of them open
+    pub fn new(size: usize, sum: usize, all: Mask, combinations: &Combinations) -> Cage {
+        let mut cage = Cage {
+            open: size,
+            sum,
+            free: all,
+            placed_sum: 0,
+            allowed: 0,
+        };
+        cage.update(combinations);
+        cage
+    }
+    pub fn allowed(&self) -> Mask {
+        self.allowed
+    }
+    /// `num` (0-based) was set in one of the cage's fields
+    pub fn place(&mut self, num: usize, combinations: &Combinations) {
+        self.free &= !(1 << num);
+        self.placed_sum += num + 1;
+        self.open -= 1;
+        self.update(combinations);
+    }
+    /// undoes `place`
+    pub fn remove(&mut self, num: usize, combinations: &Combinations) {
+        self.free |= 1 << num;
+        self.placed_sum -= num + 1;
+        self.open += 1;
+        self.update(combinations);
+    }
+    fn update(&mut self, combinations: &Combinations) {
+        self.allowed = match self.sum.checked_sub(self.placed_sum) {
+            Some(left) => combinations.union(self.open, left, self.free),
+            None => 0,
+        };
+    }
+}
+
+/// parses a cage layout: one cage id per field ('.' for none), a '/', and the cage sums separated
+/// by commas, in the order in which the ids first appear. Returns the fields and sum of every
+/// cage.
+pub fn parse_cages(spec: &str, n: usize) -> Result<Vec<(Vec<usize>, usize)>, String> {
+    let (ids, sums) = spec
+        .split_once('/')
+        .ok_or("cages need a '/' before the sums")?;
+    if ids.chars().count() != n * n {
+        return Err(format!(
+            "{} cage ids for {} fields",
+            ids.chars().count(),
+            n * n
+        ));
+    }
+    let mut seen = Vec::new();
+    let mut cages = Vec::<Vec<usize>>::new();
+    for (field, id) in ids.chars().enumerate().filter(|(_, id)| *id != '.') {
+        match seen.iter().position(|s| *s == id) {
+            Some(cage) => cages[cage].push(field),
+            None => {
+                seen.push(id);
+                cages.push(vec![field]);
+            }
+        }
+    }
+    let sums = sums
+        .split(',')
+        .map(|sum| sum.parse().map_err(|_| format!("bad cage sum: {sum}")))
+        .collect::<Result<Vec<usize>, _>>()?;
+    if sums.len() != cages.len() {
+        return Err(format!("{} sums for {} cages", sums.len(), cages.len()));
+    }
+    Ok(cages.into_iter().zip(sums).collect())
+}
+
+/// Cages implied by the 45 rule: every house holds each number once, so its fields add up to
+/// `n * (n + 1) / 2`. The fields of a house not covered by cages lying inside it (innies) add up
+/// to that total minus those cages' sums. If the house is fully covered by cages, the fields of
+/// those cages sticking out of it (outies) add up to the cages' sums minus the total. Innies or
+/// outies make a new cage when they fit into a single house, as cages need distinct numbers.
+pub fn innies_outies(
+    n: usize,
+    cages: &[(Vec<usize>, usize)],
+    houses: &[Vec<usize>],
+) -> Vec<(Vec<usize>, usize)> {
+    let total = n * (n + 1) / 2;
+    let in_one_house =
+        |fields: &[usize]| houses.iter().any(|h| fields.iter().all(|f| h.contains(f)));
+    let mut derived = Vec::<(Vec<usize>, usize)>::new();
+    for house in houses {
+        let touching = cages
+            .iter()
+            .filter(|(fields, _)| fields.iter().any(|f| house.contains(f)));
+        let (inside, sticking_out) =
+            touching.partition::<Vec<_>, _>(|(fields, _)| fields.iter().all(|f| house.contains(f)));
+
+        let inside_sum = inside.iter().map(|(_, sum)| sum).sum::<usize>();
+        let mut innies = house.clone();
+        innies.retain(|f| !inside.iter().any(|(fields, _)| fields.contains(f)));
+        if !innies.is_empty() && innies.len() < house.len() && inside_sum <= total {
+            derived.push((innies, total - inside_sum));
+        }
+
+        let covered = house
+            .iter()
+            .all(|f| cages.iter().any(|(fields, _)| fields.contains(f)));
+        let mut outies = sticking_out
+            .iter()
+            .flat_map(|(fields, _)| fields.iter().filter(|f| !house.contains(f)))
+            .copied()
+            .collect::<Vec<_>>();
+        let outside_sum = inside_sum + sticking_out.iter().map(|(_, sum)| sum).sum::<usize>();
+        outies.sort();
+        if covered && !outies.is_empty() && outside_sum >= total && in_one_house(&outies) {
+            derived.push((outies, outside_sum - total));
+        }
+    }
+    derived.retain(|(fields, _)| fields.len() <= n);
+    let mut unique = Vec::new();
+    for (mut fields, sum) in derived {
+        fields.sort();
+        let known = |(other, _): &(Vec<usize>, usize)| {
+            let mut other = other.clone();
+            other.sort();
+            other == fields
+        };
+        if !cages.iter().any(known) && !unique.iter().any(known) {
+            unique.push((fields, sum));
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_combinations() {
+        let combinations = Combinations::new(9);
+        // 17 in two fields: 8 + 9 only
+        assert_eq!(combinations.union(2, 17, 0x1ff), 0b110000000);
+        // 10 in three fields: 1+2+7, 1+3+6, 1+4+5, 2+3+5
+        assert_eq!(combinations.union(3, 10, 0x1ff), 0b001111111);
+        // without the 1 only 2+3+5 is left
+        assert_eq!(combinations.union(3, 10, 0x1fe), 0b000010110);
+        assert_eq!(combinations.union(2, 2, 0x1ff), 0);
+        assert_eq!(combinations.union(0, 0, 0x1ff), 0);
+
+        let mut cage = Cage::new(3, 10, 0x1ff, &combinations);
+        cage.place(4, &combinations);
+        // 5 placed, 5 left in two fields: 1+4, 2+3
+        assert_eq!(cage.allowed(), 0b000001111);
+        cage.place(0, &combinations);
+        assert_eq!(cage.allowed(), 0b000001000);
+        cage.remove(0, &combinations);
+        cage.remove(4, &combinations);
+        assert_eq!(cage.allowed(), 0b001111111);
+    }
+    #[test]
+    fn test_parse_cages() {
+        let cages = parse_cages("AAB.BBCDD/3,12,4,9", 3).unwrap();
+        assert_eq!(cages.len(), 4);
+        assert_eq!(cages[1], (vec![2, 4, 5], 12));
+        assert_eq!(cages[3], (vec![7, 8], 9));
+        assert!(parse_cages("AAB.BBCDD/3,12,4", 3).is_err());
+        assert!(parse_cages("AAB.BBCD/3,12,4,9", 3).is_err());
+        assert!(parse_cages("AAB.BBCDD", 3).is_err());
+    }
+    #[test]
+    fn test_innies_outies() {
+        // 4×4, houses: rows 0 and 1 and box 0
+        let houses = [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![0, 1, 4, 5]];
+        let cages = [(vec![0, 1], 3), (vec![2, 6], 7), (vec![3, 7], 6)];
+        let derived = innies_outies(4, &cages, &houses);
+        // row 0 innies: fields 2 and 3 hold 10 - 3. Row 0 outies: fields 6 and 7 hold 16 - 10.
+        // Box 0 innies: fields 4 and 5 hold 7.
+        assert_eq!(derived, [(vec![2, 3], 7), (vec![6, 7], 6), (vec![4, 5], 7)]);
+    }
+}
@@ -0,0 +1,261 @@
+//! Constraints along paths of fields: Thermometers, Arrows, Palindromes and German Whispers.
+//!
+//! The board talks to them through `Constraint`, the same way it treats its own masks: every
+//! number set in one of the constraint's fields is passed to `place` and taken back by `remove`
+//! in reverse order, and `allowed` narrows the candidates of an open field. A line only keeps the
+//! numbers set along it and bounds the candidates of its open fields by them.
+
+use super::grid::Mask;
+
+pub trait Constraint {
+    /// the fields (`y * N + x`) the constraint is on
+    fn fields(&self) -> &[usize];
+    /// `num` (0-based) was set in `field`
+    fn place(&mut self, field: usize, num: usize);
+    /// undoes `place`
+    fn remove(&mut self, field: usize, num: usize);
+    /// the numbers the open `field` can still hold
+    fn allowed(&self, field: usize) -> Mask;
+    fn clone_box(&self) -> Box<dyn Constraint>;
+}
+impl Clone for Box<dyn Constraint> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    /// strictly increasing from the bulb, the first field
+    Thermo,
+    /// the circle, the first field, holds the sum of the other fields
+    Arrow,
+    /// reads the same from both ends
+    Palindrome,
+    /// neighbours along the line differ by at least (N + 1) / 2, 5 on 9×9 grids
+    Whisper,
+}
+impl LineKind {
+    pub fn from_name(name: &str) -> Option<LineKind> {
+        match name {
+            "thermo" => Some(LineKind::Thermo),
+            "arrow" => Some(LineKind::Arrow),
+            "palindrome" => Some(LineKind::Palindrome),
+            "whisper" => Some(LineKind::Whisper),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Line {
+    kind: LineKind,
+    n: usize,
+    fields: Vec<usize>,
+    // the number set in each field along the line
+    nums: Vec<Option<usize>>,
+}
+impl Line {
+    pub fn new(kind: LineKind, fields: Vec<usize>, n: usize) -> Result<Line, String> {
+        if fields.len() < 2 {
+            return Err(format!("{kind:?} needs at least two fields"));
+        }
+        if kind == LineKind::Thermo && fields.len() > n {
+            return Err(format!(
+                "a thermometer of {} fields can't rise",
+                fields.len()
+            ));
+        }
+        for (a, b) in fields.iter().zip(&fields[1..]) {
+            let (dx, dy) = ((a % n).abs_diff(b % n), (a / n).abs_diff(b / n));
+            if dx > 1 || dy > 1 || a == b {
+                return Err(format!("{kind:?} fields {a} and {b} aren't neighbours"));
+            }
+        }
+        Ok(Line {
+            kind,
+            n,
+            nums: vec![None; fields.len()],
+            fields,
+        })
+    }
+    fn position(&self, field: usize) -> usize {
+        self.fields.iter().position(|f| *f == field).unwrap()
+    }
+    fn range(&self, lo: isize, hi: isize) -> Mask {
+        range(lo, hi, self.n)
+    }
+    fn thermo(&self, p: usize) -> Mask {
+        // room for the rising numbers before and after the field
+        let mut lo = p as isize;
+        let mut hi = (self.n - self.fields.len() + p) as isize;
+        for (i, num) in self.nums.iter().enumerate() {
+            let (Some(num), i) = (num.map(|num| num as isize), i as isize) else {
+                continue;
+            };
+            let p = p as isize;
+            if i < p {
+                lo = lo.max(num + p - i);
+            } else if i > p {
+                hi = hi.min(num - (i - p));
+            }
+        }
+        self.range(lo, hi)
+    }
+    fn arrow(&self, p: usize) -> Mask {
+        let n = self.n as isize;
+        // sum (counted from 1) and number of the open fields of the arrow but `p`
+        let placed = (1..self.nums.len())
+            .filter(|i| *i != p)
+            .filter_map(|i| self.nums[i])
+            .map(|num| num as isize + 1)
+            .sum::<isize>();
+        let open = (1..self.nums.len())
+            .filter(|i| *i != p && self.nums[*i].is_none())
+            .count() as isize;
+        if p == 0 {
+            return self.range(placed + open - 1, placed + open * n - 1);
+        }
+        match self.nums[0] {
+            Some(circle) => {
+                let left = circle as isize + 1 - placed;
+                self.range(left - open * n - 1, left - open - 1)
+            }
+            None => self.range(0, n - placed - open - 1),
+        }
+    }
+    fn palindrome(&self, p: usize) -> Mask {
+        match self.nums[self.fields.len() - 1 - p] {
+            Some(num) => 1 << num,
+            None => self.range(0, self.n as isize),
+        }
+    }
+    fn whisper(&self, p: usize) -> Mask {
+        let gap = self.n.div_ceil(2) as isize;
+        let mut allowed = self.range(0, self.n as isize);
+        let neighbours = [p.checked_sub(1), Some(p + 1)];
+        for num in neighbours
+            .into_iter()
+            .flatten()
+            .filter_map(|i| *self.nums.get(i)?)
+        {
+            let num = num as isize;
+            allowed &= self.range(0, num - gap) | self.range(num + gap, self.n as isize);
+        }
+        allowed
+    }
+}
+impl Constraint for Line {
+    fn fields(&self) -> &[usize] {
+        &self.fields
+    }
+    fn place(&mut self, field: usize, num: usize) {
+        let p = self.position(field);
+        self.nums[p] = Some(num);
+    }
+    fn remove(&mut self, field: usize, num: usize) {
+        let p = self.position(field);
+        assert_eq!(self.nums[p], Some(num));
+        self.nums[p] = None;
+    }
+    fn allowed(&self, field: usize) -> Mask {
+        let p = self.position(field);
+        match self.kind {
+            LineKind::Thermo => self.thermo(p),
+            LineKind::Arrow => self.arrow(p),
+            LineKind::Palindrome => self.palindrome(p),
+            LineKind::Whisper => self.whisper(p),
+        }
+    }
+    fn clone_box(&self) -> Box<dyn Constraint> {
+        Box::new(self.clone())
+    }
+}
+
+/// the numbers lo..=hi, clipped to 0..n
+pub fn range(lo: isize, hi: isize, n: usize) -> Mask {
+    (lo.max(0)..=hi.min(n as isize - 1)).fold(0, |mask, num| mask | 1 << num)
+}
+
+/// parses the lines of one kind, given as paths separated by ';'. A path lists its fields as
+/// `r<row>c<column>` (counted from 1) separated by '-', e.g. `r1c1-r2c2-r2c3`.
+pub fn parse_lines(kind: LineKind, spec: &str, n: usize) -> Result<Vec<Line>, String> {
+    spec.split(';')
+        .map(|path| {
+            let fields = path
+                .split('-')
+                .map(|field| parse_field(field, n))
+                .collect::<Result<Vec<_>, String>>()?;
+            Line::new(kind, fields, n)
+        })
+        .collect()
+}
+
+/// the index (`y * N + x`) of a field given as `r<row>c<column>`, counted from 1
+pub fn parse_field(field: &str, n: usize) -> Result<usize, String> {
+    let (y, x) = field
+        .strip_prefix('r')
+        .and_then(|field| field.split_once('c'))
+        .and_then(|(y, x)| Some((y.parse::<usize>().ok()?, x.parse::<usize>().ok()?)))
+        .filter(|(y, x)| (1..=n).contains(y) && (1..=n).contains(x))
+        .ok_or_else(|| format!("bad field: {field}"))?;
+    Ok((y - 1) * n + x - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_lines() {
+        let mut thermo = Line::new(LineKind::Thermo, vec![0, 1, 2, 11], 9).unwrap();
+        assert_eq!(thermo.allowed(0), 0b000111111);
+        assert_eq!(thermo.allowed(2), 0b011111100);
+        thermo.place(1, 3);
+        // a 4 second: the bulb holds 1-3, the others at least 5 and 6
+        assert_eq!(thermo.allowed(0), 0b000000111);
+        assert_eq!(thermo.allowed(2), 0b011110000);
+        assert_eq!(thermo.allowed(11), 0b111100000);
+        thermo.remove(1, 3);
+        assert_eq!(thermo.allowed(11), 0b111111000);
+
+        let mut arrow = Line::new(LineKind::Arrow, vec![0, 1, 2], 9).unwrap();
+        assert_eq!(arrow.allowed(0), 0b111111110);
+        assert_eq!(arrow.allowed(1), 0b011111111);
+        arrow.place(0, 5);
+        // 6 in the circle: 1-5 on the arrow
+        assert_eq!(arrow.allowed(2), 0b000011111);
+        arrow.place(1, 1);
+        assert_eq!(arrow.allowed(2), 0b000001000);
+        arrow.remove(0, 5);
+        assert_eq!(arrow.allowed(0), 0b111111100);
+        assert_eq!(arrow.allowed(2), 0b001111111);
+
+        let mut palindrome = Line::new(LineKind::Palindrome, vec![0, 10, 20], 9).unwrap();
+        palindrome.place(20, 6);
+        assert_eq!(palindrome.allowed(0), 0b001000000);
+        assert_eq!(palindrome.allowed(10), 0b111111111);
+
+        let mut whisper = Line::new(LineKind::Whisper, vec![0, 1, 2], 9).unwrap();
+        whisper.place(1, 3);
+        assert_eq!(whisper.allowed(0), 0b100000000);
+        whisper.place(2, 8);
+        assert_eq!(whisper.allowed(0), 0b100000000);
+        whisper.remove(2, 8);
+        whisper.remove(1, 3);
+        whisper.place(0, 4);
+        assert_eq!(whisper.allowed(1), 0);
+
+        assert!(Line::new(LineKind::Thermo, vec![0, 2], 9).is_err());
+        assert!(Line::new(LineKind::Whisper, vec![0], 9).is_err());
+    }
+    #[test]
+    fn test_parse_lines() {
+        let lines = parse_lines(LineKind::Arrow, "r1c1-r2c2-r2c3;r9c9-r9c8", 9).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].fields(), [0, 10, 11]);
+        assert_eq!(lines[1].fields(), [80, 79]);
+        assert!(parse_lines(LineKind::Thermo, "r1c1-r1c10", 9).is_err());
+        assert!(parse_lines(LineKind::Thermo, "r1c1-x", 9).is_err());
+        assert!(parse_lines(LineKind::Thermo, "r1c1-r1c3", 9).is_err());
+    }
+}
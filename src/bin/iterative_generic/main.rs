@@ -0,0 +1,1407 @@
+//! The solver for every grid size, box shape and variant. It is a bin of its own rather than a
+//! generalisation of `iterative`, whose 9×9 board keeps its fixed `u16` masks and peer tables:
+//! with the runtime layout and the constraint hooks, this one takes 1.7 times as long for the same
+//! steps on top95.txt. The two share the search, plain MRV over an explicit stack with
+//! `manipulate`/`rollback` undo, and its `--max-steps`/`--timeout` limits. The heuristics,
+//! restarts and the parallel search of `iterative` are 9×9 only, and the 9×9 bins reject the input
+//! only this one reads.
+
+use bitset_core::BitSet;
+use booklet::{Booklet, Difficulty};
+use edges::Negative;
+use grid::{Grid, Layout, Mask, Symbols};
+use houses::Variant;
+use killer::{Cage, Combinations};
+use lines::{Constraint, LineKind};
+use multi::MultiBoard;
+use outside::ClueKind;
+use relations::Relation;
+use render::{Cell, Style};
+use report::{Format, Record, Status};
+use std::cell::RefCell;
+use std::env::args;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use svg::Drawing;
+
+mod booklet;
+mod edges;
+mod fpuzzles;
+mod grid;
+mod houses;
+mod killer;
+mod lines;
+mod multi;
+mod outside;
+mod relations;
+mod render;
+mod report;
+mod svg;
+
+// grid sizes a puzzle line can have
+const SIZES: [usize; 6] = [4, 6, 9, 12, 16, 25];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Addr {
+    x: usize,
+    y: usize,
+    b: usize,
+}
+impl Addr {
+    pub fn new<const N: usize>(x: usize, y: usize, layout: &Layout<N>) -> Addr {
+        Addr {
+            x,
+            y,
+            b: layout.region(x, y),
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+enum Field {
+    #[default]
+    Empty,
+    Set(u8),
+}
+
+#[derive(Clone)]
+struct Board<const N: usize> {
+    open: Vec<Addr>,
+    fields: [[Field; N]; N],
+    h_free: [Mask; N],
+    v_free: [Mask; N],
+    b_free: [Mask; N],
+    layout: Layout<N>,
+    // houses added by variants, and for every field (y * N + x) the ones it is in
+    extra_free: Vec<Mask>,
+    extra_of: Vec<Vec<usize>>,
+    // Killer cages, and for every field the cages it is in. Cages derived by the 45 rule overlap
+    // the others.
+    cages: Vec<Cage>,
+    cages_of: Vec<Vec<usize>>,
+    // only built once the first cage is added
+    combinations: Option<Rc<Combinations>>,
+    // neighbour relations: for every field the related fields, and for every field and number
+    // how many set fields rule the number out there
+    related: Vec<Vec<(usize, Relation)>>,
+    bans: Vec<[u8; N]>,
+    banned: Vec<Mask>,
+    // constraints along lines (thermometers, arrows, ...), and for every field the ones it is on
+    constraints: Vec<Box<dyn Constraint>>,
+    constraints_of: Vec<Vec<usize>>,
+    // for every field whether it was given
+    given: Vec<bool>,
+}
+impl<const N: usize> Default for Board<N> {
+    fn default() -> Self {
+        Self::with_layout(Layout::default())
+    }
+}
+#[derive(Clone, Debug)]
+struct Edit {
+    addr: Addr,
+    num: u8,
+}
+impl<const N: usize> Board<N> {
+    pub fn with_layout(layout: Layout<N>) -> Board<N> {
+        Board {
+            open: (0..N)
+                .flat_map(|y| (0..N).map(move |x| (x, y)))
+                .map(|(x, y)| Addr::new(x, y, &layout))
+                .collect(),
+            fields: [[Field::Empty; N]; N],
+            h_free: [Grid::<N>::ALL; N],
+            v_free: [Grid::<N>::ALL; N],
+            b_free: [Grid::<N>::ALL; N],
+            layout,
+            extra_free: Vec::new(),
+            extra_of: vec![Vec::new(); N * N],
+            cages: Vec::new(),
+            cages_of: vec![Vec::new(); N * N],
+            combinations: None,
+            related: vec![Vec::new(); N * N],
+            bans: vec![[0; N]; N * N],
+            banned: vec![0; N * N],
+            constraints: Vec::new(),
+            constraints_of: vec![Vec::new(); N * N],
+            given: vec![false; N * N],
+        }
+    }
+    /// adds an all-different house over the given field indices
+    pub fn add_house(&mut self, fields: &[usize]) {
+        let mut free = Grid::<N>::ALL;
+        for field in fields {
+            if let Field::Set(num) = self.fields[field / N][field % N] {
+                free.bit_reset(num as usize);
+            }
+            self.extra_of[*field].push(self.extra_free.len());
+        }
+        self.extra_free.push(free);
+    }
+    /// the fields of every row, column, box and extra house
+    pub fn houses(&self) -> Vec<Vec<usize>> {
+        let mut houses = vec![Vec::new(); 3 * N + self.extra_free.len()];
+        for y in 0..N {
+            for x in 0..N {
+                let field = y * N + x;
+                houses[y].push(field);
+                houses[N + x].push(field);
+                houses[2 * N + self.layout.region(x, y)].push(field);
+                for house in &self.extra_of[field] {
+                    houses[3 * N + house].push(field);
+                }
+            }
+        }
+        houses
+    }
+    /// adds a Killer cage: the given fields hold distinct numbers adding up to `sum`
+    pub fn add_cage(&mut self, fields: Vec<usize>, sum: usize) -> Result<(), String> {
+        if N > Combinations::MAX_N {
+            return Err(format!(
+                "no cages on grids larger than {0}x{0}",
+                Combinations::MAX_N
+            ));
+        }
+        if fields.len() > N {
+            return Err(format!(
+                "a cage of {} fields can't hold distinct numbers",
+                fields.len()
+            ));
+        }
+        let combinations = self
+            .combinations
+            .get_or_insert_with(|| Rc::new(Combinations::new(N)));
+        let mut cage = Cage::new(fields.len(), sum, Grid::<N>::ALL, combinations);
+        for field in fields {
+            if let Field::Set(num) = self.fields[field / N][field % N] {
+                cage.place(num as usize, combinations);
+            }
+            self.cages_of[field].push(self.cages.len());
+        }
+        self.cages.push(cage);
+        Ok(())
+    }
+    pub fn add_constraint(&mut self, mut constraint: Box<dyn Constraint>) {
+        for field in constraint.fields().to_vec() {
+            if let Field::Set(num) = self.fields[field / N][field % N] {
+                constraint.place(field, num as usize);
+            }
+            self.constraints_of[field].push(self.constraints.len());
+        }
+        self.constraints.push(constraint);
+    }
+    /// relates every field to its neighbours by `relation`, in both directions
+    pub fn add_relation(&mut self, relation: Relation) {
+        for field in 0..N * N {
+            for other in relation.neighbours::<N>(field) {
+                self.related[field].push((other, relation));
+                if let Field::Set(num) = self.fields[field / N][field % N] {
+                    self.ban(other, relation.excluded(num as usize, Grid::<N>::ALL));
+                }
+            }
+        }
+    }
+    /// relates two fields both ways, `relation` being that of `b`'s number to `a`'s
+    pub fn add_pair(&mut self, a: usize, b: usize, relation: Relation) {
+        for (field, other, relation) in [(a, b, relation), (b, a, relation.reversed())] {
+            self.related[field].push((other, relation));
+            if let Field::Set(num) = self.fields[field / N][field % N] {
+                self.ban(other, relation.excluded(num as usize, Grid::<N>::ALL));
+            }
+        }
+    }
+    fn ban(&mut self, field: usize, nums: Mask) {
+        for num in (0..N).filter(|num| nums.bit_test(*num)) {
+            self.bans[field][num] += 1;
+            self.banned[field].bit_set(num);
+        }
+    }
+    fn unban(&mut self, field: usize, nums: Mask) {
+        for num in (0..N).filter(|num| nums.bit_test(*num)) {
+            self.bans[field][num] -= 1;
+            if self.bans[field][num] == 0 {
+                self.banned[field].bit_reset(num);
+            }
+        }
+    }
+    pub fn place_givens(&mut self, line: &str, symbols: &Symbols) -> Result<(), String> {
+        let board = self;
+        let mut line = line.chars();
+        for i in 0..N {
+            for j in 0..N {
+                let c = line.next().ok_or("line ended early")?;
+                let num = symbols.parse(c)?;
+
+                if let Some(num) = num {
+                    let addr = Addr::new(j, i, &board.layout);
+                    board.given[i * N + j] = true;
+                    let i = board.open.iter().position(|a| *a == addr).unwrap();
+                    board.open.remove(i);
+                    board.manipulate(&addr, num);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_h_mut(&mut self, addr: &Addr) -> &mut Mask {
+        &mut self.h_free[addr.y]
+    }
+    fn get_v_mut(&mut self, addr: &Addr) -> &mut Mask {
+        &mut self.v_free[addr.x]
+    }
+    fn get_b_mut(&mut self, addr: &Addr) -> &mut Mask {
+        &mut self.b_free[addr.b]
+    }
+    fn get_h(&self, addr: &Addr) -> Mask {
+        self.h_free[addr.y]
+    }
+    fn get_v(&self, addr: &Addr) -> Mask {
+        self.v_free[addr.x]
+    }
+    fn get_b(&self, addr: &Addr) -> Mask {
+        self.b_free[addr.b]
+    }
+    pub fn manipulate(&mut self, addr: &Addr, num: usize) -> Edit {
+        assert!(num < N);
+
+        self.get_h_mut(addr).bit_reset(num);
+        self.get_v_mut(addr).bit_reset(num);
+        self.get_b_mut(addr).bit_reset(num);
+        for house in &self.extra_of[addr.y * N + addr.x] {
+            self.extra_free[*house].bit_reset(num);
+        }
+        for cage in &self.cages_of[addr.y * N + addr.x] {
+            let combinations = self.combinations.as_ref().unwrap();
+            self.cages[*cage].place(num, combinations);
+        }
+        for i in 0..self.related[addr.y * N + addr.x].len() {
+            let (other, relation) = self.related[addr.y * N + addr.x][i];
+            self.ban(other, relation.excluded(num, Grid::<N>::ALL));
+        }
+        for constraint in &self.constraints_of[addr.y * N + addr.x] {
+            self.constraints[*constraint].place(addr.y * N + addr.x, num);
+        }
+
+        let f = &mut self.fields[addr.y][addr.x];
+        assert_eq!(*f, Field::Empty);
+        *f = Field::Set(num as u8);
+        Edit {
+            addr: *addr,
+            num: num as u8,
+        }
+    }
+    pub fn rollback(&mut self, edit: Edit) {
+        self.get_h_mut(&edit.addr).bit_set(edit.num as usize);
+        self.get_v_mut(&edit.addr).bit_set(edit.num as usize);
+        self.get_b_mut(&edit.addr).bit_set(edit.num as usize);
+        for house in &self.extra_of[edit.addr.y * N + edit.addr.x] {
+            self.extra_free[*house].bit_set(edit.num as usize);
+        }
+        for cage in &self.cages_of[edit.addr.y * N + edit.addr.x] {
+            let combinations = self.combinations.as_ref().unwrap();
+            self.cages[*cage].remove(edit.num as usize, combinations);
+        }
+        for i in 0..self.related[edit.addr.y * N + edit.addr.x].len() {
+            let (other, relation) = self.related[edit.addr.y * N + edit.addr.x][i];
+            self.unban(other, relation.excluded(edit.num as usize, Grid::<N>::ALL));
+        }
+        for constraint in self.constraints_of[edit.addr.y * N + edit.addr.x]
+            .iter()
+            .rev()
+        {
+            let field = edit.addr.y * N + edit.addr.x;
+            self.constraints[*constraint].remove(field, edit.num as usize);
+        }
+        let f = &mut self.fields[edit.addr.y][edit.addr.x];
+        assert_eq!(*f, Field::Set(edit.num));
+        *f = Field::Empty;
+    }
+    pub fn candidates_for(&self, addr: &Addr) -> Mask {
+        let field = addr.y * N + addr.x;
+        let mut candidates = self.get_h(addr) & self.get_v(addr) & self.get_b(addr);
+        for house in &self.extra_of[field] {
+            candidates &= self.extra_free[*house];
+        }
+        for cage in &self.cages_of[field] {
+            candidates &= self.cages[*cage].allowed();
+        }
+        for constraint in &self.constraints_of[field] {
+            candidates &= self.constraints[*constraint].allowed(field);
+        }
+        candidates & !self.banned[field]
+    }
+    /// the fields row by row in the notation of the givens, '.' where open
+    pub fn to_line(&self, symbols: &Symbols) -> String {
+        self.fields
+            .iter()
+            .flatten()
+            .map(|field| match field {
+                Field::Empty => '.',
+                Field::Set(num) => symbols.get(*num as usize),
+            })
+            .collect()
+    }
+    // every field (y * N + x) as given, solved or with its candidates
+    fn cells(&self) -> Vec<Cell> {
+        (0..N * N)
+            .map(|i| match self.fields[i / N][i % N] {
+                Field::Set(num) if self.given[i] => Cell::Given(num as usize),
+                Field::Set(num) => Cell::Solved(num as usize),
+                Field::Empty => {
+                    Cell::Open(self.candidates_for(&Addr::new(i % N, i / N, &self.layout)))
+                }
+            })
+            .collect()
+    }
+    fn regions(&self) -> Vec<usize> {
+        (0..N * N)
+            .map(|i| self.layout.region(i % N, i / N))
+            .collect()
+    }
+    /// the grid drawn with borders, see `render`
+    pub fn render(&self, symbols: &Symbols, style: &Style) -> String {
+        render::render(N, &self.cells(), &self.regions(), symbols, style)
+    }
+    /// the grid as an SVG picture with the decorations of its puzzle line, see `svg`
+    pub fn svg(&self, line: &str, symbols: &Symbols, pencilmarks: bool) -> Result<String, String> {
+        let (cells, regions) = (self.cells(), self.regions());
+        svg::svg(N, line, &cells, &regions, symbols, pencilmarks)
+    }
+    pub fn print(&self, symbols: &Symbols) {
+        for y in 0..N {
+            for x in 0..N {
+                match self.fields[y][x] {
+                    Field::Empty => print!(". "),
+                    Field::Set(num) => print!("{} ", symbols.get(num as usize)),
+                }
+            }
+            println!();
+        }
+    }
+}
+
+/// What the search needs from a board: the open fields, their candidates and setting and
+/// clearing numbers. Implemented by the single grid `Board` and by `MultiBoard`.
+trait Search {
+    type Addr: Copy;
+    type Edit;
+    fn open(&self) -> &Vec<Self::Addr>;
+    fn open_mut(&mut self) -> &mut Vec<Self::Addr>;
+    fn candidates_for(&self, addr: &Self::Addr) -> Mask;
+    fn manipulate(&mut self, addr: &Self::Addr, num: usize) -> Self::Edit;
+    fn rollback(&mut self, edit: Self::Edit);
+}
+impl<const N: usize> Search for Board<N> {
+    type Addr = Addr;
+    type Edit = Edit;
+    fn open(&self) -> &Vec<Addr> {
+        &self.open
+    }
+    fn open_mut(&mut self) -> &mut Vec<Addr> {
+        &mut self.open
+    }
+    fn candidates_for(&self, addr: &Addr) -> Mask {
+        Board::candidates_for(self, addr)
+    }
+    fn manipulate(&mut self, addr: &Addr, num: usize) -> Edit {
+        Board::manipulate(self, addr, num)
+    }
+    fn rollback(&mut self, edit: Edit) {
+        Board::rollback(self, edit)
+    }
+}
+
+#[derive(Debug)]
+enum IterState<A, E> {
+    Initial,
+    Applied { candidates: Mask, edit: E, addr: A },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Stats {
+    max_depth: usize,
+    num_steps: usize,
+}
+
+/// Bounds on `search`. The default is unbounded.
+#[derive(Clone, Debug, Default)]
+struct Limits {
+    max_steps: Option<usize>,
+    deadline: Option<Instant>,
+}
+impl Limits {
+    // the deadline is only checked every this many steps
+    const CHECK_INTERVAL: usize = 1024;
+
+    // whether a limit is hit after `steps` steps of the search
+    fn exceeded(&self, steps: usize) -> bool {
+        if self.max_steps.is_some_and(|max| steps >= max) {
+            return true;
+        }
+        steps.is_multiple_of(Self::CHECK_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SolveOutcome {
+    Solved,
+    Unsolvable,
+    /// a limit was hit. The board is back in the state it was in before `search`.
+    Aborted {
+        steps: usize,
+        depth: usize,
+    },
+}
+
+/// runs the search, handing every solution to `found`, until `found` returns true. Then the board
+/// holds that solution and the result is `Solved`. Otherwise the search runs out of candidates or
+/// hits a limit, and the board is back where it was.
+fn search<B: Search>(
+    board: &mut B,
+    stats: &mut Stats,
+    limits: &Limits,
+    mut found: impl FnMut(&B) -> bool,
+) -> SolveOutcome {
+    let mut stack = vec![IterState::Initial];
+    let start = stats.num_steps;
+    loop {
+        if limits.exceeded(stats.num_steps - start) {
+            let depth = stack.len();
+            // undo every applied edit, Initial states have nothing to undo
+            while let Some(state) = stack.pop() {
+                if let IterState::Applied { edit, addr, .. } = state {
+                    board.rollback(edit);
+                    board.open_mut().push(addr);
+                }
+            }
+            return SolveOutcome::Aborted {
+                steps: stats.num_steps,
+                depth,
+            };
+        }
+        stats.max_depth = stats.max_depth.max(stack.len());
+        stats.num_steps += 1;
+        let (mut candidates, addr) = match stack.pop() {
+            Some(IterState::Initial) => {
+                if board.open().is_empty() {
+                    if found(board) {
+                        return SolveOutcome::Solved;
+                    }
+                    // the solution's Initial state is gone, so going on backtracks out of it
+                    continue;
+                }
+                let (candidates, min_i) = best_candidate(board);
+                (candidates, board.open_mut().swap_remove(min_i))
+            }
+            Some(IterState::Applied {
+                candidates,
+                edit,
+                addr,
+            }) => {
+                board.rollback(edit);
+                (candidates, addr)
+            }
+            None => return SolveOutcome::Unsolvable,
+        };
+        if candidates == 0 {
+            // all candidate numbers knocked out but not solved -> return / backtrack
+            board.open_mut().push(addr);
+            continue;
+        }
+        // test candidate field:
+        // 1. knock out lowest bit
+        // 2. 'recursion'
+        let test = candidates.trailing_zeros() as usize;
+        candidates.bit_reset(test);
+        let edit = board.manipulate(&addr, test);
+        stack.push(IterState::Applied {
+            candidates,
+            edit,
+            addr,
+        });
+        stack.push(IterState::Initial);
+    }
+}
+
+fn best_candidate<B: Search>(board: &B) -> (Mask, usize) {
+    let mut min_candidates = 0;
+    let mut min_i = usize::MAX;
+    let mut min = u32::MAX;
+    for (i, field) in board.open().iter().enumerate() {
+        let candidates = board.candidates_for(field);
+        let num = candidates.count_ones();
+        if num < min {
+            min_i = i;
+            min = num;
+            min_candidates = candidates;
+        }
+        if min <= 1 {
+            break;
+        }
+    }
+    if min_i == usize::MAX {
+        panic!("no minimal candidate found. should be impossible.")
+    }
+    (min_candidates, min_i)
+}
+
+/// Command line options that apply to every puzzle of the file.
+#[derive(Clone, Debug, Default)]
+struct Options {
+    // `--size=<n>` for lines with trailing data, otherwise the size follows from the length
+    size: Option<usize>,
+    // `--symbols=<chars>`, otherwise detected per line
+    symbols: Option<Symbols>,
+    // `--box=<width>x<height>`, e.g. `--box=2x3` for 6×6 grids with tall boxes
+    shape: Option<String>,
+    // `--variant=<name>[,<name>...]`, repeatable. Names of house variants and of neighbour
+    // relations can be mixed.
+    variants: Vec<Variant>,
+    relations: Vec<Relation>,
+    // `--negative=kropki,xv`: edges without a Kropki dot or without X or V are constrained, too
+    negative: Vec<Negative>,
+    // add the cages implied by the 45 rule to Killer puzzles, unless `--no-innies-outies`
+    innies_outies: bool,
+    // `--export=fpuzzles`: print every puzzle as an f-puzzles export instead, solved if possible
+    fpuzzles: bool,
+    // `--format=json|csv|line`: print a record per puzzle instead of the boards
+    format: Format,
+    // `--unique`: search on for a second solution, reported as multiple solutions
+    unique: bool,
+    // `--max-steps=<n>` and `--timeout=<ms>`: bounds on the search of every puzzle
+    max_steps: Option<usize>,
+    timeout: Option<Duration>,
+    // `--grid=ascii|unicode`, `--pencilmarks`, `--color`: draw single grids with borders
+    style: Option<Style>,
+    // `draw`: write pictures of the puzzles and their solutions instead
+    drawing: Option<Drawing>,
+    // `booklet`: collect the pictures of the puzzles and their solutions for a PDF booklet
+    booklet: Option<Booklet>,
+}
+impl Options {
+    fn from_args() -> Options {
+        let arg =
+            |prefix: &str| args().find_map(|arg| arg.strip_prefix(prefix).map(str::to_string));
+        let mut variants = Vec::new();
+        let mut relations = Vec::new();
+        let names = args()
+            .filter_map(|arg| arg.strip_prefix("--variant=").map(str::to_string))
+            .collect::<Vec<_>>();
+        for name in names.iter().flat_map(|names| names.split(',')) {
+            match Variant::from_name(name) {
+                Ok(variant) => variants.push(variant),
+                Err(_) => {
+                    relations.push(Relation::from_name(name).unwrap_or_else(|err| panic!("{err}")))
+                }
+            }
+        }
+        Options {
+            size: arg("--size=").map(|n| n.parse().expect("bad size")),
+            symbols: arg("--symbols=").map(|chars| Symbols::new(&chars)),
+            shape: arg("--box="),
+            variants,
+            relations,
+            negative: Negative::from_args(),
+            innies_outies: !args().any(|arg| arg == "--no-innies-outies"),
+            fpuzzles: args().any(|arg| arg == "--export=fpuzzles"),
+            format: Format::from_args(),
+            unique: args().any(|arg| arg == "--unique"),
+            max_steps: arg("--max-steps=").map(|n| n.parse().expect("bad limit")),
+            timeout: arg("--timeout=")
+                .map(|ms| Duration::from_millis(ms.parse().expect("bad limit"))),
+            style: Style::from_args(),
+            drawing: Drawing::from_args(),
+            booklet: Booklet::from_args(),
+        }
+    }
+    // whether the boards and messages are printed
+    fn text(&self) -> bool {
+        self.format == Format::Text && !self.fpuzzles && !self.pictures()
+    }
+    // the limits of a puzzle, each gets the full timeout
+    fn limits(&self) -> Limits {
+        Limits {
+            max_steps: self.max_steps,
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+    // whether pictures of the puzzles are taken
+    fn pictures(&self) -> bool {
+        self.drawing.is_some() || self.booklet.is_some()
+    }
+    /// adds the options of a puzzle imported from f-puzzles, whose line only reads with its own
+    /// size and symbols
+    fn merge(&mut self, imported: Options) {
+        self.size = imported.size;
+        self.symbols = imported.symbols;
+        self.variants.extend(imported.variants);
+        self.relations.extend(imported.relations);
+        self.negative.extend(imported.negative);
+    }
+}
+
+// A puzzle line holds the givens, optionally followed by whitespace separated parts:
+// - one region id per field for Jigsaw puzzles, e.g. `111222333111222333<more ids>`
+// - `cages=<cage ids>/<sums>` for Killer puzzles, see `killer::parse_cages`
+// - `edges=<horizontal>/<vertical>` for Greater-Than, Kropki and XV markers, see
+//   `edges::parse_edges`
+// - `thermo=`, `arrow=`, `palindrome=` or `whisper=` followed by paths, see `lines::parse_lines`
+// - `sandwich=` or `skyscraper=` followed by clues around the grid, see `outside::parse_sides`
+// - `littlekiller=` followed by diagonal sums, see `outside::parse_diagonals`
+fn board_from_line<const N: usize>(
+    line: &str,
+    symbols: &Symbols,
+    options: &Options,
+) -> Result<Board<N>, String> {
+    let mut parts = line.split_whitespace();
+    let givens = parts.next().ok_or("empty puzzle line")?;
+    let mut regions = None;
+    let mut cages = Vec::new();
+    // without markers, negative constraints still apply to every edge
+    let no_markers = format!("{0}/{0}", ".".repeat(N * (N - 1)));
+    let mut edges = no_markers.as_str();
+    let mut lines = Vec::new();
+    let mut clues = Vec::new();
+    for part in parts {
+        let line_kind = part
+            .split_once('=')
+            .and_then(|(name, spec)| Some((LineKind::from_name(name)?, spec)));
+        let clue_kind = part
+            .split_once('=')
+            .and_then(|(name, spec)| Some((ClueKind::from_name(name)?, spec)));
+        if let Some(spec) = part.strip_prefix("cages=") {
+            cages = killer::parse_cages(spec, N)?;
+        } else if let Some(spec) = part.strip_prefix("edges=") {
+            edges = spec;
+        } else if let Some((kind, spec)) = line_kind {
+            lines.extend(lines::parse_lines(kind, spec, N)?);
+        } else if let Some(spec) = part.strip_prefix("littlekiller=") {
+            clues.extend(outside::parse_diagonals(spec, N)?);
+        } else if let Some((kind, spec)) = clue_kind {
+            clues.extend(outside::parse_sides(kind, spec, N)?);
+        } else if !part.contains('=') && part.chars().count() == N * N {
+            regions = Some(part);
+        } else {
+            let key = part.split_once('=').map_or(part, |(key, _)| key);
+            return Err(format!("unknown constraint: {key}"));
+        }
+    }
+    let layout = match (regions, &options.shape) {
+        (Some(regions), _) => Layout::from_regions(regions),
+        (None, Some(shape)) => Layout::from_shape(shape),
+        (None, None) => Ok(Layout::default()),
+    }?;
+    let mut board = Board::<N>::with_layout(layout);
+    for variant in &options.variants {
+        for house in variant.houses(&board.layout)? {
+            board.add_house(&house);
+        }
+    }
+    for relation in &options.relations {
+        board.add_relation(*relation);
+    }
+    for (a, b, relation) in edges::parse_edges(edges, N, &options.negative)? {
+        board.add_pair(a, b, relation);
+    }
+    for line in lines {
+        board.add_constraint(Box::new(line));
+    }
+    for clue in clues {
+        board.add_constraint(Box::new(clue));
+    }
+    if options.innies_outies && !cages.is_empty() {
+        let derived = killer::innies_outies(N, &cages, &board.houses());
+        cages.extend(derived);
+    }
+    for (fields, sum) in cages {
+        board.add_cage(fields, sum)?;
+    }
+    board.place_givens(givens, symbols)?;
+    Ok(board)
+}
+
+/// What became of a puzzle line.
+struct Outcome {
+    solution: Option<String>,
+    // a second solution was found
+    multiple: bool,
+    // a limit was hit before the search was done
+    aborted: bool,
+    stats: Stats,
+}
+impl Outcome {
+    fn status(&self) -> Status {
+        match (&self.solution, self.multiple) {
+            _ if self.aborted => Status::Aborted,
+            (None, _) => Status::Unsolvable,
+            (Some(_), false) => Status::Solved,
+            (Some(_), true) => Status::Multiple,
+        }
+    }
+}
+
+/// searches the board for a solution, with `--unique` for a second one, too. In text mode the
+/// board is printed before and after.
+fn run<B: Search>(
+    board: &mut B,
+    options: &Options,
+    print: impl Fn(&B),
+    to_line: impl Fn(&B) -> String,
+) -> Outcome {
+    if options.text() {
+        println!("=========================\nsolving:\n");
+        print(board);
+    }
+    let mut stats = Stats::default();
+    let mut solution = None;
+    let outcome = search(board, &mut stats, &options.limits(), |board| {
+        if solution.is_some() {
+            return true;
+        }
+        solution = Some(to_line(board));
+        if options.text() {
+            print(board);
+        }
+        !options.unique
+    });
+    let multiple = outcome == SolveOutcome::Solved && options.unique;
+    let aborted = matches!(outcome, SolveOutcome::Aborted { .. });
+    if options.text() {
+        if let SolveOutcome::Aborted { steps, depth } = outcome {
+            println!("aborted after {steps} steps at depth {depth}");
+        } else if solution.is_some() {
+            println!("max depth: {}, steps: {}", stats.max_depth, stats.num_steps);
+            println!(
+                "{}",
+                if multiple {
+                    "multiple solutions"
+                } else {
+                    "solved."
+                }
+            );
+        } else {
+            println!("unsolvable");
+        }
+    }
+    Outcome {
+        solution,
+        multiple,
+        aborted,
+        stats,
+    }
+}
+
+fn solve_line<const N: usize>(
+    line: &str,
+    symbols: &Symbols,
+    options: &Options,
+) -> Result<Outcome, String> {
+    let mut board = board_from_line::<N>(line, symbols, options)?;
+    let print = |board: &Board<N>| match &options.style {
+        Some(style) => print!("{}", board.render(symbols, style)),
+        None => board.print(symbols),
+    };
+    // the pictures of the puzzle, and of the solution when it's found
+    let pencilmarks = options.drawing.as_ref().is_some_and(|d| d.pencilmarks);
+    let puzzle = options
+        .pictures()
+        .then(|| board.svg(line, symbols, pencilmarks));
+    let puzzle = puzzle.transpose()?;
+    let open = board.open.len();
+    let solved = RefCell::new(None);
+    let to_line = |board: &Board<N>| {
+        if options.pictures() {
+            *solved.borrow_mut() = Some(board.svg(line, symbols, false));
+        }
+        board.to_line(symbols)
+    };
+    let outcome = run(&mut board, options, print, to_line);
+    let solved = solved.into_inner().transpose()?;
+    if let (Some(drawing), Some(puzzle)) = (&options.drawing, &puzzle) {
+        drawing.write("", puzzle)?;
+        if let Some(solved) = &solved {
+            drawing.write("-solution", solved)?;
+        }
+    }
+    if let (Some(booklet), Some(puzzle)) = (&options.booklet, puzzle) {
+        let difficulty = Difficulty::from_steps(outcome.stats.num_steps, open);
+        booklet.add(puzzle, solved, difficulty);
+    }
+    if options.fpuzzles {
+        let solution = outcome.solution.as_deref();
+        println!("{}", fpuzzles::export(N, line, symbols, options, solution)?);
+    }
+    Ok(outcome)
+}
+
+// A multi-grid puzzle line holds the givens of the whole canvas, row by row, followed by
+// `grids=<offsets>`, see `multi::parse_offsets`.
+fn solve_multi_line<const N: usize>(
+    line: &str,
+    symbols: &Symbols,
+    options: &Options,
+) -> Result<Outcome, String> {
+    if options.fpuzzles {
+        return Err("f-puzzles exports hold a single grid".to_string());
+    }
+    if options.pictures() {
+        return Err("pictures hold a single grid".to_string());
+    }
+    let (givens, offsets) = line
+        .split_once(" grids=")
+        .map(|(givens, offsets)| (givens.trim(), offsets.trim()))
+        .unwrap();
+    let offsets = multi::parse_offsets::<N>(offsets)?;
+    let mut board = MultiBoard::<N>::new(&offsets)?;
+    board.place_givens(givens, symbols)?;
+    Ok(run(
+        &mut board,
+        options,
+        |b| b.print(symbols),
+        |b| b.to_line(symbols),
+    ))
+}
+
+/// solves a puzzle line of any size
+fn solve_any(line: &str, options: &Options) -> Result<Outcome, String> {
+    let givens = line.split_whitespace().next().unwrap_or_default();
+    if line.contains(" grids=") {
+        // multi-grid canvases don't tell the grid size, 9×9 unless `--size` is given
+        let n = options.size.unwrap_or(9);
+        let symbols = options
+            .symbols
+            .clone()
+            .unwrap_or_else(|| Symbols::detect(n, givens));
+        return match n {
+            4 => solve_multi_line::<4>(line, &symbols, options),
+            6 => solve_multi_line::<6>(line, &symbols, options),
+            9 => solve_multi_line::<9>(line, &symbols, options),
+            12 => solve_multi_line::<12>(line, &symbols, options),
+            16 => solve_multi_line::<16>(line, &symbols, options),
+            25 => solve_multi_line::<25>(line, &symbols, options),
+            _ => Err(format!("unsupported size: {n}")),
+        };
+    }
+    let n = options
+        .size
+        .or_else(|| SIZES.into_iter().find(|n| n * n == givens.chars().count()))
+        .ok_or_else(|| format!("no grid has {} fields", givens.chars().count()))?;
+    let symbols = options
+        .symbols
+        .clone()
+        .unwrap_or_else(|| Symbols::detect(n, givens));
+    match n {
+        4 => solve_line::<4>(line, &symbols, options),
+        6 => solve_line::<6>(line, &symbols, options),
+        9 => solve_line::<9>(line, &symbols, options),
+        12 => solve_line::<12>(line, &symbols, options),
+        16 => solve_line::<16>(line, &symbols, options),
+        25 => solve_line::<25>(line, &symbols, options),
+        _ => Err(format!("unsupported size: {n}")),
+    }
+}
+
+fn main() {
+    let mut options = Options::from_args();
+    let mut args = args();
+    if args.len() < 2 {
+        println!("missing filename");
+        return;
+    }
+
+    let filename = args.next_back().unwrap();
+
+    if options.text() {
+        println!("{filename}");
+    }
+    // an f-puzzles export holds a single puzzle
+    let lines = if filename.ends_with(".json") {
+        let json = std::fs::read_to_string(&filename).unwrap();
+        let (line, imported) = fpuzzles::import(&json).unwrap_or_else(|err| panic!("{err}"));
+        options.merge(imported);
+        vec![line]
+    } else {
+        let file = File::open(&filename).unwrap();
+        io::BufReader::new(file)
+            .lines()
+            .map(Result::unwrap)
+            .collect()
+    };
+    // f-puzzles exports take the place of the records
+    let format = if options.fpuzzles {
+        Format::Text
+    } else {
+        options.format
+    };
+    format.print_header();
+    // pictures are named after the file and the line of the puzzle
+    let stem = std::path::Path::new(&filename)
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(drawing) = &mut options.drawing {
+            drawing.name = format!("{stem}-{}", i + 1);
+        }
+        let start = Instant::now();
+        let outcome = solve_any(line, &options);
+        if format == Format::Text {
+            // as before, a bad line ends the run
+            outcome.unwrap_or_else(|err| panic!("{err}"));
+            continue;
+        }
+        let record = match outcome {
+            Ok(outcome) => Record {
+                puzzle: line,
+                status: outcome.status(),
+                solution: outcome.solution,
+                max_depth: outcome.stats.max_depth,
+                num_steps: outcome.stats.num_steps,
+                time: start.elapsed(),
+            },
+            Err(err) => {
+                eprintln!("{line}: {err}");
+                Record {
+                    puzzle: line,
+                    solution: None,
+                    status: Status::ParseError,
+                    max_depth: 0,
+                    num_steps: 0,
+                    time: start.elapsed(),
+                }
+            }
+        };
+        record.print(format);
+    }
+    if let Some(booklet) = &options.booklet {
+        booklet.write(&stem).unwrap_or_else(|err| panic!("{err}"));
+    }
+    if options.text() {
+        println!("end");
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // stops at the first solution
+    fn solve<B: Search>(board: &mut B, stats: &mut Stats) -> bool {
+        search(board, stats, &Limits::default(), |_| true) == SolveOutcome::Solved
+    }
+
+    fn solution<const N: usize>(line: &str) -> String {
+        solution_with(line, Layout::<N>::default())
+    }
+    fn solution_with<const N: usize>(line: &str, layout: Layout<N>) -> String {
+        let symbols = Symbols::detect(N, line);
+        let mut board = Board::<N>::with_layout(layout);
+        board.place_givens(line, &symbols).unwrap();
+        assert!(solve(&mut board, &mut Stats::default()));
+        board
+            .fields
+            .iter()
+            .flatten()
+            .map(|f| match f {
+                Field::Set(num) => symbols.get(*num as usize),
+                Field::Empty => panic!("open field after solving"),
+            })
+            .collect()
+    }
+    // the solution of a puzzle line with constraints, like on the command line
+    // three lines of each kind
+    const LINES: &str =
+        "...........4..2...1..................3..........7...5..........4..9......5......3 \
+         thermo=r5c5-r6c4-r5c3;r1c7-r2c8-r2c7;r4c4-r5c4-r6c5-r6c6 \
+         arrow=r7c8-r8c8-r9c8-r9c9;r3c6-r2c6-r3c5;r4c8-r3c8-r4c7 \
+         palindrome=r4c6-r5c7-r5c6-r4c5-r3c4;r1c4-r1c3-r2c2;r3c9-r4c9-r5c8 \
+         whisper=r6c7-r7c7-r7c6-r8c7;r2c1-r3c1-r3c2-r4c2;r6c1-r7c2-r8c2-r7c1";
+    fn solution_of(line: &str, options: &Options) -> String {
+        let symbols = Symbols::detect(9, line);
+        let mut board = board_from_line::<9>(line, &symbols, options).unwrap();
+        assert!(solve(&mut board, &mut Stats::default()));
+        board.to_line(&symbols)
+    }
+    // every row, column and box holds every number once
+    fn valid<const N: usize>(solution: &str) -> bool {
+        let symbols = Symbols::detect(N, solution);
+        let mut board = Board::<N>::default();
+        board.place_givens(solution, &symbols).unwrap();
+        board.open.is_empty()
+            && board
+                .h_free
+                .iter()
+                .chain(&board.v_free)
+                .chain(&board.b_free)
+                .all(|m| *m == 0)
+    }
+
+    #[test]
+    fn test_manipulate() {
+        let mut board = Board::<16>::default();
+        let a = &Addr::new(15, 15, &board.layout);
+        assert_eq!(a.b, 15);
+        assert_eq!(board.get_h(a).count_ones(), 16);
+        let edit = board.manipulate(a, 15);
+        assert!(!board.get_h(a).bit_test(15));
+        assert!(!board.get_v(a).bit_test(15));
+        assert!(!board.get_b(a).bit_test(15));
+        assert_eq!(
+            board
+                .candidates_for(&Addr::new(12, 12, &board.layout))
+                .count_ones(),
+            15
+        );
+        board.rollback(edit);
+        assert_eq!(board.get_b(a).count_ones(), 16);
+    }
+    #[test]
+    fn test_solve_sizes() {
+        let four = solution::<4>("1.34.4.22.4343.1");
+        assert_eq!(four, "1234341221434321");
+
+        let nine = solution::<9>(
+            "4.....8.5.3..........7......2.....6.....8.4......1.......6.3.7.5..2.....1.4......",
+        );
+        assert_eq!(
+            nine,
+            "417369825632158947958724316825437169791586432346912758289643571573291684164875293"
+        );
+
+        let sixteen = solution::<16>(
+            "G8.C3..1E2.A.........C9....4D....D2..6...5.18CG.4..6D.....8G..1...G.F...3....8C....\
+             .....7.F..DEG..4.53.B.G2.F7..6....D..8.9C.3.A8469A5......1..B....G.CD...8.5.......9..\
+             FB17G......2..B75EA34.8..B...G82....EA.D....6...1.B...2...7..A.5..C....3.......F.D.56..7",
+        );
+        assert!(sixteen.starts_with("G89C3B51E2DA764F135B8C9G6F74DEA2"));
+        assert!(valid::<16>(&sixteen));
+
+        // a pattern solution with 40% of the fields cleared
+        let twentyfive = (0..25 * 25)
+            .map(|i| {
+                let (x, y) = (i % 25, i / 25);
+                match (x * 7 + y * 3) % 5 {
+                    0 | 1 => '.',
+                    _ => Symbols::detect(25, "").get((5 * (y % 5) + y / 5 + x) % 25),
+                }
+            })
+            .collect::<String>();
+        assert!(valid::<25>(&solution::<25>(&twentyfive)));
+    }
+    #[test]
+    fn test_rectangular_boxes() {
+        assert_eq!(
+            solution::<6>("51.........216......4..5.....16..25."),
+            "512436436512165324324165253641641253"
+        );
+        let tall = Layout::from_shape("2x3").unwrap();
+        assert_eq!(
+            solution_with::<6>("43...........51.3...5.......2.6..21.", tall),
+            "432561164352251634325146516423643215"
+        );
+        assert_eq!(
+            solution::<12>(
+                "C..3.B..9....5..7...3.A..6....A8..4..........9C65..4.1.......9C..A2..B7.3A52.7....8\
+                 .B4...C...A.....C3.....6....5..9.....4..6...C52......A.B2..9."
+            ),
+            "C8A32B459617254B791638AC7619C3A8B542832A547B19C65B7461C9A32869C18A234B753A52B764C189\
+             B4679C812A53918C325A746BA2B546978C314796183C52BA1C38A5B26794"
+        );
+    }
+    #[test]
+    fn test_jigsaw() {
+        let regions =
+            "111123333412222536411255536441253336741255666744259666747959998747999898777888888";
+        let layout = Layout::<9>::from_regions(regions).unwrap();
+        assert_eq!(
+            solution_with::<9>(
+                "...67........5.....4.....3....8...59.8.......3.............8......2..4....17.....",
+                layout
+            ),
+            "513674928196352874948126735237841659682495317354917286769538142875263491421789563"
+        );
+    }
+    #[test]
+    fn test_unknown_constraint() {
+        let givens = ".".repeat(81);
+        let parse = |extra: &str| {
+            let line = format!("{givens} {extra}");
+            board_from_line::<9>(&line, &Symbols::detect(9, &line), &Options::default()).err()
+        };
+        assert_eq!(
+            parse("cage=1"),
+            Some("unknown constraint: cage".to_string())
+        );
+        // too short for a region map
+        assert_eq!(parse("1112"), Some("unknown constraint: 1112".to_string()));
+        assert_eq!(
+            parse(&"1".repeat(81)),
+            Some("region 1 has 81 fields instead of 9".to_string())
+        );
+    }
+    // puzzles of every kind with the options they need
+    #[test]
+    fn test_solutions() {
+        use Relation::*;
+        let variant = |variant| Options {
+            variants: vec![variant],
+            ..Default::default()
+        };
+        let relations = |relations: &[Relation]| Options {
+            relations: relations.to_vec(),
+            ..Default::default()
+        };
+        let negative = |negative: &[Negative]| Options {
+            negative: negative.to_vec(),
+            ..Default::default()
+        };
+        let innies_outies = Options {
+            innies_outies: true,
+            ..Default::default()
+        };
+        let killer = |cages| format!("{} cages={cages}", ".".repeat(81));
+        let killers = [
+            killer(
+                "gaaaAAUUUgGGAAAKNNcQQbbiMXDFQQQddMMRFCZTdEERRCCZTWESBBJJHHWLOBBVVPeeLOYBVfIILLOYh/\
+                 11,18,29,14,5,1,12,5,30,4,4,11,7,9,11,13,12,16,11,5,20,8,8,30,9,11,22,14,11,9,12,3,\
+                 3,12,5",
+            ),
+            killer(
+                "ZIIYHHUTTIIIJHHQRbWWJJGddRCWWeDGGGXCMNNDDaXXcVAAAELKgcVBBALLKKfVBBOOLKFFSSSOOOPPP/\
+                 6,33,4,18,7,4,13,5,17,2,17,23,11,10,1,10,16,3,10,8,10,17,22,5,18,16,1,23,8,26,16,12,\
+                 13",
+            ),
+        ];
+        let edges =
+            "214879356673125948589436271748593162196247583352681794461752839837914625925368417";
+        let outside =
+            "254367198376189425189542673492736581617895342538214769763921854941658237825473916";
+        let puzzles = [
+            (
+                "6......4....3.............5..74...818..1.....3...2.4......8......1.9.83........5.",
+                variant(Variant::X),
+                "639251748458367912172849365967435281824176593315928476796583124541692837283714659",
+            ),
+            (
+                "..9.5.7.....3.7..21.........1...5......1..3..3..8.................4...2.......89.",
+                variant(Variant::Windoku),
+                "639251748458367912172984635714635289986172354325849176593728461861493527247516893",
+            ),
+            (
+                "63.2......58..........49.....3..46........1.39.......4.1......68....54..5...7..9.",
+                variant(Variant::CentreDot),
+                "639251748458367912172849365783514629245986173961732584317498256896125437524673891",
+            ),
+            (
+                "6....1.4........1........65..7........4.......1..2....3...9......17...8......42..",
+                variant(Variant::Disjoint),
+                "639251748458367912172849365567913824824675193913428657385192476241736589796584231",
+            ),
+            (
+                "6......4........9.........5.......7..........3...4....9...2..8..748......2...9...",
+                relations(&[AntiKnight]),
+                "639251748452687193187394265268935471745168932391742856916423587574816329823579614",
+            ),
+            (
+                ".3.....4........3....9...62..........8.4....1.9...2..62......84..812..57.........",
+                relations(&[AntiKing]),
+                "639251748421678935857943162142567893786439521395812476213795684968124357574386219",
+            ),
+            (
+                ".................6.............2.3.......6........3..........4...........1.......",
+                relations(&[NonConsecutive]),
+                "639751482275384916841962573196428357753196824428573169962835741384617295517249638",
+            ),
+            // the Miracle Sudoku: two givens only
+            (
+                "......................................1............2.............................",
+                relations(&[AntiKnight, AntiKing, NonConsecutive]),
+                "483726159726159483159483726837261594261594837594837261372615948615948372948372615",
+            ),
+            (
+                // Greater-Than signs within the boxes
+                ".................................1..............6...........8..8................. \
+                 edges=><.><.<<<>.<<.><<<.><.<>><.<>.<><>.<<.<><>.<>.<><>.>>.><><.><.><><.<<.></\
+                 <<>>>><><><<<<<><>.........><>>><<<<<>><<><<<.........<><<><>>><>>><<>><",
+                Options::default(),
+                edges,
+            ),
+            (
+                "..........................................................................5...... \
+                 edges=b.bw...ww..b...b.w.wb....b.........b...............................b.b../\
+                 ..w....w.ww..ww....bww.bbwb........w....b..wwwwww.bw..bb...b.w.ww...b.b.",
+                negative(&[Negative::Kropki]),
+                edges,
+            ),
+            (
+                "....................9..6........................6.........5.....................7 \
+                 edges=.v.......x......................x...............x....x...x.xvx........v./\
+                 ............vv..................x..v.........................v..v....x..",
+                negative(&[Negative::Xv]),
+                edges,
+            ),
+            (
+                LINES,
+                Options::default(),
+                "365897241784152639192346587547283196639514872218769354873421965426935718951678423",
+            ),
+            (
+                "..............9.......4......2.3..81..............47...........9......3.......9.. \
+                 sandwich=22,0,20,22,0,17,35,35,2/0,8,8,31,15,17,2,4,0",
+                Options::default(),
+                outside,
+            ),
+            (
+                "2.....1......8......9...6.3............8...4......47....3...........8..7...4.391. \
+                 skyscraper=6,4,3,5,,2,,1,2/5,4,3,2,,,2,,2/2,4,3,3,2,,1,6,3/2,2,3,3,4,,,3,2",
+                Options::default(),
+                outside,
+            ),
+            (
+                "........8....89.........6.3................4......47....3....5.................16 \
+                 littlekiller=r8c1:se:11;r3c1:se:38;r1c2:sw:8;r1c7:se:6;r7c9:nw:28;r8c9:nw:43;\
+                 r1c5:se:31;r9c5:ne:31;r9c4:nw:16;r5c1:ne:31;r1c6:se:19;r9c6:nw:30;r3c9:sw:31;\
+                 r9c3:ne:31",
+                Options::default(),
+                outside,
+            ),
+            (
+                &killers[0],
+                Options::default(),
+                "491827563723569184586134279852941637367285941914376852275613498149758326638492715",
+            ),
+            (
+                &killers[0],
+                innies_outies.clone(),
+                "491827563723569184586134279852941637367285941914376852275613498149758326638492715",
+            ),
+            (
+                &killers[1],
+                Options::default(),
+                "658492731974316582132875694581739426346128957729654813293567148865241379417983265",
+            ),
+            (
+                &killers[1],
+                innies_outies,
+                "658492731974316582132875694581739426346128957729654813293567148865241379417983265",
+            ),
+        ];
+        for (line, options, expected) in puzzles {
+            assert_eq!(solution_of(line, &options), expected, "{line}");
+        }
+    }
+    #[test]
+    fn test_relations() {
+        use Relation::*;
+        let mut board = Board::<9>::default();
+        board.add_relation(NonConsecutive);
+        let centre = Addr::new(4, 4, &board.layout);
+        let edit = board.manipulate(&centre, 4);
+        // next to the 5 the 4 and 6 are gone besides the 5 of the row, a knight's move away nothing
+        let candidates = |x, y| board.candidates_for(&Addr::new(x, y, &board.layout));
+        assert_eq!(candidates(5, 4), 0b111000111);
+        assert_eq!(candidates(6, 5), 0b111111111);
+        board.rollback(edit);
+        assert_eq!(board.banned.iter().filter(|b| **b != 0).count(), 0);
+    }
+    #[test]
+    fn test_lines() {
+        let board =
+            board_from_line::<9>(LINES, &Symbols::detect(9, LINES), &Options::default()).unwrap();
+        assert_eq!(board.constraints.len(), 12);
+    }
+    #[test]
+    fn test_samurai() {
+        let givens =
+            ".3...1......1..3.......8...9.2......9.6...17...9.6.....9......2............36.4...19\
+             2..7....6...8.....57.7.....4..........1.....6.2..7...6...8.49.68..9.....8..5........\
+             ...6.3...73.....2..4.........2...................6......9.............1..2.7........\
+             .....6.5.......3......3...8...2...5............2..83...7..1......2..............245.\
+             ..91...3......7.84..9.....76.........6..3..17...............8....53....2....6.54..9.\
+             8.3.5...........2.7..";
+        let expected =
+            "639251748...158342697458367912...723916485172849365...694758132583416297...365487219\
+             294738156...819263574761592483...472591368346125879456231874956825974631892547639821\
+             917683524731986125743......982674315............467513829............315928764......\
+             984716253167498356271231598746289153278946576432198345672419583142863579...916732458\
+             769125834...537184629358947621...284965137417289365...725693814695371482...861547392\
+             823654917...349821765";
+        let symbols = Symbols::detect(9, givens);
+        let offsets = multi::parse_offsets::<9>("samurai").unwrap();
+        let mut board = MultiBoard::<9>::new(&offsets).unwrap();
+        board.place_givens(givens, &symbols).unwrap();
+        assert!(solve(&mut board, &mut Stats::default()));
+        let solution = board.fields.iter().map(|f| match f {
+            Some(Field::Set(num)) => symbols.get(*num as usize),
+            _ => '.',
+        });
+        assert_eq!(solution.collect::<String>(), expected);
+    }
+    #[test]
+    fn test_killer() {
+        assert!(Board::<25>::default().add_cage(vec![0, 1], 3).is_err());
+    }
+    #[test]
+    fn test_unique() {
+        let options = Options {
+            format: Format::Json,
+            unique: true,
+            ..Options::default()
+        };
+        let two =
+            "2957..8614318659..8761925433874592166123874955492167387635..1899286713541549386..";
+        let outcome = solve_any(two, &options).unwrap();
+        assert_eq!(outcome.status(), Status::Multiple);
+        let one = outcome.solution.unwrap();
+        assert_eq!(solve_any(&one, &options).unwrap().status(), Status::Solved);
+        // r1c9 can only hold the 9 that's already in its column
+        let none = format!("12345678.........9{}", ".".repeat(63));
+        assert!(solve_any(&none, &options).unwrap().solution.is_none());
+        assert!(solve_any("123", &options).is_err());
+        assert!(solve_any(&format!("{two} cages=x"), &options).is_err());
+    }
+    #[test]
+    fn test_limits() {
+        let line =
+            ".....6....59.....82....8....45........3........6..3.54...325..6..................";
+        let symbols = Symbols::detect(9, line);
+        let initial = board_from_line::<9>(line, &symbols, &Options::default()).unwrap();
+        let mut board = initial.clone();
+        let limits = Limits {
+            max_steps: Some(5000),
+            ..Default::default()
+        };
+        let outcome = search(&mut board, &mut Stats::default(), &limits, |_| true);
+        assert!(matches!(outcome, SolveOutcome::Aborted { steps: 5000, .. }));
+        assert_eq!(board.fields, initial.fields);
+        assert!(board.h_free == initial.h_free && board.v_free == initial.v_free);
+        assert!(board.b_free == initial.b_free);
+        let mut open = board.open.clone();
+        open.sort();
+        let mut initial_open = initial.open.clone();
+        initial_open.sort();
+        assert_eq!(open, initial_open);
+
+        let options = Options {
+            format: Format::Json,
+            max_steps: Some(5000),
+            ..Options::default()
+        };
+        let outcome = solve_any(line, &options).unwrap();
+        assert_eq!(outcome.status(), Status::Aborted);
+        assert_eq!(outcome.stats.num_steps, 5000);
+        let options = Options {
+            timeout: Some(Duration::ZERO),
+            ..options
+        };
+        assert_eq!(solve_any(line, &options).unwrap().stats.num_steps, 0);
+    }
+}
@@ -0,0 +1,211 @@
+//! Overlapping N×N grids on a common canvas, like the five grids of a Samurai Sudoku sharing
+//! their corner boxes. A field of the canvas belongs to the rows, columns and boxes of every grid
+//! covering it, and the search runs over all fields at once.
+
+use super::grid::{Grid, Layout, Mask, Symbols};
+use super::{Field, Search};
+use bitset_core::BitSet;
+
+pub struct MultiBoard<const N: usize> {
+    pub width: usize,
+    pub height: usize,
+    // the canvas row by row, None where no grid is
+    pub fields: Vec<Option<Field>>,
+    // canvas indices of the open fields
+    open: Vec<usize>,
+    free: Vec<Mask>,
+    // for every canvas field the houses it is in
+    houses_of: Vec<Vec<usize>>,
+}
+#[derive(Clone, Debug)]
+pub struct MultiEdit {
+    field: usize,
+    num: u8,
+}
+impl<const N: usize> MultiBoard<N> {
+    /// grids with their top left corners at the given (x, y) canvas positions
+    pub fn new(offsets: &[(usize, usize)]) -> Result<MultiBoard<N>, String> {
+        if offsets.is_empty() {
+            return Err("no grids".to_string());
+        }
+        let width = offsets.iter().map(|(x, _)| x + N).max().unwrap();
+        let height = offsets.iter().map(|(_, y)| y + N).max().unwrap();
+        let layout = Layout::<N>::default();
+        let mut houses = Vec::new();
+        for (ox, oy) in offsets {
+            let at = |x: usize, y: usize| (oy + y) * width + ox + x;
+            for i in 0..N {
+                houses.push((0..N).map(|x| at(x, i)).collect::<Vec<_>>());
+                houses.push((0..N).map(|y| at(i, y)).collect());
+            }
+            for b in 0..N {
+                let fields = (0..N * N).filter(|i| layout.region(i % N, i / N) == b);
+                houses.push(fields.map(|i| at(i % N, i / N)).collect());
+            }
+        }
+        // boxes shared by two grids only count once
+        for house in houses.iter_mut() {
+            house.sort();
+        }
+        houses.sort();
+        houses.dedup();
+
+        let mut fields = vec![None; width * height];
+        let mut houses_of = vec![Vec::new(); width * height];
+        for (h, house) in houses.iter().enumerate() {
+            for field in house {
+                fields[*field] = Some(Field::Empty);
+                houses_of[*field].push(h);
+            }
+        }
+        Ok(MultiBoard {
+            width,
+            height,
+            open: (0..width * height)
+                .filter(|i| fields[*i].is_some())
+                .collect(),
+            fields,
+            free: vec![Grid::<N>::ALL; houses.len()],
+            houses_of,
+        })
+    }
+    /// the givens of the whole canvas, row by row, with empty fields (e.g. '.') where no grid is
+    pub fn place_givens(&mut self, line: &str, symbols: &Symbols) -> Result<(), String> {
+        let count = line.chars().count();
+        if count != self.width * self.height {
+            return Err(format!(
+                "{count} givens for a {}x{} canvas",
+                self.width, self.height
+            ));
+        }
+        for (field, c) in line.chars().enumerate() {
+            let Some(num) = symbols.parse(c)? else {
+                continue;
+            };
+            if self.fields[field].is_none() {
+                return Err(format!("given {c} outside the grids"));
+            }
+            let i = self.open.iter().position(|f| *f == field).unwrap();
+            self.open.remove(i);
+            self.manipulate(&field, num);
+        }
+        Ok(())
+    }
+    /// the canvas row by row in the notation of the givens, '.' where open or where no grid is
+    pub fn to_line(&self, symbols: &Symbols) -> String {
+        self.fields
+            .iter()
+            .map(|field| match field {
+                Some(Field::Set(num)) => symbols.get(*num as usize),
+                _ => '.',
+            })
+            .collect()
+    }
+    /// prints the canvas, leaving the space between the grids blank
+    pub fn print(&self, symbols: &Symbols) {
+        for row in self.fields.chunks(self.width) {
+            let line = row
+                .iter()
+                .map(|field| match field {
+                    None => "  ".to_string(),
+                    Some(Field::Empty) => ". ".to_string(),
+                    Some(Field::Set(num)) => format!("{} ", symbols.get(*num as usize)),
+                })
+                .collect::<String>();
+            println!("{}", line.trim_end());
+        }
+    }
+}
+impl<const N: usize> Search for MultiBoard<N> {
+    type Addr = usize;
+    type Edit = MultiEdit;
+    fn open(&self) -> &Vec<usize> {
+        &self.open
+    }
+    fn open_mut(&mut self) -> &mut Vec<usize> {
+        &mut self.open
+    }
+    fn candidates_for(&self, field: &usize) -> Mask {
+        self.houses_of[*field]
+            .iter()
+            .fold(Grid::<N>::ALL, |candidates, h| candidates & self.free[*h])
+    }
+    fn manipulate(&mut self, field: &usize, num: usize) -> MultiEdit {
+        assert!(num < N);
+        for h in &self.houses_of[*field] {
+            self.free[*h].bit_reset(num);
+        }
+        let f = self.fields[*field].as_mut().unwrap();
+        assert_eq!(*f, Field::Empty);
+        *f = Field::Set(num as u8);
+        MultiEdit {
+            field: *field,
+            num: num as u8,
+        }
+    }
+    fn rollback(&mut self, edit: MultiEdit) {
+        for h in &self.houses_of[edit.field] {
+            self.free[*h].bit_set(edit.num as usize);
+        }
+        let f = self.fields[edit.field].as_mut().unwrap();
+        assert_eq!(*f, Field::Set(edit.num));
+        *f = Field::Empty;
+    }
+}
+
+/// parses grid offsets: `x,y` pairs separated by ';', or `samurai` for five grids overlapping
+/// in a corner box of the centre grid each
+pub fn parse_offsets<const N: usize>(spec: &str) -> Result<Vec<(usize, usize)>, String> {
+    if spec == "samurai" {
+        let (dx, dy) = (N - Grid::<N>::BOX_W, N - Grid::<N>::BOX_H);
+        return Ok(vec![
+            (0, 0),
+            (2 * dx, 0),
+            (dx, dy),
+            (0, 2 * dy),
+            (2 * dx, 2 * dy),
+        ]);
+    }
+    spec.split(';')
+        .map(|offset| {
+            offset
+                .split_once(',')
+                .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+                .ok_or_else(|| format!("bad grid offset: {offset}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_multi_board() {
+        let offsets = parse_offsets::<9>("samurai").unwrap();
+        assert_eq!(offsets[4], (12, 12));
+        let board = MultiBoard::<9>::new(&offsets).unwrap();
+        assert_eq!((board.width, board.height), (21, 21));
+        assert_eq!(board.open.len(), 5 * 81 - 4 * 9);
+        // 5 * 27 houses, the 4 shared boxes once
+        assert_eq!(board.free.len(), 5 * 27 - 4);
+        // a corner of the centre grid is in two rows, two columns and one box
+        assert_eq!(board.houses_of[6 * 21 + 6].len(), 5);
+        assert_eq!(board.fields[9], None);
+
+        // two 4×4 grids sharing a box
+        let offsets = parse_offsets::<4>("0,0;2,2").unwrap();
+        let mut board = MultiBoard::<4>::new(&offsets).unwrap();
+        assert_eq!(board.open.len(), 28);
+        let symbols = Symbols::detect(4, "");
+        assert!(board.place_givens("1", &symbols).is_err());
+        let outside = format!("{}1{}", ".".repeat(5), ".".repeat(30));
+        assert!(board.place_givens(&outside, &symbols).is_err());
+        board.place_givens(&".".repeat(36), &symbols).unwrap();
+        let edit = board.manipulate(&(2 * 6 + 2), 0);
+        assert_eq!(board.candidates_for(&(5 * 6 + 2)), 0b1110);
+        assert_eq!(board.candidates_for(&(2 * 6)), 0b1110);
+        board.rollback(edit);
+        assert_eq!(board.candidates_for(&(2 * 6)), 0b1111);
+        assert!(parse_offsets::<9>("0,0;12").is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Clues outside the grid: Sandwich sums of the numbers between the 1 and the N of a row or
+//! column, Skyscraper counts of the fields visible from a side (higher numbers hide lower ones
+//! behind them) and Little Killer sums along diagonals. Every clue is a `Constraint` over its
+//! fields, ordered away from the clue.
+
+use super::grid::Mask;
+use super::lines::{parse_field, range, Constraint};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClueKind {
+    Sandwich,
+    Skyscraper,
+    LittleKiller,
+}
+impl ClueKind {
+    pub fn from_name(name: &str) -> Option<ClueKind> {
+        match name {
+            "sandwich" => Some(ClueKind::Sandwich),
+            "skyscraper" => Some(ClueKind::Skyscraper),
+            "littlekiller" => Some(ClueKind::LittleKiller),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clue {
+    kind: ClueKind,
+    n: usize,
+    clue: usize,
+    fields: Vec<usize>,
+    // the number set in each of the fields
+    nums: Vec<Option<usize>>,
+}
+impl Clue {
+    pub fn new(kind: ClueKind, fields: Vec<usize>, clue: usize, n: usize) -> Result<Clue, String> {
+        let valid = match kind {
+            ClueKind::Sandwich => clue <= (2..n).sum(),
+            ClueKind::Skyscraper => (1..=n).contains(&clue),
+            ClueKind::LittleKiller => (fields.len()..=fields.len() * n).contains(&clue),
+        };
+        if !valid {
+            return Err(format!("impossible {kind:?} clue: {clue}"));
+        }
+        Ok(Clue {
+            kind,
+            n,
+            clue,
+            nums: vec![None; fields.len()],
+            fields,
+        })
+    }
+    fn position(&self, field: usize) -> usize {
+        self.fields.iter().position(|f| *f == field).unwrap()
+    }
+    fn sandwich(&self, p: usize) -> Mask {
+        let n = self.n;
+        // where the 1 and the N can be
+        let positions = |num| match self.nums.iter().position(|set| *set == Some(num)) {
+            Some(i) => vec![i],
+            None => (0..n).filter(|i| self.nums[*i].is_none()).collect(),
+        };
+        let mut allowed = 0;
+        for one in positions(0) {
+            for last in positions(n - 1) {
+                if one == last {
+                    continue;
+                }
+                let between = one.min(last) + 1..one.max(last);
+                let others = between.clone().filter(|i| *i != p);
+                let placed = others
+                    .clone()
+                    .filter_map(|i| self.nums[i])
+                    .map(|num| num as isize + 1)
+                    .sum::<isize>();
+                let open = others.filter(|i| self.nums[*i].is_none()).count() as isize;
+                // the open fields in between hold 2..N-1 each
+                let fits = |sum: isize| (2 * open..=(n as isize - 1) * open).contains(&sum);
+                let left = self.clue as isize - placed;
+                if p == one {
+                    allowed |= if fits(left) { 1 } else { 0 };
+                } else if p == last {
+                    allowed |= if fits(left) { 1 << (n - 1) } else { 0 };
+                } else if between.contains(&p) {
+                    for num in (1..n - 1).filter(|num| fits(left - *num as isize - 1)) {
+                        allowed |= 1 << num;
+                    }
+                } else if fits(left) {
+                    allowed |= range(1, n as isize - 2, n);
+                }
+            }
+        }
+        allowed
+    }
+    fn skyscraper(&self, p: usize) -> Mask {
+        let n = self.n;
+        // to leave room for `clue` visible fields, the p-th is at most the (N - clue + p)-th number
+        let mut allowed = range(0, (n - self.clue + p) as isize, n);
+        // numbers set in front of `p`, if none is open
+        let front = self.nums[..p].iter().copied().collect::<Option<Vec<_>>>();
+        let visible = |nums: &[usize]| {
+            let mut highest = None;
+            nums.iter()
+                .filter(|num| {
+                    let higher = highest.is_none_or(|highest| **num > highest);
+                    if higher {
+                        highest = Some(**num);
+                    }
+                    higher
+                })
+                .count()
+        };
+        if let Some(front) = front {
+            let highest = front.iter().max().copied();
+            if visible(&front) == self.clue {
+                // nothing else may show up
+                allowed &= range(0, highest.unwrap_or(n) as isize - 1, n);
+            } else if highest == Some(n - 1) {
+                // too few visible, and the N hides everything behind it
+                return 0;
+            }
+        }
+        if self
+            .nums
+            .iter()
+            .enumerate()
+            .all(|(i, num)| i == p || num.is_some())
+        {
+            // the last open field: count with every number it can take
+            let mut nums = self
+                .nums
+                .iter()
+                .map(|num| num.unwrap_or(0))
+                .collect::<Vec<_>>();
+            for num in 0..n {
+                nums[p] = num;
+                if visible(&nums) != self.clue {
+                    allowed &= !(1 << num);
+                }
+            }
+        }
+        allowed
+    }
+    fn little_killer(&self, p: usize) -> Mask {
+        let n = self.n as isize;
+        let others = (0..self.nums.len()).filter(|i| *i != p);
+        let placed = others
+            .clone()
+            .filter_map(|i| self.nums[i])
+            .map(|num| num as isize + 1)
+            .sum::<isize>();
+        let open = others.filter(|i| self.nums[*i].is_none()).count() as isize;
+        let left = self.clue as isize - placed;
+        range(left - open * n - 1, left - open - 1, self.n)
+    }
+}
+impl Constraint for Clue {
+    fn fields(&self) -> &[usize] {
+        &self.fields
+    }
+    fn place(&mut self, field: usize, num: usize) {
+        let p = self.position(field);
+        self.nums[p] = Some(num);
+    }
+    fn remove(&mut self, field: usize, num: usize) {
+        let p = self.position(field);
+        assert_eq!(self.nums[p], Some(num));
+        self.nums[p] = None;
+    }
+    fn allowed(&self, field: usize) -> Mask {
+        let p = self.position(field);
+        match self.kind {
+            ClueKind::Sandwich => self.sandwich(p),
+            ClueKind::Skyscraper => self.skyscraper(p),
+            ClueKind::LittleKiller => self.little_killer(p),
+        }
+    }
+    fn clone_box(&self) -> Box<dyn Constraint> {
+        Box::new(self.clone())
+    }
+}
+
+/// parses clues on the border of an N×N grid: up to four sides separated by '/', in the order
+/// top, left, bottom, right, so that puzzles only clued on top and on the left can leave out the
+/// rest. A side lists N clues separated by ',', empty for none, top and bottom from left to
+/// right, left and right from top to bottom.
+pub fn parse_sides(kind: ClueKind, spec: &str, n: usize) -> Result<Vec<Clue>, String> {
+    let mut clues = Vec::new();
+    for (side, spec) in spec.split('/').enumerate() {
+        let lines = spec.split(',').collect::<Vec<_>>();
+        if lines.len() != n || side >= 4 {
+            return Err(format!("bad {kind:?} side: {spec}"));
+        }
+        for (i, clue) in lines.into_iter().enumerate() {
+            if clue.is_empty() {
+                continue;
+            }
+            let clue = clue
+                .parse()
+                .map_err(|_| format!("bad {kind:?} clue: {clue}"))?;
+            let fields = match side {
+                0 => (0..n).map(|y| y * n + i).collect(),
+                1 => (0..n).map(|x| i * n + x).collect(),
+                2 => (0..n).rev().map(|y| y * n + i).collect(),
+                _ => (0..n).rev().map(|x| i * n + x).collect(),
+            };
+            clues.push(Clue::new(kind, fields, clue, n)?);
+        }
+    }
+    Ok(clues)
+}
+
+/// parses Little Killer clues separated by ';', each `<first field>:<direction>:<sum>`, e.g.
+/// `r1c2:se:15`. The diagonal runs from the first field in direction ne, se, sw or nw up to the
+/// border.
+pub fn parse_diagonals(spec: &str, n: usize) -> Result<Vec<Clue>, String> {
+    spec.split(';')
+        .map(|clue| {
+            let mut parts = clue.split(':');
+            let (Some(first), Some(direction), Some(sum), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(format!("bad Little Killer clue: {clue}"));
+            };
+            let first = parse_field(first, n)?;
+            let (dx, dy) = match direction {
+                "ne" => (1, -1),
+                "se" => (1, 1),
+                "sw" => (-1, 1),
+                "nw" => (-1, -1),
+                _ => return Err(format!("bad Little Killer direction: {direction}")),
+            };
+            let sum = sum
+                .parse()
+                .map_err(|_| format!("bad Little Killer sum: {sum}"))?;
+            let (mut x, mut y) = ((first % n) as isize, (first / n) as isize);
+            let mut fields = Vec::new();
+            while (0..n as isize).contains(&x) && (0..n as isize).contains(&y) {
+                fields.push(y as usize * n + x as usize);
+                x += dx;
+                y += dy;
+            }
+            Clue::new(ClueKind::LittleKiller, fields, sum, n)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_clues() {
+        let mut sandwich = Clue::new(ClueKind::Sandwich, (0..9).collect(), 7, 9).unwrap();
+        sandwich.place(0, 0);
+        sandwich.place(3, 8);
+        // 1 and 9 three apart, 7 in the two fields between: 2+5 or 3+4
+        assert_eq!(sandwich.allowed(1), 0b000011110);
+        sandwich.place(2, 2);
+        assert_eq!(sandwich.allowed(1), 0b000001000);
+        assert_eq!(sandwich.allowed(5), 0b011111110);
+        sandwich.remove(2, 2);
+        sandwich.remove(3, 8);
+        // at most three fields of 2 or more between the 1 and the 9
+        assert_eq!(sandwich.allowed(4) & 1 << 8, 1 << 8);
+        assert_eq!(sandwich.allowed(5) & 1 << 8, 0);
+
+        let mut skyscraper = Clue::new(ClueKind::Skyscraper, (0..4).collect(), 3, 4).unwrap();
+        assert_eq!(skyscraper.allowed(0), 0b0011);
+        assert_eq!(skyscraper.allowed(3), 0b1111);
+        skyscraper.place(0, 1);
+        skyscraper.place(1, 3);
+        // 2 and 4 visible, the 4 hides the third one
+        assert_eq!(skyscraper.allowed(2), 0);
+        skyscraper.remove(1, 3);
+        skyscraper.place(1, 0);
+        skyscraper.place(2, 2);
+        assert_eq!(skyscraper.allowed(3), 0b1000);
+
+        let mut diagonal = Clue::new(ClueKind::LittleKiller, vec![2, 10, 18], 20, 9).unwrap();
+        assert_eq!(diagonal.allowed(2), 0b111111110);
+        diagonal.place(10, 8);
+        assert_eq!(diagonal.allowed(2), 0b111111110);
+        diagonal.place(18, 5);
+        assert_eq!(diagonal.allowed(2), 0b000010000);
+
+        assert!(Clue::new(ClueKind::Skyscraper, (0..4).collect(), 5, 4).is_err());
+        assert!(Clue::new(ClueKind::LittleKiller, vec![0, 10], 19, 9).is_err());
+    }
+    #[test]
+    fn test_parse_clues() {
+        let clues = parse_sides(ClueKind::Skyscraper, "1,,,/,2,,/,,,4", 4).unwrap();
+        assert_eq!(clues.len(), 3);
+        assert_eq!(clues[0].fields(), [0, 4, 8, 12]);
+        assert_eq!(clues[1].fields(), [4, 5, 6, 7]);
+        assert_eq!(clues[2].fields(), [15, 11, 7, 3]);
+        assert!(parse_sides(ClueKind::Skyscraper, "1,,", 4).is_err());
+        assert!(parse_sides(ClueKind::Skyscraper, ",,,/,,,/,,,/,,,/,,,", 4).is_err());
+        assert!(parse_sides(ClueKind::Sandwich, "a,,,", 4).is_err());
+
+        let clues = parse_diagonals("r1c2:se:15;r9c1:ne:45", 9).unwrap();
+        assert_eq!(clues[0].fields(), [1, 11, 21, 31, 41, 51, 61, 71]);
+        assert_eq!(clues[1].fields().len(), 9);
+        assert!(parse_diagonals("r1c2:s:15", 9).is_err());
+        assert!(parse_diagonals("r1c2:se", 9).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Variants relating a field to other fields rather than to a house: Anti-Knight and Anti-King
+//! forbid equal numbers a chess knight's or king's move apart, Non-Consecutive forbids numbers
+//! differing by one in orthogonally adjacent fields. The relations marked on single pairs of
+//! fields (Greater-Than, Kropki, XV, see `edges`) work the same way. Setting a field rules out
+//! numbers in the related fields, so the board counts per field and number how many set fields
+//! rule it out.
+
+use super::grid::Mask;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    AntiKnight,
+    AntiKing,
+    NonConsecutive,
+    /// the related field holds a smaller number
+    Less,
+    /// the related field holds a larger number
+    Greater,
+    /// white Kropki dot: the numbers differ by one
+    Consecutive,
+    /// black Kropki dot: one number is twice the other
+    Double,
+    /// the numbers add up to the given sum (X: 10, V: 5)
+    Sum(usize),
+    /// negative black dot
+    NotDouble,
+    /// negative X or V
+    NotSum(usize),
+}
+impl Relation {
+    pub fn from_name(name: &str) -> Result<Relation, String> {
+        match name {
+            "anti-knight" => Ok(Relation::AntiKnight),
+            "anti-king" => Ok(Relation::AntiKing),
+            "non-consecutive" => Ok(Relation::NonConsecutive),
+            _ => Err(format!("unknown variant: {name}")),
+        }
+    }
+    // (dx, dy) of the related fields
+    fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Relation::AntiKnight => &[
+                (1, 2),
+                (2, 1),
+                (2, -1),
+                (1, -2),
+                (-1, -2),
+                (-2, -1),
+                (-2, 1),
+                (-1, 2),
+            ],
+            Relation::AntiKing => &[
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+            ],
+            Relation::NonConsecutive => &[(1, 0), (0, 1), (-1, 0), (0, -1)],
+            // only marked pairs
+            _ => &[],
+        }
+    }
+    /// the relation seen from the other field
+    pub fn reversed(self) -> Relation {
+        match self {
+            Relation::Less => Relation::Greater,
+            Relation::Greater => Relation::Less,
+            relation => relation,
+        }
+    }
+    /// the fields of an N×N grid related to `field` (`y * N + x`)
+    pub fn neighbours<const N: usize>(self, field: usize) -> impl Iterator<Item = usize> {
+        let (x, y) = ((field % N) as isize, (field / N) as isize);
+        self.offsets()
+            .iter()
+            .map(move |(dx, dy)| (x + dx, y + dy))
+            .filter(|(x, y)| (0..N as isize).contains(x) && (0..N as isize).contains(y))
+            .map(|(x, y)| y as usize * N + x as usize)
+    }
+    /// the numbers (0-based) a related field can't hold once a field holds `num`
+    pub fn excluded(self, num: usize, all: Mask) -> Mask {
+        let bit: Mask = 1 << num;
+        // with numbers counted from 1, the double of `num` is at 2 * num + 1, the half at
+        // (num - 1) / 2 for odd `num`, and the complement to `sum` at sum - num - 2
+        let shifted = |by: usize| bit.checked_shl(by as u32).unwrap_or(0);
+        let half = if num.is_multiple_of(2) {
+            0
+        } else {
+            bit >> (num / 2 + 1)
+        };
+        let double = shifted(num + 1) | half;
+        let complement = |sum: usize| sum.checked_sub(num + 2).map_or(0, |other| 1 << other);
+        let excluded = match self {
+            Relation::AntiKnight | Relation::AntiKing => bit,
+            Relation::NonConsecutive => bit << 1 | bit >> 1,
+            Relation::Less => !(bit - 1),
+            Relation::Greater => (bit << 1) - 1,
+            Relation::Consecutive => !(bit << 1 | bit >> 1),
+            Relation::Double => !double,
+            Relation::Sum(sum) => !complement(sum),
+            Relation::NotDouble => double,
+            Relation::NotSum(sum) => complement(sum),
+        };
+        excluded & all
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_relations() {
+        let knight = Relation::AntiKnight.neighbours::<9>(0).collect::<Vec<_>>();
+        assert_eq!(knight, [19, 11]);
+        assert_eq!(Relation::AntiKnight.neighbours::<9>(40).count(), 8);
+        let king = Relation::AntiKing.neighbours::<9>(8).collect::<Vec<_>>();
+        assert_eq!(king, [17, 16, 7]);
+        let adjacent = Relation::NonConsecutive
+            .neighbours::<4>(5)
+            .collect::<Vec<_>>();
+        assert_eq!(adjacent, [6, 9, 4, 1]);
+
+        assert_eq!(Relation::AntiKing.excluded(4, 0x1ff), 0b000010000);
+        assert_eq!(Relation::NonConsecutive.excluded(4, 0x1ff), 0b000101000);
+        assert_eq!(Relation::NonConsecutive.excluded(0, 0x1ff), 0b000000010);
+        assert_eq!(Relation::NonConsecutive.excluded(8, 0x1ff), 0b010000000);
+
+        // a 4 next to the field
+        assert_eq!(Relation::Less.excluded(3, 0x1ff), 0b111111000);
+        assert_eq!(Relation::Greater.excluded(3, 0x1ff), 0b000001111);
+        assert_eq!(Relation::Consecutive.excluded(3, 0x1ff), 0b111101011);
+        assert_eq!(Relation::Double.excluded(3, 0x1ff), 0b101111101);
+        assert_eq!(Relation::NotDouble.excluded(3, 0x1ff), 0b010000010);
+        assert_eq!(Relation::Sum(10).excluded(3, 0x1ff), 0b111011111);
+        assert_eq!(Relation::NotSum(5).excluded(3, 0x1ff), 0b000000001);
+        assert_eq!(Relation::NotSum(5).excluded(4, 0x1ff), 0);
+        assert_eq!(Relation::Double.excluded(8, 0x1ff), 0x1ff);
+        assert_eq!(Relation::Less.reversed(), Relation::Greater);
+    }
+}
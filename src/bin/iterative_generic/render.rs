@@ -0,0 +1,219 @@
+//! Grids drawn with borders around the boxes (or Jigsaw regions), in ASCII or with Unicode
+//! box-drawing characters, optionally with the candidates of every open field as pencilmarks and
+//! with ANSI colours telling givens from solved fields.
+//!
+//! The grid is drawn on a canvas of one character per cell: between every two fields (and around
+//! the grid) there is a column or row for the border, which is blank inside a region. Without
+//! pencilmarks, border rows without any border segment are left out, so the fields of a box stay
+//! close together.
+
+use super::grid::{box_height, Mask, Symbols};
+use std::env::args;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Borders {
+    /// `+`, `-` and `|`
+    Ascii,
+    /// `┌`, `─`, `│` and friends
+    Unicode,
+}
+
+/// How to draw a grid, given as `--grid=ascii|unicode`, `--pencilmarks` and `--color`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Style {
+    pub borders: Borders,
+    /// the candidates of every open field in a mini grid shaped like the boxes
+    pub pencilmarks: bool,
+    /// ANSI colours: givens bold blue, solved fields green
+    pub color: bool,
+}
+impl Style {
+    /// None without any of the options, for the plain rows of numbers
+    pub fn from_args() -> Option<Style> {
+        let borders = args().find_map(|arg| match arg.strip_prefix("--grid=")? {
+            "ascii" => Some(Borders::Ascii),
+            "unicode" => Some(Borders::Unicode),
+            name => panic!("unknown grid style: {name}"),
+        });
+        let pencilmarks = args().any(|arg| arg == "--pencilmarks");
+        let color = args().any(|arg| arg == "--color");
+        (borders.is_some() || pencilmarks || color).then_some(Style {
+            borders: borders.unwrap_or(Borders::Ascii),
+            pencilmarks,
+            color,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    Given(usize),
+    Solved(usize),
+    /// an open field with its candidates
+    Open(Mask),
+}
+
+/// draws an N×N grid of `cells` with the region of every field (`y * N + x`) in `regions`
+pub fn render(
+    n: usize,
+    cells: &[Cell],
+    regions: &[usize],
+    symbols: &Symbols,
+    style: &Style,
+) -> String {
+    // the size of a field on the canvas
+    let (cw, ch) = if style.pencilmarks {
+        (n / box_height(n), box_height(n))
+    } else {
+        (1, 1)
+    };
+    let region = |x: usize, y: usize| regions[y * n + x];
+    // whether there is a border left of field (x, y), or above it. x or y may be N.
+    let vertical = |x: usize, y: usize| x == 0 || x == n || region(x - 1, y) != region(x, y);
+    let horizontal = |x: usize, y: usize| y == 0 || y == n || region(x, y - 1) != region(x, y);
+
+    let width = n * (cw + 1) + 1;
+    let mut canvas = vec![vec![" ".to_string(); width]; n * (ch + 1) + 1];
+    for y in 0..=n {
+        for x in 0..=n {
+            let (cx, cy) = (x * (cw + 1), y * (ch + 1));
+            if y < n && vertical(x, y) {
+                for row in &mut canvas[cy + 1..cy + 1 + ch] {
+                    row[cx] = line(style.borders, false).to_string();
+                }
+            }
+            if x < n && horizontal(x, y) {
+                for c in &mut canvas[cy][cx + 1..cx + 1 + cw] {
+                    *c = line(style.borders, true).to_string();
+                }
+            }
+            let up = y > 0 && vertical(x, y - 1);
+            let down = y < n && vertical(x, y);
+            let left = x > 0 && horizontal(x - 1, y);
+            let right = x < n && horizontal(x, y);
+            canvas[cy][cx] = junction(style.borders, up, right, down, left).to_string();
+            if x < n && y < n {
+                let cell = cells[y * n + x];
+                for (i, c) in field(cell, cw, ch, symbols, style.color)
+                    .into_iter()
+                    .enumerate()
+                {
+                    canvas[cy + 1 + i / cw][cx + 1 + i % cw] = c;
+                }
+            }
+        }
+    }
+    let mut out = String::new();
+    for (y, row) in canvas.iter().enumerate() {
+        let border_row = y % (ch + 1) == 0;
+        if border_row && !style.pencilmarks && row.iter().skip(1).step_by(2).all(|c| c == " ") {
+            continue;
+        }
+        out.push_str(row.concat().trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+// the characters of a field: its number in the middle of the mini grid, or its candidates at
+// their places in it
+fn field(cell: Cell, cw: usize, ch: usize, symbols: &Symbols, color: bool) -> Vec<String> {
+    let paint = |num: usize, code: &str| match color {
+        true => format!("\x1b[{code}m{}\x1b[0m", symbols.get(num)),
+        false => symbols.get(num).to_string(),
+    };
+    let mut chars = vec![" ".to_string(); cw * ch];
+    let middle = ch / 2 * cw + cw / 2;
+    match cell {
+        Cell::Given(num) => chars[middle] = paint(num, "1;34"),
+        Cell::Solved(num) => chars[middle] = paint(num, "32"),
+        Cell::Open(_) if cw * ch == 1 => chars[0] = ".".to_string(),
+        Cell::Open(candidates) => {
+            for (num, c) in chars.iter_mut().enumerate() {
+                if candidates & 1 << num != 0 {
+                    *c = symbols.get(num).to_string();
+                }
+            }
+        }
+    }
+    chars
+}
+
+fn line(borders: Borders, horizontal: bool) -> char {
+    match (borders, horizontal) {
+        (Borders::Ascii, true) => '-',
+        (Borders::Ascii, false) => '|',
+        (Borders::Unicode, true) => '─',
+        (Borders::Unicode, false) => '│',
+    }
+}
+
+// where border lines meet, by the directions they leave in
+fn junction(borders: Borders, up: bool, right: bool, down: bool, left: bool) -> char {
+    let arms = up as usize | (right as usize) << 1 | (down as usize) << 2 | (left as usize) << 3;
+    match borders {
+        Borders::Ascii => match arms {
+            0 => ' ',
+            0b0101 => '|',
+            0b1010 => '-',
+            _ => '+',
+        },
+        Borders::Unicode => " ╵╶└╷│┌├╴┘─┴┐┤┬┼".chars().nth(arms).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_render() {
+        let symbols = Symbols::detect(4, "");
+        let regions = [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3];
+        let mut cells = vec![Cell::Open(0b1111); 16];
+        cells[0] = Cell::Given(0);
+        cells[5] = Cell::Solved(3);
+        cells[6] = Cell::Open(0b0110);
+        let ascii = Style {
+            borders: Borders::Ascii,
+            pencilmarks: false,
+            color: false,
+        };
+        assert_eq!(
+            render(4, &cells, &regions, &symbols, &ascii),
+            "+---+---+\n|1 .|. .|\n|. 4|. .|\n+---+---+\n|. .|. .|\n|. .|. .|\n+---+---+\n"
+        );
+        let unicode = Style {
+            borders: Borders::Unicode,
+            ..ascii
+        };
+        let drawn = render(4, &cells, &regions, &symbols, &unicode);
+        assert_eq!(drawn.lines().next(), Some("┌───┬───┐"));
+        assert_eq!(drawn.lines().nth(3), Some("├───┼───┤"));
+
+        // mini grids two wide and two high
+        let pencilmarks = Style {
+            pencilmarks: true,
+            ..ascii
+        };
+        let drawn = render(4, &cells, &regions, &symbols, &pencilmarks);
+        let lines = drawn.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4 * 3 + 1);
+        assert_eq!(lines[0], "+-----+-----+");
+        assert_eq!(lines[3], "|     |     |");
+        assert_eq!(lines[4], "|12   | 2 12|");
+        assert_eq!(lines[5], "|34  4|3  34|");
+
+        let color = Style {
+            color: true,
+            ..ascii
+        };
+        let drawn = render(4, &cells, &regions, &symbols, &color);
+        assert!(drawn.contains("|\x1b[1;34m1\x1b[0m ."));
+        assert!(drawn.contains("\x1b[32m4\x1b[0m|"));
+
+        // Jigsaw borders
+        let regions = [0, 0, 0, 1, 2, 0, 1, 1, 2, 2, 3, 1, 2, 3, 3, 3];
+        let drawn = render(4, &cells, &regions, &symbols, &ascii);
+        assert_eq!(drawn.lines().nth(2), Some("+-+ +-+ |"));
+    }
+}
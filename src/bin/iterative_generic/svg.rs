@@ -0,0 +1,490 @@
+//! Puzzles drawn as SVG pictures for print: the grid with its box (or Jigsaw region) borders,
+//! givens and solved numbers, pencilmarks, and the decorations of the variants read from the
+//! puzzle line: Killer cages, lines, edge markers and clues outside the grid. PNG images are
+//! rasterized from the SVG by resvg, without any system libraries.
+//!
+//! `draw` as the first argument writes a picture of every puzzle of the file and one of its
+//! solution, see `Drawing`.
+
+use super::edges;
+use super::grid::{box_height, Symbols};
+use super::killer;
+use super::lines::{self, parse_field, Constraint, LineKind};
+use super::relations::Relation;
+use super::render::Cell;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{self, fontdb, PostProcessingSteps, TreeParsing, TreePostProc};
+use std::env::args;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// the size of a field
+const CELL: f32 = 40.0;
+pub const FONT: &str = "DejaVu Sans, Helvetica, Arial, sans-serif";
+
+/// What `draw <options> <file>` writes: `<out>/<name>.svg` for every puzzle and
+/// `<out>/<name>-solution.svg` for its solution, `.png` with `--png`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Drawing {
+    /// `--png`: rasterize instead of writing the SVG
+    pub png: bool,
+    /// `--scale=<factor>`: pixels per SVG unit of the PNG images, 2 by default
+    pub scale: f32,
+    /// `--pencilmarks`: the candidates of the open fields of the puzzle
+    pub pencilmarks: bool,
+    /// `--out=<dir>`, the current directory by default
+    pub out: PathBuf,
+    /// the file name of the current puzzle, without the extension
+    pub name: String,
+}
+impl Drawing {
+    /// None unless the first argument is `draw`
+    pub fn from_args() -> Option<Drawing> {
+        if args().nth(1).as_deref() != Some("draw") {
+            return None;
+        }
+        let arg =
+            |prefix: &str| args().find_map(|arg| arg.strip_prefix(prefix).map(str::to_string));
+        Some(Drawing {
+            png: args().any(|arg| arg == "--png"),
+            scale: arg("--scale=").map_or(2.0, |scale| scale.parse().expect("bad scale")),
+            pencilmarks: args().any(|arg| arg == "--pencilmarks"),
+            out: PathBuf::from(arg("--out=").unwrap_or_else(|| ".".to_string())),
+            name: String::new(),
+        })
+    }
+    /// writes the picture of the current puzzle, its name followed by `suffix`, and prints the
+    /// file name
+    pub fn write(&self, suffix: &str, svg: &str) -> Result<(), String> {
+        let extension = if self.png { "png" } else { "svg" };
+        let path = self.out.join(format!("{}{suffix}.{extension}", self.name));
+        let data = match self.png {
+            true => png(svg, self.scale)?,
+            false => svg.as_bytes().to_vec(),
+        };
+        std::fs::write(&path, data).map_err(|err| format!("{}: {err}", path.display()))?;
+        println!("{}", path.display());
+        Ok(())
+    }
+}
+
+/// draws the N×N grid of `cells` (`y * N + x`) with the region of every field in `regions` and
+/// the decorations of the puzzle `line`
+pub fn svg(
+    n: usize,
+    line: &str,
+    cells: &[Cell],
+    regions: &[usize],
+    symbols: &Symbols,
+    pencilmarks: bool,
+) -> Result<String, String> {
+    let parts = line.split_whitespace().skip(1);
+    // clues outside the grid need a margin of a field
+    let outside = line.split_whitespace().skip(1).any(|part| {
+        ["sandwich=", "skyscraper=", "littlekiller="]
+            .iter()
+            .any(|name| part.starts_with(name))
+    });
+    let margin = if outside { CELL } else { CELL / 4.0 };
+    let size = n as f32 * CELL + 2.0 * margin;
+    let center = |field: usize| {
+        let (x, y) = ((field % n) as f32, (field / n) as f32);
+        (margin + (x + 0.5) * CELL, margin + (y + 0.5) * CELL)
+    };
+
+    let mut svg = vec![
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size:.1}" height="{size:.1}" viewBox="0 0 {size:.1} {size:.1}" font-family="{FONT}">"#
+        ),
+        format!(r#"<rect width="{size:.1}" height="{size:.1}" fill="white"/>"#),
+    ];
+    // lines first, below everything else
+    let mut cages = Vec::new();
+    let mut markers = Vec::new();
+    let mut clues = Vec::new();
+    for part in parts {
+        let (name, spec) = part.split_once('=').unwrap_or(("", part));
+        match name {
+            "cages" => cages = killer::parse_cages(spec, n)?,
+            "edges" => markers = edges::parse_edges(spec, n, &[])?,
+            "thermo" | "arrow" | "palindrome" | "whisper" => {
+                let kind = LineKind::from_name(name).unwrap();
+                for path in lines::parse_lines(kind, spec, n)? {
+                    svg.extend(path_line(kind, path.fields(), &center));
+                }
+            }
+            "sandwich" | "skyscraper" => clues.extend(side_clues(spec, n, margin)),
+            "littlekiller" => clues.extend(diagonal_clues(spec, n, margin)?),
+            _ => {}
+        }
+    }
+    for (fields, sum) in &cages {
+        svg.extend(cage(fields, *sum, n, margin));
+    }
+
+    // thin lines between all fields, thick ones between regions
+    let end = margin + n as f32 * CELL;
+    let mut thin = String::new();
+    for i in 1..n {
+        let at = margin + i as f32 * CELL;
+        thin += &format!("M{at:.1} {margin:.1}V{end:.1}M{margin:.1} {at:.1}H{end:.1}");
+    }
+    svg.push(format!(
+        r##"<path d="{thin}" stroke="#999" stroke-width="1" fill="none"/>"##
+    ));
+    let region = |x: usize, y: usize| regions[y * n + x];
+    let mut thick = String::new();
+    for y in 0..=n {
+        for x in 0..=n {
+            let (left, top) = (margin + x as f32 * CELL, margin + y as f32 * CELL);
+            if y < n && (x == 0 || x == n || region(x - 1, y) != region(x, y)) {
+                thick += &format!("M{left:.1} {top:.1}v{CELL:.1}");
+            }
+            if x < n && (y == 0 || y == n || region(x, y - 1) != region(x, y)) {
+                thick += &format!("M{left:.1} {top:.1}h{CELL:.1}");
+            }
+        }
+    }
+    svg.push(format!(
+        r#"<path d="{thick}" stroke="black" stroke-width="3" stroke-linecap="square" fill="none"/>"#
+    ));
+
+    for (a, b, relation) in markers {
+        svg.push(marker(center(a), center(b), relation));
+    }
+
+    let (cw, ch) = (n / box_height(n), box_height(n));
+    for (field, cell) in cells.iter().enumerate() {
+        let (cx, cy) = center(field);
+        match *cell {
+            Cell::Given(num) => svg.push(text(cx, cy, 0.6 * CELL, "black", symbols.get(num))),
+            Cell::Solved(num) => svg.push(text(cx, cy, 0.6 * CELL, "#1f5fbf", symbols.get(num))),
+            Cell::Open(candidates) if pencilmarks => {
+                // every candidate at its place in a mini grid shaped like the boxes, inside
+                // the outline of a cage
+                let inner = 0.76 * CELL;
+                let (w, h) = (inner / cw as f32, inner / ch as f32);
+                for num in (0..n).filter(|num| candidates & 1 << num != 0) {
+                    let x = cx - inner / 2.0 + ((num % cw) as f32 + 0.5) * w;
+                    let y = cy - inner / 2.0 + ((num / cw) as f32 + 0.5) * h;
+                    svg.push(text(x, y, 0.8 * h.min(w), "#666", symbols.get(num)));
+                }
+            }
+            Cell::Open(_) => {}
+        }
+    }
+    svg.extend(clues);
+    svg.push("</svg>".to_string());
+    Ok(svg.join("\n") + "\n")
+}
+
+// a text centered on (x, y)
+fn text(x: f32, y: f32, size: f32, color: &str, content: impl ToString) -> String {
+    let content = content
+        .to_string()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    // the baseline sits a third of the size below the middle of the digits
+    let baseline = y + 0.35 * size;
+    format!(
+        r#"<text x="{x:.1}" y="{baseline:.1}" font-size="{size:.1}" text-anchor="middle" fill="{color}">{content}</text>"#
+    )
+}
+
+fn points(fields: &[usize], center: &impl Fn(usize) -> (f32, f32)) -> String {
+    let points = fields.iter().map(|field| {
+        let (x, y) = center(*field);
+        format!("{x:.1},{y:.1}")
+    });
+    points.collect::<Vec<_>>().join(" ")
+}
+
+// a thermometer with its bulb, an arrow from its circle, or a grey (palindrome) or green
+// (whisper) line through the centres of the fields
+fn path_line(
+    kind: LineKind,
+    fields: &[usize],
+    center: &impl Fn(usize) -> (f32, f32),
+) -> Vec<String> {
+    let polyline = |color: &str, width: f32, points: String| {
+        format!(
+            r#"<polyline points="{points}" stroke="{color}" stroke-width="{width:.1}" stroke-linecap="round" stroke-linejoin="round" fill="none"/>"#
+        )
+    };
+    let (x, y) = center(fields[0]);
+    match kind {
+        LineKind::Thermo => vec![
+            polyline("#ccc", 0.3 * CELL, points(fields, center)),
+            format!(
+                r##"<circle cx="{x:.1}" cy="{y:.1}" r="{:.1}" fill="#ccc"/>"##,
+                0.4 * CELL
+            ),
+        ],
+        LineKind::Arrow => {
+            let r = 0.4 * CELL;
+            // the shaft starts on the circle, towards the second field
+            let (nx, ny) = center(fields[1]);
+            let length = (nx - x).hypot(ny - y);
+            let start = format!(
+                "{:.1},{:.1} ",
+                x + (nx - x) / length * r,
+                y + (ny - y) / length * r
+            );
+            // the head at the last field, pointing along the last step
+            let (ex, ey) = center(fields[fields.len() - 1]);
+            let (px, py) = center(fields[fields.len() - 2]);
+            let length = (ex - px).hypot(ey - py);
+            let (dx, dy) = (
+                (ex - px) / length * 0.2 * CELL,
+                (ey - py) / length * 0.2 * CELL,
+            );
+            let head = format!(
+                "{:.1},{:.1} {ex:.1},{ey:.1} {:.1},{:.1}",
+                ex - dx - dy,
+                ey - dy + dx,
+                ex - dx + dy,
+                ey - dy - dx
+            );
+            vec![
+                format!(
+                    r##"<circle cx="{x:.1}" cy="{y:.1}" r="{r:.1}" stroke="#777" stroke-width="2" fill="none"/>"##
+                ),
+                polyline("#777", 2.0, start + &points(&fields[1..], center)),
+                polyline("#777", 2.0, head),
+            ]
+        }
+        LineKind::Palindrome => vec![polyline("#aaa", 0.2 * CELL, points(fields, center))],
+        LineKind::Whisper => vec![polyline("#5c5", 0.2 * CELL, points(fields, center))],
+    }
+}
+
+// a dashed outline inside the cage's border, with its sum in the top left corner of its first
+// field
+fn cage(fields: &[usize], sum: usize, n: usize, margin: f32) -> Vec<String> {
+    let inset = 0.1 * CELL;
+    let inside = |field: usize, dx: isize, dy: isize| {
+        let (x, y) = ((field % n) as isize + dx, (field / n) as isize + dy);
+        (0..n as isize).contains(&x)
+            && (0..n as isize).contains(&y)
+            && fields.contains(&(y as usize * n + x as usize))
+    };
+    // how far a side runs past the field's corner: up to the inset if the neighbour along it is
+    // outside the cage, into the next field if the diagonal one is inside, too
+    let overhang = |side: bool, diagonal: bool| match (side, diagonal) {
+        (false, _) => -inset,
+        (true, true) => inset,
+        (true, false) => 0.0,
+    };
+    let mut d = String::new();
+    for &field in fields {
+        let x0 = margin + (field % n) as f32 * CELL;
+        let y0 = margin + (field / n) as f32 * CELL;
+        let (x1, y1) = (x0 + CELL, y0 + CELL);
+        let at = |dx, dy| inside(field, dx, dy);
+        if !at(0, -1) {
+            let (a, b) = (
+                overhang(at(-1, 0), at(-1, -1)),
+                overhang(at(1, 0), at(1, -1)),
+            );
+            d += &format!("M{:.1} {:.1}H{:.1}", x0 - a, y0 + inset, x1 + b);
+        }
+        if !at(0, 1) {
+            let (a, b) = (overhang(at(-1, 0), at(-1, 1)), overhang(at(1, 0), at(1, 1)));
+            d += &format!("M{:.1} {:.1}H{:.1}", x0 - a, y1 - inset, x1 + b);
+        }
+        if !at(-1, 0) {
+            let (a, b) = (
+                overhang(at(0, -1), at(-1, -1)),
+                overhang(at(0, 1), at(-1, 1)),
+            );
+            d += &format!("M{:.1} {:.1}V{:.1}", x0 + inset, y0 - a, y1 + b);
+        }
+        if !at(1, 0) {
+            let (a, b) = (overhang(at(0, -1), at(1, -1)), overhang(at(0, 1), at(1, 1)));
+            d += &format!("M{:.1} {:.1}V{:.1}", x1 - inset, y0 - a, y1 + b);
+        }
+    }
+    let x0 = margin + (fields[0] % n) as f32 * CELL;
+    let y0 = margin + (fields[0] / n) as f32 * CELL;
+    let sum = sum.to_string();
+    vec![
+        format!(
+            r#"<path d="{d}" stroke="black" stroke-width="1" stroke-dasharray="3 2" fill="none"/>"#
+        ),
+        // a white patch under the sum keeps the outline from running through it
+        format!(
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="white"/>"#,
+            x0 + 0.05 * CELL,
+            y0 + 0.05 * CELL,
+            0.15 * CELL * sum.len() as f32 + 0.05 * CELL,
+            0.3 * CELL
+        ),
+        format!(
+            r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" fill="black">{sum}</text>"#,
+            x0 + 0.08 * CELL,
+            y0 + 0.3 * CELL,
+            0.25 * CELL
+        ),
+    ]
+}
+
+// the marker on the edge between the centres `a` and `b`, `relation` being that of `b`'s number
+// to `a`'s
+fn marker(a: (f32, f32), b: (f32, f32), relation: Relation) -> String {
+    let (mx, my) = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+    let dot = |fill: &str| {
+        format!(
+            r#"<circle cx="{mx:.1}" cy="{my:.1}" r="{:.1}" stroke="black" stroke-width="1.5" fill="{fill}"/>"#,
+            0.12 * CELL
+        )
+    };
+    // a chevron pointing at the smaller number
+    let chevron = |towards: f32| {
+        let (dx, dy) = ((b.0 - a.0) / CELL * towards, (b.1 - a.1) / CELL * towards);
+        let (tip, wing) = (0.1 * CELL, 0.15 * CELL);
+        format!(
+            r#"<polyline points="{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}" stroke="black" stroke-width="2" fill="none"/>"#,
+            mx - tip * dx + wing * dy,
+            my - tip * dy - wing * dx,
+            mx + tip * dx,
+            my + tip * dy,
+            mx - tip * dx - wing * dy,
+            my - tip * dy + wing * dx
+        )
+    };
+    match relation {
+        Relation::Consecutive => dot("white"),
+        Relation::Double => dot("black"),
+        Relation::Greater => chevron(-1.0),
+        Relation::Less => chevron(1.0),
+        Relation::Sum(sum) => {
+            let letter = if sum == 10 { "X" } else { "V" };
+            text(mx, my, 0.35 * CELL, "black", letter)
+        }
+        _ => String::new(),
+    }
+}
+
+// Sandwich or Skyscraper clues next to the grid, on the sides top, left, bottom, right
+fn side_clues(spec: &str, n: usize, margin: f32) -> Vec<String> {
+    let mut clues = Vec::new();
+    let far = margin + n as f32 * CELL + margin / 2.0;
+    for (side, spec) in spec.split('/').enumerate() {
+        for (i, clue) in spec.split(',').enumerate().filter(|(_, c)| !c.is_empty()) {
+            let along = margin + (i as f32 + 0.5) * CELL;
+            let (x, y) = match side {
+                0 => (along, margin / 2.0),
+                1 => (margin / 2.0, along),
+                2 => (along, far),
+                _ => (far, along),
+            };
+            clues.push(text(x, y, 0.45 * CELL, "black", clue));
+        }
+    }
+    clues
+}
+
+// Little Killer sums one step before the first field of their diagonal, with an arrow along it
+fn diagonal_clues(spec: &str, n: usize, margin: f32) -> Result<Vec<String>, String> {
+    let mut clues = Vec::new();
+    for clue in spec.split(';') {
+        let mut parts = clue.split(':');
+        let (Some(first), Some(direction), Some(sum)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("bad Little Killer clue: {clue}"));
+        };
+        let first = parse_field(first, n)?;
+        let (dx, dy) = match direction {
+            "ne" => (1.0, -1.0),
+            "se" => (1.0, 1.0),
+            "sw" => (-1.0, 1.0),
+            "nw" => (-1.0, -1.0),
+            _ => return Err(format!("bad Little Killer direction: {direction}")),
+        };
+        let x = margin + ((first % n) as f32 + 0.5 - dx) * CELL;
+        let y = margin + ((first / n) as f32 + 0.5 - dy) * CELL;
+        clues.push(text(x, y, 0.35 * CELL, "black", sum));
+        let (sx, sy) = (x + 0.25 * dx * CELL, y + 0.25 * dy * CELL);
+        let (ex, ey) = (x + 0.45 * dx * CELL, y + 0.45 * dy * CELL);
+        let head = 0.08 * CELL;
+        clues.push(format!(
+            r#"<path d="M{sx:.1} {sy:.1}L{ex:.1} {ey:.1}M{:.1} {ey:.1}L{ex:.1} {ey:.1}L{ex:.1} {:.1}" stroke="black" stroke-width="1.5" fill="none"/>"#,
+            ex - head * dx,
+            ey - head * dy
+        ));
+    }
+    Ok(clues)
+}
+
+/// parses an SVG picture, with its texts turned into paths in the fonts of the system
+pub fn tree(svg: &str) -> Result<usvg::Tree, String> {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    let fonts = FONTS.get_or_init(|| {
+        let mut fonts = fontdb::Database::new();
+        fonts.load_system_fonts();
+        fonts
+    });
+    let mut tree =
+        usvg::Tree::from_str(svg, &usvg::Options::default()).map_err(|err| err.to_string())?;
+    tree.postprocess(PostProcessingSteps::default(), fonts);
+    Ok(tree)
+}
+
+/// rasterizes an SVG picture into a PNG image, `scale` pixels per unit
+pub fn png(svg: &str, scale: f32) -> Result<Vec<u8>, String> {
+    let tree = tree(svg)?;
+    let (width, height) = (tree.size.width() * scale, tree.size.height() * scale);
+    let mut pixmap = Pixmap::new(width.ceil() as u32, height.ceil() as u32)
+        .ok_or("empty picture".to_string())?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_svg() {
+        let symbols = Symbols::detect(4, "");
+        let regions = [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3];
+        let mut cells = vec![Cell::Open(0b0110); 16];
+        cells[0] = Cell::Given(0);
+        cells[5] = Cell::Solved(3);
+        let line = "1............... cages=aab.aab.......cc/6,3,7 thermo=r4c1-r4c2 \
+                    edges=..w.....<.../.....x......";
+        let svg = svg(4, line, &cells, &regions, &symbols, false).unwrap();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // 4 fields and a quarter field of margin on either side
+        assert!(svg.contains(r#"width="180.0""#));
+        assert!(svg.contains(">1</text>") && svg.contains(r##"fill="#1f5fbf">4</text>"##));
+        // cage sums, the bulb, a white dot, a chevron and an X
+        assert!(svg.contains(">6</text>") && svg.contains(">7</text>"));
+        assert!(svg.contains(r##"r="16.0" fill="#ccc""##));
+        assert!(svg.contains(r#"r="4.8""#) && svg.contains("<polyline"));
+        assert!(svg.contains(">X</text>"));
+        assert!(!svg.contains(">2</text>"));
+
+        let pencilmarks = super::svg(4, "1...............", &cells, &regions, &symbols, true);
+        assert_eq!(pencilmarks.unwrap().matches(">2</text>").count(), 14);
+        // Sandwich clues need a margin
+        let sandwich = super::svg(4, "1... sandwich=,5,,", &cells, &regions, &symbols, false);
+        let sandwich = sandwich.unwrap();
+        assert!(sandwich.contains(r#"width="240.0""#) && sandwich.contains(">5</text>"));
+        assert!(super::svg(4, "1... cages=a/1", &cells, &regions, &symbols, false).is_err());
+    }
+    #[test]
+    fn test_png() {
+        let cells = vec![Cell::Given(1); 16];
+        let regions = [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 3, 3, 2, 2, 3, 3];
+        let svg = svg(4, "2222", &cells, &regions, &Symbols::detect(4, ""), false).unwrap();
+        let png = png(&svg, 2.0).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        // width and height in the IHDR chunk
+        assert_eq!(png[16..24], [0, 0, 1, 104, 0, 0, 1, 104]);
+    }
+}
@@ -2,6 +2,7 @@ use bitset_core::BitSet;
 use std::env::args;
 use std::fs::File;
 use std::io::{self, BufRead};
+use std::time::{Duration, Instant};
 
 const STACK_SIZE: usize = 9 * 9;
 const FIELD_UNDEFINED: u8 = u8::MAX;
@@ -86,6 +87,52 @@ const TRAILING_ZEROS: [u8; 256] = [
     4,0,1,0,2,0,1,0,3,0,1,0,2,0,1,0,
 ];
 
+/// Bounds on `solve`. The default is unbounded.
+#[derive(Clone, Debug, Default)]
+struct Limits {
+    max_steps: Option<usize>,
+    deadline: Option<Instant>,
+}
+impl Limits {
+    // the deadline is only checked every this many steps
+    const CHECK_INTERVAL: usize = 1024;
+
+    /// parses `--max-steps=<n>` and `--timeout=<ms>` from the command line. The deadline is
+    /// relative to the time of the call.
+    fn from_args() -> Limits {
+        let arg = |prefix: &str| {
+            args().find_map(|arg| {
+                arg.strip_prefix(prefix)
+                    .map(|v| v.parse::<u64>().expect("bad limit"))
+            })
+        };
+        Limits {
+            max_steps: arg("--max-steps=").map(|steps| steps as usize),
+            deadline: arg("--timeout=").map(|ms| Instant::now() + Duration::from_millis(ms)),
+        }
+    }
+    fn exceeded(&self, num_steps: usize) -> bool {
+        if self.max_steps.is_some_and(|max| num_steps >= max) {
+            return true;
+        }
+        num_steps.is_multiple_of(Self::CHECK_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SolveOutcome {
+    Solved,
+    Unsolvable,
+    /// a limit was hit. The board is back in the state it was in before `solve`.
+    Aborted {
+        steps: usize,
+        depth: usize,
+    },
+}
+
 #[derive(Clone)]
 struct Board {
     open: Vec<u8>,
@@ -110,7 +157,15 @@ impl Default for Board {
 impl Board {
     pub fn from_line(line: &str) -> Board {
         let mut board = Board::default();
-        let mut line = line.chars();
+        let givens = line.split_whitespace().next().unwrap_or_default();
+        let len = givens.chars().count();
+        assert!(len == 9 * 9, "{len} fields instead of 81");
+        // region maps and the other constraints are only solved by iterative_generic
+        if let Some(part) = line.split_whitespace().nth(1) {
+            let key = part.split_once('=').map_or("regions", |(key, _)| key);
+            panic!("unsupported constraint: {key}");
+        }
+        let mut line = givens.chars();
         for i in 0..9 {
             for j in 0..9 {
                 let c = line.next().expect("line ended early");
@@ -171,7 +226,7 @@ impl Board {
             println!();
         }
     }
-    fn solve(&mut self) -> bool {
+    fn solve(&mut self, limits: &Limits) -> SolveOutcome {
         let mut candidates_stack = [CANDIDATES_UNDEFINED; STACK_SIZE];
         let mut num_stack = [0u8; STACK_SIZE];
         let mut field_stack = [FIELD_UNDEFINED; STACK_SIZE];
@@ -181,6 +236,22 @@ impl Board {
         let mut num_steps: usize = 0;
 
         loop {
+            if limits.exceeded(num_steps) {
+                // undo the fields set so far. The top frame only has one if it was entered by
+                // backtracking.
+                let mut set = stack_ptr;
+                if candidates_stack[stack_ptr] != CANDIDATES_UNDEFINED {
+                    set += 1;
+                }
+                for field in field_stack[..set].iter().rev() {
+                    self.clear_field(*field);
+                    self.open.push(*field);
+                }
+                return SolveOutcome::Aborted {
+                    steps: num_steps,
+                    depth: stack_ptr + 1,
+                };
+            }
             max_depth = max_depth.max(stack_ptr + 1);
             num_steps += 1;
             let cur_candidates = &mut candidates_stack[stack_ptr];
@@ -191,7 +262,7 @@ impl Board {
                 if self.open.is_empty() {
                     self.print();
                     println!("max depth: {}, steps: {}", max_depth, num_steps);
-                    return true;
+                    return SolveOutcome::Solved;
                 }
                 *cur_candidates = 0u16;
                 // let mut min_candidates = 0u16;
@@ -232,16 +303,26 @@ impl Board {
                 field_stack[stack_ptr] = FIELD_UNDEFINED;
             } else {
                 // unsolvable -> return / backtrack
-                stack_ptr -= 1;
                 self.open.push(*cur_field);
+                if stack_ptr == 0 {
+                    // nothing left to try for the first field
+                    return SolveOutcome::Unsolvable;
+                }
+                stack_ptr -= 1;
             }
         }
     }
 }
 
 fn main() {
+    // other sizes, box shapes and extra houses are only solved by iterative_generic
+    let generic_only = ["--size=", "--box=", "--variant="];
+    if let Some(arg) = args().find(|arg| generic_only.iter().any(|o| arg.starts_with(o))) {
+        println!("{arg} needs iterative_generic");
+        return;
+    }
     let args = args();
-    if args.len() != 2 {
+    if args.len() < 2 {
         println!("missing filename");
         return;
     }
@@ -255,12 +336,16 @@ fn main() {
         let mut board = Board::from_line(&line[..]);
         println!("=========================\nsolving:\n");
         board.print();
-        let solved = board.solve();
+        // each puzzle gets the full timeout
+        let solved = board.solve(&Limits::from_args());
         match solved {
-            true => {
+            SolveOutcome::Solved => {
                 println!("solved.");
             }
-            false => println!("unsolvable"),
+            SolveOutcome::Unsolvable => println!("unsolvable"),
+            SolveOutcome::Aborted { steps, depth } => {
+                println!("aborted after {steps} steps at depth {depth}")
+            }
         }
     }
     println!("end");
@@ -278,3 +363,52 @@ fn gen_trailing_zeros() {
         .collect::<Vec<_>>();
     println!("{:?}", out);
 }
+#[test]
+fn test_limits() {
+    let initial = Board::from_line(
+        ".....6....59.....82....8....45........3........6..3.54...325..6..................",
+    );
+    let mut board = initial.clone();
+    let limits = Limits {
+        max_steps: Some(5000),
+        ..Default::default()
+    };
+    let outcome = board.solve(&limits);
+    assert!(matches!(outcome, SolveOutcome::Aborted { steps: 5000, .. }));
+    assert_eq!(board.fields, initial.fields);
+    assert!(board.h_free == initial.h_free && board.v_free == initial.v_free);
+    assert!(board.b_free == initial.b_free);
+    let mut open = board.open.clone();
+    open.sort();
+    let mut initial_open = initial.open.clone();
+    initial_open.sort();
+    assert_eq!(open, initial_open);
+    // the deadline is hit in the first check or after a multiple of the check interval
+    let mut board = initial.clone();
+    let limits = Limits {
+        deadline: Some(Instant::now() + Duration::from_millis(1)),
+        ..Default::default()
+    };
+    let SolveOutcome::Aborted { steps, .. } = board.solve(&limits) else {
+        panic!("solved within the deadline");
+    };
+    assert!(steps.is_multiple_of(Limits::CHECK_INTERVAL));
+    assert_eq!(board.fields, initial.fields);
+    assert!(board.h_free == initial.h_free && board.v_free == initial.v_free);
+    assert!(board.b_free == initial.b_free);
+    let mut open = board.open.clone();
+    open.sort();
+    let mut initial_open = initial.open.clone();
+    initial_open.sort();
+    assert_eq!(open, initial_open);
+}
+#[test]
+#[should_panic(expected = "36 fields instead of 81")]
+fn test_other_sizes() {
+    Board::from_line("51.........216......4..5.....16..25.");
+}
+#[test]
+#[should_panic(expected = "unsupported constraint: regions")]
+fn test_constraints() {
+    Board::from_line("9......8....17...9..............54.1......79....4...2......2...6..93.....8......7 111333333111222223144552263147522663147555866447558869477858669447888869777999999");
+}